@@ -0,0 +1,19 @@
+//! `PacketOptions::decode` must not panic, and whatever it accepts must encode back to the same options.
+#![no_main]
+
+use green_engine::protocol::{PacketOptions, RawData};
+use libfuzzer_sys::fuzz_target;
+
+fn check(data: RawData) {
+    let binary = data.is_binary();
+    if let Ok(options) = PacketOptions::decode(data) {
+        assert_eq!(PacketOptions::decode(options.encode(binary)), Ok(options));
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    check(RawData::Binary(data.to_vec()));
+    if let Ok(text) = std::str::from_utf8(data) {
+        check(RawData::Text(text.to_string()));
+    }
+});
@@ -0,0 +1,12 @@
+//! `Packet::decode` must reject malformed input without panicking, in both encodings.
+#![no_main]
+
+use green_engine::protocol::{Packet, RawData};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Packet::decode(RawData::Binary(data.to_vec()));
+    if let Ok(text) = std::str::from_utf8(data) {
+        let _ = Packet::decode(RawData::Text(text.to_string()));
+    }
+});
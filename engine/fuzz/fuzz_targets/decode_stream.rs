@@ -0,0 +1,12 @@
+//! `PacketDecoderStream` must not panic on any byte stream, however it is split into chunks.
+#![no_main]
+
+use futures::executor::block_on_stream;
+use futures::stream;
+use green_engine::protocol::PacketDecoderStream;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|chunks: Vec<Vec<u8>>| {
+    let decoder = PacketDecoderStream::new(stream::iter(chunks));
+    for _ in block_on_stream(decoder) {}
+});
@@ -0,0 +1,59 @@
+//! Every valid packet must decode to itself after encoding, as a packet in both encodings,
+//! in a payload of either encoding, and as a frame on a stream split at arbitrary points.
+#![no_main]
+
+use arbitrary::{Arbitrary, Unstructured};
+use futures::executor::block_on_stream;
+use futures::stream;
+use green_engine::protocol::{Packet, PacketDecoderStream, PacketEncoderStream};
+use libfuzzer_sys::{arbitrary, fuzz_target};
+
+/// Packets to encode, and the sizes to split their stream frames at.
+#[derive(Debug)]
+struct Input {
+    packets: Vec<Packet>,
+    splits: Vec<usize>,
+}
+
+impl<'a> Arbitrary<'a> for Input {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self {
+            packets: Vec::arbitrary(u)?,
+            splits: Vec::<u16>::arbitrary(u)?.into_iter().map(|split| split as usize + 1).collect(),
+        })
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let Input { packets, splits } = input;
+
+    for packet in &packets {
+        for binary in [false, true] {
+            let decoded = Packet::decode(packet.clone().encode(binary));
+            assert_eq!(decoded.as_ref(), Ok(packet), "binary: {}", binary);
+        }
+    }
+
+    for binary in [false, true] {
+        let payload = Packet::encode_payload(packets.clone(), binary);
+        assert_eq!(Packet::decode_payload(payload), Ok(packets.clone()), "binary: {}", binary);
+    }
+
+    let bytes: Vec<u8> = block_on_stream(PacketEncoderStream::new(stream::iter(packets.clone())))
+        .flatten()
+        .collect();
+    let mut chunks = Vec::new();
+    let mut rest = bytes.as_slice();
+    for split in splits.into_iter().chain(std::iter::repeat(usize::MAX)) {
+        if rest.is_empty() {
+            break;
+        }
+        let (chunk, tail) = rest.split_at(split.min(rest.len()));
+        chunks.push(chunk.to_vec());
+        rest = tail;
+    }
+    let decoded: Vec<_> = block_on_stream(PacketDecoderStream::new(stream::iter(chunks)))
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(decoded, packets);
+});
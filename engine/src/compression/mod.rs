@@ -0,0 +1,247 @@
+//! Packet data compression: deflate, gzip, zstd and brotli, with an optional shared zstd dictionary.
+//!
+//! The server lists the algorithms it accepts in the handshake. It compresses with the first of them the client
+//! offered in its handshake request, and sends uncompressed when the client offered none, as over unix and QUIC;
+//! the client compresses with the first listed algorithm it also accepts.
+//! Every compressed packet names its algorithm in the options header; a packet naming one its receiver does not
+//! accept is rejected.
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use brotli::enc::BrotliEncoderParams;
+use flate2::read::{DeflateDecoder, GzDecoder};
+use flate2::write::{DeflateEncoder, GzEncoder};
+use zstd::dict::{DecoderDictionary, EncoderDictionary};
+
+use crate::protocol::{Compression, DecodingError, Packet, PacketOptions, RawData, MAX_PACKET_SIZE};
+
+/// zstd level used for every packet; low levels suit short messages.
+const ZSTD_LEVEL: i32 = 3;
+/// Brotli quality and window size (log2) used for every packet.
+const BROTLI_QUALITY: i32 = 5;
+const BROTLI_WINDOW: i32 = 22;
+/// Internal buffer size for the brotli reader.
+const BROTLI_BUFFER: usize = 4096;
+
+/// Marks whether compressed data was text or binary before compression.
+const TEXT_DATA: u8 = 0;
+const BINARY_DATA: u8 = 1;
+
+/// Compression settings for a session.
+#[derive(Debug, Clone)]
+pub struct CompressionConfig {
+    /// Accepted algorithms, most preferred first.
+    algorithms: Vec<Compression>,
+    /// Packet data smaller than this many bytes is sent uncompressed.
+    threshold: usize,
+    /// Shared zstd dictionary, prepared once and used by every session.
+    dictionary: Option<Arc<Dictionary>>,
+}
+
+/// A zstd dictionary prepared for compression and decompression.
+struct Dictionary {
+    id: u32,
+    encoder: EncoderDictionary<'static>,
+    decoder: DecoderDictionary<'static>,
+}
+
+impl fmt::Debug for Dictionary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dictionary").field("id", &self.id).finish_non_exhaustive()
+    }
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            algorithms: Compression::ALL.to_vec(),
+            threshold: 64,
+            dictionary: None,
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// Returns the accepted algorithms, most preferred first.
+    pub fn algorithms(&self) -> &[Compression] {
+        &self.algorithms
+    }
+
+    /// Sets the accepted algorithms, most preferred first.
+    pub fn with_algorithms(mut self, algorithms: Vec<Compression>) -> Self {
+        self.algorithms = algorithms;
+        self
+    }
+
+    /// Returns the size below which packet data is sent uncompressed.
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Sets the size below which packet data is sent uncompressed.
+    pub fn with_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Returns the id of the zstd dictionary, if any.
+    pub fn dictionary_id(&self) -> Option<u32> {
+        self.dictionary.as_ref().map(|dictionary| dictionary.id)
+    }
+
+    /// Uses a shared dictionary for zstd, advertised to clients under `id`.
+    /// Small, repetitive messages such as short JSON compress far better with one.
+    /// The dictionary is prepared here, once, and every session shares it; clients need the same bytes and id.
+    pub fn with_zstd_dictionary(mut self, id: u32, dictionary: &[u8]) -> Self {
+        self.dictionary = Some(Arc::new(Dictionary {
+            id,
+            encoder: EncoderDictionary::copy(dictionary, ZSTD_LEVEL),
+            decoder: DecoderDictionary::copy(dictionary),
+        }));
+        self
+    }
+
+    /// Returns whether `compression` is accepted with the peer's zstd dictionary `dictionary`.
+    /// zstd is only usable when both sides hold the same dictionary, or neither has one.
+    pub fn accepts(&self, compression: Compression, dictionary: Option<u32>) -> bool {
+        self.algorithms.contains(&compression)
+            && (compression != Compression::Zstd || dictionary == self.dictionary_id())
+    }
+
+    /// Returns these settings limited to the algorithms a peer offered, or `None` if there are none in common.
+    pub fn negotiate(&self, offered: &[Compression], dictionary: Option<u32>) -> Option<Self> {
+        let algorithms: Vec<_> = self.algorithms.iter()
+            .copied()
+            .filter(|&compression| offered.contains(&compression) && self.accepts(compression, dictionary))
+            .collect();
+        match algorithms.is_empty() {
+            true => None,
+            false => Some(self.clone().with_algorithms(algorithms)),
+        }
+    }
+
+    /// Compresses a packet's data with `compression`.
+    /// Packets without data, below the threshold, already compressed, or that would not shrink are returned as they are.
+    pub fn compress(&self, compression: Compression, packet: Packet) -> Packet {
+        let options = packet.options().copied().unwrap_or_default();
+        let (kind, data) = match packet.data() {
+            _ if options.compress() => return packet,
+            Some(RawData::Text(text)) => (TEXT_DATA, text.as_bytes()),
+            Some(RawData::Binary(binary)) => (BINARY_DATA, binary.as_slice()),
+            None => return packet,
+        };
+        if data.len() < self.threshold {
+            return packet;
+        }
+        let mut compressed = vec![kind];
+        if self.encode(compression, data, &mut compressed).is_err() || compressed.len() >= data.len() {
+            return packet;
+        }
+
+        let mut compressed_packet = Packet::new(packet._type().clone());
+        compressed_packet.with_options(options.with_compression(compression));
+        match compressed_packet.with_data(RawData::Binary(compressed)) {
+            Ok(()) => compressed_packet,
+            Err(_) => packet,
+        }
+    }
+
+    /// Restores a compressed packet's data. Packets that are not compressed are returned as they are.
+    /// Packets compressed with an algorithm outside these settings, data that does not decompress,
+    /// or data that decompresses past `MAX_PACKET_SIZE` are rejected.
+    pub fn decompress(&self, packet: Packet) -> Result<Packet, DecodingError> {
+        let options = packet.options().copied().unwrap_or_default();
+        let compression = match options.compression() {
+            Some(compression) => compression,
+            None => return Ok(packet),
+        };
+        if !self.algorithms.contains(&compression) {
+            return Err(DecodingError::InvalidFormat);
+        }
+        let (kind, compressed) = match packet.data() {
+            Some(RawData::Binary(binary)) => binary.split_first().ok_or(DecodingError::MissingField)?,
+            _ => return Err(DecodingError::InvalidFormat),
+        };
+        let data = self.decode(compression, compressed)
+            .map_err(|_| DecodingError::InvalidFormat)?;
+        let data = match *kind {
+            TEXT_DATA => RawData::Text(String::from_utf8(data).map_err(|_| DecodingError::InvalidFormat)?),
+            BINARY_DATA => RawData::Binary(data),
+            _ => return Err(DecodingError::InvalidFormat),
+        };
+
+        let mut decompressed = Packet::new(packet._type().clone());
+        let signature = options.signature().copied();
+        let mut options = PacketOptions::new(None, options.encrypt(), options.sequence(), options.total_chunks())
+            .map_err(DecodingError::Packet)?;
+        if let Some(signature) = signature {
+            options = options.with_signature(signature);
+        }
+        if options != PacketOptions::default() {
+            decompressed.with_options(options);
+        }
+        decompressed.with_data(data).map_err(DecodingError::Packet)?;
+        Ok(decompressed)
+    }
+
+    fn encode(&self, compression: Compression, data: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        match compression {
+            Compression::Deflate => {
+                let mut encoder = DeflateEncoder::new(out, flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()?;
+            }
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(out, flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()?;
+            }
+            Compression::Zstd => {
+                let compressed = match &self.dictionary {
+                    Some(dictionary) => zstd::bulk::Compressor::with_prepared_dictionary(&dictionary.encoder)?
+                        .compress(data)?,
+                    None => zstd::bulk::compress(data, ZSTD_LEVEL)?,
+                };
+                out.extend_from_slice(&compressed);
+            }
+            Compression::Brotli => {
+                // Unlike `CompressorWriter`, which finishes the stream on drop, this reports a failed finish.
+                let params = BrotliEncoderParams {
+                    quality: BROTLI_QUALITY,
+                    lgwin: BROTLI_WINDOW,
+                    ..BrotliEncoderParams::default()
+                };
+                brotli::BrotliCompress(&mut &data[..], out, &params)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn decode(&self, compression: Compression, data: &[u8]) -> io::Result<Vec<u8>> {
+        match compression {
+            Compression::Deflate => read_limited(DeflateDecoder::new(data)),
+            Compression::Gzip => read_limited(GzDecoder::new(data)),
+            Compression::Zstd => match &self.dictionary {
+                Some(dictionary) => zstd::bulk::Decompressor::with_prepared_dictionary(&dictionary.decoder)?
+                    .decompress(data, MAX_PACKET_SIZE),
+                None => zstd::bulk::decompress(data, MAX_PACKET_SIZE),
+            },
+            Compression::Brotli => read_limited(brotli::Decompressor::new(data, BROTLI_BUFFER)),
+        }
+    }
+}
+
+/// Reads a decoder to the end, failing once the output passes `MAX_PACKET_SIZE`.
+fn read_limited<R: Read>(reader: R) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader.take(MAX_PACKET_SIZE as u64 + 1).read_to_end(&mut data)?;
+    if data.len() > MAX_PACKET_SIZE {
+        return Err(io::ErrorKind::InvalidData.into());
+    }
+    Ok(data)
+}
@@ -0,0 +1,113 @@
+use std::io::Write;
+
+use crate::compression::CompressionConfig;
+use crate::protocol::{Compression, DecodingError, Packet, PacketOptions, PacketType, RawData, MAX_PACKET_SIZE};
+use crate::protocol::tests::message;
+
+fn chatty_json() -> String {
+    r#"{"event":"position","x":10,"y":20},"#.repeat(20)
+}
+
+#[test]
+fn every_algorithm_round_trips_text_and_binary() {
+    let config = CompressionConfig::default();
+    for compression in Compression::ALL {
+        for data in [RawData::Text(chatty_json()), RawData::Binary(chatty_json().into_bytes())] {
+            let compressed = config.compress(compression, message(data.clone()));
+            assert_eq!(compressed.options().and_then(PacketOptions::compression), Some(compression));
+            assert!(matches!(compressed.data(), Some(RawData::Binary(binary)) if binary.len() < chatty_json().len()));
+
+            let restored = config.decompress(compressed).unwrap();
+            assert_eq!(restored, message(data), "{}", compression.name());
+        }
+    }
+}
+
+#[test]
+fn compressed_packets_survive_encoding() {
+    let config = CompressionConfig::default();
+    let compressed = config.compress(Compression::Brotli, message(RawData::Text(chatty_json())));
+    for encoded in [compressed.clone().encode(true), compressed.encode(false)] {
+        let decoded = Packet::decode(encoded).unwrap();
+        assert_eq!(config.decompress(decoded).unwrap(), message(RawData::Text(chatty_json())));
+    }
+}
+
+#[test]
+fn small_and_incompressible_data_is_left_alone() {
+    let config = CompressionConfig::default().with_threshold(16);
+    let short = message(RawData::Text("hello".into()));
+    assert_eq!(config.compress(Compression::Zstd, short.clone()), short);
+
+    // Distinct bytes do not shrink.
+    let noise = message(RawData::Binary((0..=255).collect()));
+    assert_eq!(config.compress(Compression::Deflate, noise.clone()), noise);
+
+    let empty = Packet::new(PacketType::Message);
+    assert_eq!(config.compress(Compression::Gzip, empty.clone()), empty);
+}
+
+#[test]
+fn zstd_dictionary_round_trips_and_must_match() {
+    let dictionary = chatty_json().into_bytes();
+    let config = CompressionConfig::default()
+        .with_threshold(0)
+        .with_zstd_dictionary(7, &dictionary);
+    assert_eq!(config.dictionary_id(), Some(7));
+
+    let data = RawData::Text(r#"{"event":"position","x":10,"y":20}"#.repeat(3));
+    let compressed = config.compress(Compression::Zstd, message(data.clone()));
+    assert_eq!(config.decompress(compressed.clone()).unwrap(), message(data));
+    assert!(CompressionConfig::default().decompress(compressed).is_err());
+}
+
+#[test]
+fn dictionary_decides_whether_zstd_is_accepted() {
+    let plain = CompressionConfig::default();
+    let shared = CompressionConfig::default().with_zstd_dictionary(7, b"dictionary");
+    assert!(plain.accepts(Compression::Zstd, None));
+    assert!(!plain.accepts(Compression::Zstd, Some(7)));
+    assert!(shared.accepts(Compression::Zstd, Some(7)));
+    assert!(!shared.accepts(Compression::Zstd, Some(8)));
+    assert!(shared.accepts(Compression::Gzip, None));
+
+    let negotiated = shared.negotiate(&[Compression::Deflate, Compression::Zstd], None).unwrap();
+    assert_eq!(negotiated.algorithms(), &[Compression::Deflate]);
+    assert!(plain.with_algorithms(vec![Compression::Brotli]).negotiate(&[Compression::Gzip], None).is_none());
+}
+
+#[test]
+fn uncompressed_packets_pass_through() {
+    let packet = message(RawData::Text(chatty_json()));
+    assert_eq!(CompressionConfig::default().decompress(packet.clone()).unwrap(), packet);
+}
+
+#[test]
+fn algorithms_outside_the_agreed_set_are_rejected() {
+    let compressed = CompressionConfig::default().compress(Compression::Brotli, message(RawData::Text(chatty_json())));
+    let agreed = CompressionConfig::default().negotiate(&[Compression::Gzip], None).unwrap();
+    assert_eq!(agreed.decompress(compressed), Err(DecodingError::InvalidFormat));
+
+    let compressed = agreed.compress(Compression::Gzip, message(RawData::Text(chatty_json())));
+    assert_eq!(agreed.decompress(compressed).unwrap(), message(RawData::Text(chatty_json())));
+}
+
+#[test]
+fn corrupt_or_oversized_data_is_rejected() {
+    let config = CompressionConfig::default();
+    let mut corrupt = message(RawData::Binary(vec![0, 1, 2, 3, 4]));
+    corrupt.with_options(PacketOptions::default().with_compression(Compression::Gzip));
+    assert_eq!(config.decompress(corrupt), Err(DecodingError::InvalidFormat));
+
+    // Data that decompresses to one byte past the limit.
+    let zeros = vec![0; MAX_PACKET_SIZE + 1];
+    let mut gzip = flate2::write::GzEncoder::new(vec![1], flate2::Compression::default());
+    gzip.write_all(&zeros).unwrap();
+    let mut zstd = vec![1];
+    zstd.extend(zstd::bulk::compress(&zeros, 3).unwrap());
+    for (compression, data) in [(Compression::Gzip, gzip.finish().unwrap()), (Compression::Zstd, zstd)] {
+        let mut bomb = message(RawData::Binary(data));
+        bomb.with_options(PacketOptions::default().with_compression(compression));
+        assert_eq!(config.decompress(bomb), Err(DecodingError::InvalidFormat), "{}", compression.name());
+    }
+}
@@ -0,0 +1,35 @@
+use std::fmt;
+
+/// Error type for key agreement and packet encryption.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncryptionError {
+    /// Peer's public key is missing from the handshake.
+    MissingKey,
+    /// Peer's public key is not a valid X25519 key.
+    InvalidKey,
+    /// Key generation or derivation failed.
+    KeyAgreement,
+    /// Packet data is too large to encrypt.
+    TooLarge,
+    /// Message arrived unencrypted on an encrypted session.
+    Unencrypted,
+    /// Packet's nonce is not past the last one accepted: it was replayed or reordered.
+    Replayed,
+    /// Packet failed authentication or is malformed.
+    InvalidCiphertext,
+}
+
+impl std::error::Error for EncryptionError {}
+impl fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptionError::MissingKey => write!(f, "Peer did not send a public key"),
+            EncryptionError::InvalidKey => write!(f, "Peer's public key is invalid"),
+            EncryptionError::KeyAgreement => write!(f, "Key agreement failed"),
+            EncryptionError::TooLarge => write!(f, "Packet data is too large to encrypt"),
+            EncryptionError::Unencrypted => write!(f, "Message is not encrypted"),
+            EncryptionError::Replayed => write!(f, "Packet was replayed or reordered"),
+            EncryptionError::InvalidCiphertext => write!(f, "Packet failed authentication"),
+        }
+    }
+}
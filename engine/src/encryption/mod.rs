@@ -0,0 +1,359 @@
+//! End-to-end packet encryption with keys agreed in the handshake.
+//!
+//! The client sends an ephemeral X25519 public key with its handshake request and the server answers with its own
+//! in the `Open` packet. Both sides derive one ChaCha20-Poly1305 key per direction from the shared secret with HKDF,
+//! so no key is ever configured up front.
+//!
+//! The key exchange is unauthenticated: it keeps a passive observer out, but an active man in the middle can run one
+//! exchange with each side and read everything. Run the engine over TLS, or sign messages with keys the peers already
+//! trust, where the peer's identity matters.
+//!
+//! Every encrypted `Message` carries its nonce: a 4-byte epoch and an 8-byte counter, both big-endian.
+//! The counter increases with every packet, and a receiver rejects any packet whose nonce is not past the last one,
+//! which catches replayed, reordered and dropped-then-resent ciphertexts alike.
+//! A sender rotates its key by ratcheting the traffic secret through HKDF and bumping the epoch; the receiver follows
+//! as soon as it authenticates a packet from the next epoch, and keys from earlier epochs are discarded.
+
+mod error;
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use base64::{Engine as _, engine::general_purpose};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305};
+use ring::agreement::{self, EphemeralPrivateKey, UnparsedPublicKey, X25519};
+use ring::hkdf::{Prk, Salt, HKDF_SHA256};
+use ring::rand::SystemRandom;
+
+use crate::protocol::{Packet, PacketOptions, PacketType, RawData, MAX_PACKET_SIZE};
+use crate::rt;
+use crate::session::Role;
+
+pub use error::EncryptionError;
+
+const NONCE_LEN: usize = 12;
+/// HKDF salt; changing it makes old and new peers fail the handshake rather than talk past each other.
+const SALT: &[u8] = b"green-engine encryption v1";
+
+/// Marks whether encrypted data was text or binary before encryption.
+const TEXT_DATA: u8 = 0;
+const BINARY_DATA: u8 = 1;
+
+/// Encryption settings for a session.
+#[derive(Debug, Clone)]
+pub struct EncryptionConfig {
+    /// Whether the server refuses clients that do not ask for encryption.
+    required: bool,
+    /// Packets sent under one key before it is rotated.
+    rotate_after: u64,
+    /// Time a key is used for before it is rotated.
+    rotate_interval: Duration,
+    /// The client's public key, set on the server once a handshake asks for encryption.
+    peer_key: Option<String>,
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            required: false,
+            rotate_after: 1 << 20,
+            rotate_interval: Duration::from_secs(600),
+            peer_key: None,
+        }
+    }
+}
+
+impl EncryptionConfig {
+    /// Returns whether the server refuses clients that do not ask for encryption.
+    pub fn required(&self) -> bool {
+        self.required
+    }
+
+    /// Sets whether the server refuses clients that do not ask for encryption.
+    pub fn with_required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Returns the number of packets sent under one key before it is rotated.
+    pub fn rotate_after(&self) -> u64 {
+        self.rotate_after
+    }
+
+    /// Sets the number of packets sent under one key before it is rotated, at least one.
+    pub fn with_rotate_after(mut self, packets: u64) -> Self {
+        self.rotate_after = packets.max(1);
+        self
+    }
+
+    /// Returns the time a key is used for before it is rotated.
+    pub fn rotate_interval(&self) -> Duration {
+        self.rotate_interval
+    }
+
+    /// Sets the time a key is used for before it is rotated.
+    pub fn with_rotate_interval(mut self, interval: Duration) -> Self {
+        self.rotate_interval = interval;
+        self
+    }
+
+    /// Returns the client's public key, if a handshake asked for encryption.
+    pub(crate) fn peer_key(&self) -> Option<&str> {
+        self.peer_key.as_deref()
+    }
+
+    /// Sets the client's public key for one session.
+    pub(crate) fn with_peer_key(mut self, key: String) -> Self {
+        self.peer_key = Some(key);
+        self
+    }
+}
+
+/// One side's ephemeral X25519 key pair for a single session.
+/// A client creates one, sends `public_key` with its handshake request and passes it to `Session::connect_encrypted`.
+pub struct KeyExchange {
+    private: EphemeralPrivateKey,
+    public: Vec<u8>,
+}
+
+impl fmt::Debug for KeyExchange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyExchange").field("public", &self.public_key()).finish_non_exhaustive()
+    }
+}
+
+impl KeyExchange {
+    /// Generates a fresh key pair.
+    pub fn new() -> Result<Self, EncryptionError> {
+        let private = EphemeralPrivateKey::generate(&X25519, &SystemRandom::new())
+            .map_err(|_| EncryptionError::KeyAgreement)?;
+        let public = private.compute_public_key()
+            .map_err(|_| EncryptionError::KeyAgreement)?
+            .as_ref()
+            .to_vec();
+        Ok(Self { private, public })
+    }
+
+    /// Returns the public key, base64url-encoded without padding, as sent in the `key` query or handshake field.
+    pub fn public_key(&self) -> String {
+        general_purpose::URL_SAFE_NO_PAD.encode(&self.public)
+    }
+
+    /// Agrees on session keys with the peer's base64url public key. The key pair is used up.
+    pub(crate) fn agree(self, peer_key: &str, role: Role, config: &EncryptionConfig) -> Result<SecureChannel, EncryptionError> {
+        let peer = general_purpose::URL_SAFE_NO_PAD.decode(peer_key)
+            .map_err(|_| EncryptionError::InvalidKey)?;
+        let (client, server) = match role {
+            Role::Client => (self.public.as_slice(), peer.as_slice()),
+            Role::Server => (peer.as_slice(), self.public.as_slice()),
+        };
+        let (client_secret, server_secret) = agreement::agree_ephemeral(self.private, &UnparsedPublicKey::new(&X25519, &peer), |shared| {
+            let prk = Salt::new(HKDF_SHA256, SALT).extract(shared);
+            // Both public keys are bound into the keys, so the sides only agree if each saw the other's key.
+            // Nothing authenticates those keys, though, so this does not stop a man in the middle.
+            (expand(&prk, &[b"client", client, server]), expand(&prk, &[b"server", client, server]))
+        }).map_err(|_| EncryptionError::InvalidKey)?;
+
+        let (sending, receiving) = match role {
+            Role::Client => (client_secret?, server_secret?),
+            Role::Server => (server_secret?, client_secret?),
+        };
+        Ok(SecureChannel {
+            sending: Sending {
+                keys: Epoch::new(0, sending)?,
+                counter: 0,
+                started: rt::now(),
+            },
+            receiving: Receiving {
+                keys: Epoch::new(0, receiving)?,
+                next: 0,
+            },
+            rotate_after: config.rotate_after,
+            rotate_interval: config.rotate_interval,
+        })
+    }
+}
+
+/// Expands 32 bytes of key material for `info`.
+fn expand(prk: &Prk, info: &[&[u8]]) -> Result<[u8; 32], EncryptionError> {
+    let mut out = [0; 32];
+    prk.expand(info, HKDF_SHA256)
+        .and_then(|okm| okm.fill(&mut out))
+        .map_err(|_| EncryptionError::KeyAgreement)?;
+    Ok(out)
+}
+
+/// The key for one direction in one epoch.
+struct Epoch {
+    number: u32,
+    /// Traffic secret the key was derived from, and the next epoch's is ratcheted from.
+    secret: [u8; 32],
+    key: LessSafeKey,
+}
+
+impl Epoch {
+    fn new(number: u32, secret: [u8; 32]) -> Result<Self, EncryptionError> {
+        let prk = Prk::new_less_safe(HKDF_SHA256, &secret);
+        let key = expand(&prk, &[b"key"])?;
+        let key = UnboundKey::new(&CHACHA20_POLY1305, &key)
+            .map_err(|_| EncryptionError::KeyAgreement)?;
+        Ok(Self { number, secret, key: LessSafeKey::new(key) })
+    }
+
+    /// Derives the key for the following epoch.
+    fn next(&self) -> Result<Self, EncryptionError> {
+        let number = self.number.checked_add(1).ok_or(EncryptionError::KeyAgreement)?;
+        let secret = expand(&Prk::new_less_safe(HKDF_SHA256, &self.secret), &[b"rotate"])?;
+        Self::new(number, secret)
+    }
+}
+
+struct Sending {
+    keys: Epoch,
+    /// Counter for the next packet in this epoch.
+    counter: u64,
+    /// When this epoch's key was first used.
+    started: Instant,
+}
+
+struct Receiving {
+    keys: Epoch,
+    /// Lowest counter still accepted in this epoch.
+    next: u64,
+}
+
+/// Keys and nonce state of an encrypted session, owned by its driver.
+pub(crate) struct SecureChannel {
+    sending: Sending,
+    receiving: Receiving,
+    rotate_after: u64,
+    rotate_interval: Duration,
+}
+
+impl fmt::Debug for SecureChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecureChannel")
+            .field("sending_epoch", &self.sending.keys.number)
+            .field("receiving_epoch", &self.receiving.keys.number)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SecureChannel {
+    /// Bytes an encrypted packet's data grows by: the nonce, the data kind and the authentication tag.
+    pub(crate) const OVERHEAD: usize = NONCE_LEN + 1 + 16;
+
+    /// Encrypts a packet's data under the next nonce, rotating the key first if it is due.
+    pub(crate) fn seal(&mut self, packet: &Packet) -> Result<Packet, EncryptionError> {
+        let (kind, data) = match packet.data() {
+            Some(RawData::Text(text)) => (TEXT_DATA, text.as_bytes()),
+            Some(RawData::Binary(binary)) => (BINARY_DATA, binary.as_slice()),
+            None => (TEXT_DATA, &[][..]),
+        };
+        if data.len() + Self::OVERHEAD > MAX_PACKET_SIZE {
+            return Err(EncryptionError::TooLarge);
+        }
+        if self.sending.counter >= self.rotate_after || rt::now().duration_since(self.sending.started) >= self.rotate_interval {
+            self.sending.keys = self.sending.keys.next()?;
+            self.sending.counter = 0;
+            self.sending.started = rt::now();
+        }
+
+        let options = packet.options().copied().unwrap_or_default();
+        let nonce = nonce(self.sending.keys.number, self.sending.counter);
+        let mut sealed = Vec::with_capacity(data.len() + Self::OVERHEAD);
+        sealed.extend_from_slice(&nonce);
+        sealed.push(kind);
+        sealed.extend_from_slice(data);
+        let tag = self.sending.keys.key.seal_in_place_separate_tag(Nonce::assume_unique_for_key(nonce), aad(packet._type(), &options), &mut sealed[NONCE_LEN..])
+            .map_err(|_| EncryptionError::InvalidCiphertext)?;
+        sealed.extend_from_slice(tag.as_ref());
+        self.sending.counter += 1;
+
+        let mut encrypted = Packet::new(packet._type().clone());
+        encrypted.with_options(options.with_encryption());
+        encrypted.with_data(RawData::Binary(sealed))
+            .map_err(|_| EncryptionError::TooLarge)?;
+        Ok(encrypted)
+    }
+
+    /// Authenticates and decrypts a packet, rejecting any whose nonce is not past the last one accepted.
+    pub(crate) fn open(&mut self, packet: Packet) -> Result<Packet, EncryptionError> {
+        let options = packet.options().copied().unwrap_or_default();
+        if !options.encrypt() {
+            return Err(EncryptionError::Unencrypted);
+        }
+        let sealed = match packet.data() {
+            Some(RawData::Binary(binary)) if binary.len() >= Self::OVERHEAD => binary,
+            _ => return Err(EncryptionError::InvalidCiphertext),
+        };
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into()
+            .map_err(|_| EncryptionError::InvalidCiphertext)?;
+        let (epoch, counter) = nonce.split_at(4);
+        let epoch = u32::from_be_bytes(epoch.try_into().map_err(|_| EncryptionError::InvalidCiphertext)?);
+        let counter = u64::from_be_bytes(counter.try_into().map_err(|_| EncryptionError::InvalidCiphertext)?);
+
+        // The receiver follows the sender one epoch at a time; anything older or further ahead is rejected.
+        let rotated = match epoch.checked_sub(self.receiving.keys.number) {
+            Some(0) if counter >= self.receiving.next => None,
+            Some(1) => Some(self.receiving.keys.next()?),
+            _ => return Err(EncryptionError::Replayed),
+        };
+        let keys = rotated.as_ref().unwrap_or(&self.receiving.keys);
+        let mut in_out = ciphertext.to_vec();
+        let plaintext = keys.key.open_in_place(Nonce::assume_unique_for_key(nonce), aad(packet._type(), &options), &mut in_out)
+            .map_err(|_| EncryptionError::InvalidCiphertext)?;
+        let (kind, data) = plaintext.split_first().ok_or(EncryptionError::InvalidCiphertext)?;
+        let data = match *kind {
+            TEXT_DATA => RawData::Text(String::from_utf8(data.to_vec()).map_err(|_| EncryptionError::InvalidCiphertext)?),
+            BINARY_DATA => RawData::Binary(data.to_vec()),
+            _ => return Err(EncryptionError::InvalidCiphertext),
+        };
+
+        // Only an authenticated packet moves the receiver forward.
+        if let Some(keys) = rotated {
+            self.receiving.keys = keys;
+        }
+        self.receiving.next = counter.saturating_add(1);
+
+        let mut decrypted = Packet::new(packet._type().clone());
+        let signature = options.signature().copied();
+        let mut options = PacketOptions::new(options.compression(), false, options.sequence(), options.total_chunks())
+            .map_err(|_| EncryptionError::InvalidCiphertext)?;
+        if let Some(signature) = signature {
+            options = options.with_signature(signature);
+        }
+        if options != PacketOptions::default() {
+            decrypted.with_options(options);
+        }
+        decrypted.with_data(data)
+            .map_err(|_| EncryptionError::InvalidCiphertext)?;
+        Ok(decrypted)
+    }
+}
+
+fn nonce(epoch: u32, counter: u64) -> [u8; NONCE_LEN] {
+    let mut nonce = [0; NONCE_LEN];
+    nonce[..4].copy_from_slice(&epoch.to_be_bytes());
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    nonce
+}
+
+/// Authenticates the packet type, compression algorithm and chunk fields along with the data,
+/// so none of them can be altered in transit. Each chunk field is a presence byte followed by its big-endian value.
+fn aad(packet_type: &PacketType, options: &PacketOptions) -> Aad<[u8; 8]> {
+    let mut aad = [0; 8];
+    aad[0] = u8::from(packet_type.clone());
+    aad[1] = options.compression().map_or(0, u8::from);
+    for (field, bytes) in [options.sequence(), options.total_chunks()].into_iter().zip(aad[2..].chunks_mut(3)) {
+        if let Some(value) = field {
+            bytes[0] = 1;
+            bytes[1..].copy_from_slice(&value.to_be_bytes());
+        }
+    }
+    Aad::from(aad)
+}
@@ -0,0 +1,154 @@
+use crate::encryption::{EncryptionConfig, EncryptionError, KeyExchange, SecureChannel};
+use crate::protocol::{Compression, Packet, PacketOptions, PacketType, RawData};
+use crate::protocol::tests::message;
+use crate::session::Role;
+
+fn pair(config: &EncryptionConfig) -> (SecureChannel, SecureChannel) {
+    let client = KeyExchange::new().unwrap();
+    let server = KeyExchange::new().unwrap();
+    let (client_key, server_key) = (client.public_key(), server.public_key());
+    (
+        client.agree(&server_key, Role::Client, config).unwrap(),
+        server.agree(&client_key, Role::Server, config).unwrap(),
+    )
+}
+
+/// Returns the epoch and counter a sealed packet carries.
+fn nonce(packet: &Packet) -> (u32, u64) {
+    match packet.data() {
+        Some(RawData::Binary(sealed)) => (
+            u32::from_be_bytes(sealed[..4].try_into().unwrap()),
+            u64::from_be_bytes(sealed[4..12].try_into().unwrap()),
+        ),
+        data => panic!("not sealed: {data:?}"),
+    }
+}
+
+#[test]
+fn agreed_keys_round_trip_both_directions() {
+    let (mut client, mut server) = pair(&EncryptionConfig::default());
+    for data in [RawData::Text("hello".into()), RawData::Binary(vec![1, 2, 3]), RawData::Text(String::new())] {
+        let sealed = client.seal(&message(data.clone())).unwrap();
+        assert!(sealed.options().is_some_and(PacketOptions::encrypt));
+        assert_ne!(sealed.data(), Some(&data));
+        assert_eq!(server.open(sealed).unwrap(), message(data.clone()));
+
+        let sealed = server.seal(&message(data.clone())).unwrap();
+        assert_eq!(client.open(sealed).unwrap(), message(data));
+    }
+}
+
+#[test]
+fn public_keys_are_fresh_and_url_safe() {
+    let (a, b) = (KeyExchange::new().unwrap(), KeyExchange::new().unwrap());
+    assert_ne!(a.public_key(), b.public_key());
+    assert_eq!(a.public_key().len(), 43);
+    assert!(a.public_key().chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+}
+
+#[test]
+fn invalid_peer_keys_are_rejected() {
+    let config = EncryptionConfig::default();
+    for key in ["not base64!", "c2hvcnQ"] {
+        let result = KeyExchange::new().unwrap().agree(key, Role::Client, &config);
+        assert_eq!(result.err(), Some(EncryptionError::InvalidKey), "{key}");
+    }
+}
+
+#[test]
+fn keys_from_another_exchange_do_not_open() {
+    let config = EncryptionConfig::default();
+    let (mut client, _) = pair(&config);
+    let (_, mut stranger) = pair(&config);
+    let sealed = client.seal(&message(RawData::Text("secret".into()))).unwrap();
+    assert_eq!(stranger.open(sealed), Err(EncryptionError::InvalidCiphertext));
+}
+
+#[test]
+fn replayed_and_reordered_packets_are_rejected() {
+    let (mut client, mut server) = pair(&EncryptionConfig::default());
+    let first = client.seal(&message(RawData::Text("first".into()))).unwrap();
+    let second = client.seal(&message(RawData::Text("second".into()))).unwrap();
+    let third = client.seal(&message(RawData::Text("third".into()))).unwrap();
+    assert_eq!((nonce(&first), nonce(&second)), ((0, 0), (0, 1)));
+
+    assert!(server.open(second.clone()).is_ok());
+    assert_eq!(server.open(second), Err(EncryptionError::Replayed));
+    assert_eq!(server.open(first), Err(EncryptionError::Replayed));
+    assert!(server.open(third).is_ok());
+}
+
+#[test]
+fn tampering_is_detected() {
+    let (mut client, mut server) = pair(&EncryptionConfig::default());
+    let sealed = client.seal(&message(RawData::Text("hello".into()))).unwrap();
+
+    let mut flipped = sealed.clone();
+    if let Some(RawData::Binary(data)) = sealed.data() {
+        let mut data = data.clone();
+        *data.last_mut().unwrap() ^= 1;
+        flipped.with_data(RawData::Binary(data)).unwrap();
+    }
+    assert_eq!(server.open(flipped), Err(EncryptionError::InvalidCiphertext));
+
+    // The compression id is authenticated too.
+    let mut relabelled = sealed.clone();
+    relabelled.with_options(sealed.options().copied().unwrap().with_compression(Compression::Gzip));
+    assert_eq!(server.open(relabelled), Err(EncryptionError::InvalidCiphertext));
+
+    // So are the packet type and chunk fields.
+    let mut retyped = Packet::new(PacketType::Ping);
+    retyped.with_options(*sealed.options().unwrap());
+    retyped.with_data(sealed.data().unwrap().clone()).unwrap();
+    assert_eq!(server.open(retyped), Err(EncryptionError::InvalidCiphertext));
+    let mut rechunked = sealed.clone();
+    rechunked.with_options(PacketOptions::new(None, true, Some(1), Some(2)).unwrap());
+    assert_eq!(server.open(rechunked), Err(EncryptionError::InvalidCiphertext));
+
+    // A rejected packet does not move the receiver on.
+    assert!(server.open(sealed).is_ok());
+}
+
+#[test]
+fn plaintext_messages_are_rejected() {
+    let (_, mut server) = pair(&EncryptionConfig::default());
+    assert_eq!(server.open(message(RawData::Text("hi".into()))), Err(EncryptionError::Unencrypted));
+}
+
+#[test]
+fn keys_rotate_after_packet_count() {
+    let (mut client, mut server) = pair(&EncryptionConfig::default().with_rotate_after(2));
+    let sealed: Vec<_> = (0..5)
+        .map(|n| client.seal(&message(RawData::Text(n.to_string()))).unwrap())
+        .collect();
+    let nonces: Vec<_> = sealed.iter().map(nonce).collect();
+    assert_eq!(nonces, [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)]);
+
+    for (n, packet) in sealed.iter().enumerate() {
+        assert_eq!(server.open(packet.clone()).unwrap(), message(RawData::Text(n.to_string())));
+    }
+    // Keys from earlier epochs are gone.
+    assert_eq!(server.open(sealed[3].clone()), Err(EncryptionError::Replayed));
+}
+
+#[test]
+fn receiver_follows_one_epoch_at_a_time() {
+    let (mut client, mut server) = pair(&EncryptionConfig::default().with_rotate_after(1));
+    let _lost = client.seal(&message(RawData::Text("epoch 0".into()))).unwrap();
+    let _lost = client.seal(&message(RawData::Text("epoch 1".into()))).unwrap();
+    let skipped = client.seal(&message(RawData::Text("epoch 2".into()))).unwrap();
+    assert_eq!(server.open(skipped), Err(EncryptionError::Replayed));
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test(start_paused = true)]
+async fn keys_rotate_after_interval() {
+    let (mut client, mut server) = pair(&EncryptionConfig::default().with_rotate_interval(std::time::Duration::from_secs(60)));
+    let before = client.seal(&message(RawData::Text("before".into()))).unwrap();
+    tokio::time::advance(std::time::Duration::from_secs(61)).await;
+    let after = client.seal(&message(RawData::Text("after".into()))).unwrap();
+
+    assert_eq!((nonce(&before), nonce(&after)), ((0, 0), (1, 0)));
+    assert!(server.open(before).is_ok());
+    assert!(server.open(after).is_ok());
+}
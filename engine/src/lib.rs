@@ -1 +1,24 @@
-pub mod protocol;
\ No newline at end of file
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[macro_use]
+mod macros;
+
+#[cfg(feature = "compression")]
+pub mod compression;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+#[cfg(feature = "runtime")]
+pub mod metrics;
+pub mod protocol;
+#[cfg(feature = "runtime")]
+pub mod rt;
+#[cfg(feature = "runtime")]
+pub mod server;
+#[cfg(feature = "runtime")]
+pub mod session;
+#[cfg(feature = "signing")]
+pub mod signing;
+#[cfg(feature = "runtime")]
+pub mod transport;
@@ -0,0 +1,80 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use futures::{Sink, SinkExt, Stream, StreamExt};
+
+use crate::metrics::Metrics;
+use crate::protocol::{EncodedPacket, Packet, RawData};
+use crate::transport::{Transport, TransportError};
+
+/// Wraps a transport so its traffic is reported to `metrics`, if given.
+pub(crate) fn instrument(transport: Box<dyn Transport>, metrics: Option<&Arc<dyn Metrics>>) -> Box<dyn Transport> {
+    match metrics {
+        Some(metrics) => Box::new(Metered { inner: transport, metrics: metrics.clone() }),
+        None => transport,
+    }
+}
+
+/// A transport that reports every packet and decoding error it sees.
+struct Metered {
+    inner: Box<dyn Transport>,
+    metrics: Arc<dyn Metrics>,
+}
+
+impl Transport for Metered {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn supports_binary(&self) -> bool {
+        self.inner.supports_binary()
+    }
+
+    fn received_len(&self) -> Option<usize> {
+        self.inner.received_len()
+    }
+}
+
+impl Stream for Metered {
+    type Item = Result<Packet, TransportError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = ready!(self.inner.poll_next_unpin(cx));
+        match &item {
+            Some(Ok(packet)) => {
+                // A transport that cannot tell its wire size is credited with the packet's data.
+                let bytes = self.inner.received_len()
+                    .unwrap_or_else(|| packet.data().map_or(0, RawData::len));
+                self.metrics.packet_received(self.inner.name(), packet._type(), bytes);
+            }
+            Some(Err(TransportError::Decoding(error))) => self.metrics.decoding_error(error),
+            _ => {}
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl Sink<EncodedPacket> for Metered {
+    type Error = TransportError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready_unpin(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, packet: EncodedPacket) -> Result<(), Self::Error> {
+        let bytes = packet.len(self.inner.supports_binary());
+        let packet_type = packet._type().clone();
+        self.inner.start_send_unpin(packet)?;
+        self.metrics.packet_sent(self.inner.name(), &packet_type, bytes);
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_flush_unpin(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_close_unpin(cx)
+    }
+}
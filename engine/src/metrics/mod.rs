@@ -0,0 +1,45 @@
+mod metered;
+mod prometheus;
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+
+use crate::protocol::{DecodingError, PacketType};
+use crate::session::CloseReason;
+#[cfg(feature = "signing")]
+use crate::signing::SignatureError;
+
+pub(crate) use metered::instrument;
+
+pub use prometheus::PrometheusMetrics;
+
+/// Receives protocol-level events from sessions.
+/// Every method defaults to doing nothing, so implementations only handle what they record.
+pub trait Metrics: fmt::Debug + Send + Sync + 'static {
+    /// A packet was read from a transport; `bytes` is its encoded size.
+    fn packet_received(&self, _transport: &'static str, _packet_type: &PacketType, _bytes: usize) {}
+
+    /// A packet was written to a transport; `bytes` is its encoded size.
+    fn packet_sent(&self, _transport: &'static str, _packet_type: &PacketType, _bytes: usize) {}
+
+    /// A session opened over the given transport.
+    fn session_opened(&self, _transport: &'static str) {}
+
+    /// A session closed.
+    fn session_closed(&self, _reason: CloseReason) {}
+
+    /// A session moved from one transport to another.
+    fn upgraded(&self, _from: &'static str, _to: &'static str) {}
+
+    /// A peer missed its heartbeat.
+    fn heartbeat_timeout(&self) {}
+
+    /// A transport failed to decode an inbound frame.
+    fn decoding_error(&self, _error: &DecodingError) {}
+
+    /// A session dropped an inbound message that failed signature verification.
+    #[cfg(feature = "signing")]
+    fn signature_rejected(&self, _error: &SignatureError) {}
+}
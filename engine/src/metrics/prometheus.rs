@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use bytes::Bytes;
+use http::header::CONTENT_TYPE;
+use http::{HeaderValue, Response};
+
+use crate::metrics::Metrics;
+use crate::protocol::{DecodingError, PacketType};
+use crate::session::CloseReason;
+#[cfg(feature = "signing")]
+use crate::signing::SignatureError;
+
+/// Content type of the Prometheus text exposition format.
+const CONTENT_TYPE_TEXT: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Built-in `Metrics` that keeps counters in memory and renders them in the Prometheus text format.
+#[derive(Debug, Default)]
+pub struct PrometheusMetrics {
+    packets_received: Mutex<BTreeMap<&'static str, u64>>,
+    packets_sent: Mutex<BTreeMap<&'static str, u64>>,
+    bytes_received: Mutex<BTreeMap<&'static str, u64>>,
+    bytes_sent: Mutex<BTreeMap<&'static str, u64>>,
+    sessions_active: AtomicI64,
+    sessions_opened: AtomicU64,
+    upgrades: Mutex<BTreeMap<(&'static str, &'static str), u64>>,
+    heartbeat_timeouts: AtomicU64,
+    decoding_errors: Mutex<BTreeMap<&'static str, u64>>,
+    #[cfg(feature = "signing")]
+    signature_rejections: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+impl PrometheusMetrics {
+    /// Creates a registry with every counter at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of open sessions.
+    pub fn sessions_active(&self) -> i64 {
+        self.sessions_active.load(Ordering::Relaxed)
+    }
+
+    /// Renders every metric in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        family(&mut out, "engine_packets_received_total", "counter", "Packets received, by packet type.");
+        labelled(&mut out, "engine_packets_received_total", "type", &self.packets_received);
+        family(&mut out, "engine_packets_sent_total", "counter", "Packets sent, by packet type.");
+        labelled(&mut out, "engine_packets_sent_total", "type", &self.packets_sent);
+        family(&mut out, "engine_bytes_received_total", "counter", "Encoded bytes received, by transport.");
+        labelled(&mut out, "engine_bytes_received_total", "transport", &self.bytes_received);
+        family(&mut out, "engine_bytes_sent_total", "counter", "Encoded bytes sent, by transport.");
+        labelled(&mut out, "engine_bytes_sent_total", "transport", &self.bytes_sent);
+
+        family(&mut out, "engine_sessions_active", "gauge", "Sessions currently open.");
+        let _ = writeln!(out, "engine_sessions_active {}", self.sessions_active.load(Ordering::Relaxed));
+        family(&mut out, "engine_sessions_opened_total", "counter", "Sessions opened.");
+        let _ = writeln!(out, "engine_sessions_opened_total {}", self.sessions_opened.load(Ordering::Relaxed));
+
+        family(&mut out, "engine_upgrades_total", "counter", "Transport upgrades, by source and target transport.");
+        for ((from, to), count) in self.upgrades.lock().unwrap().iter() {
+            let _ = writeln!(out, "engine_upgrades_total{{from=\"{}\",to=\"{}\"}} {}", from, to, count);
+        }
+        family(&mut out, "engine_heartbeat_timeouts_total", "counter", "Sessions closed for a missed heartbeat.");
+        let _ = writeln!(out, "engine_heartbeat_timeouts_total {}", self.heartbeat_timeouts.load(Ordering::Relaxed));
+        family(&mut out, "engine_decoding_errors_total", "counter", "Inbound frames that failed to decode, by error.");
+        labelled(&mut out, "engine_decoding_errors_total", "error", &self.decoding_errors);
+        #[cfg(feature = "signing")]
+        {
+            family(&mut out, "engine_signature_rejections_total", "counter", "Inbound messages dropped for a failed signature check, by error.");
+            labelled(&mut out, "engine_signature_rejections_total", "error", &self.signature_rejections);
+        }
+        out
+    }
+
+    /// Answers a scrape with the rendered metrics.
+    pub fn response(&self) -> Response<Bytes> {
+        let mut response = Response::new(Bytes::from(self.render()));
+        response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static(CONTENT_TYPE_TEXT));
+        response
+    }
+}
+
+impl Metrics for PrometheusMetrics {
+    fn packet_received(&self, transport: &'static str, packet_type: &PacketType, bytes: usize) {
+        increment(&self.packets_received, packet_type.clone().into(), 1);
+        increment(&self.bytes_received, transport, bytes as u64);
+    }
+
+    fn packet_sent(&self, transport: &'static str, packet_type: &PacketType, bytes: usize) {
+        increment(&self.packets_sent, packet_type.clone().into(), 1);
+        increment(&self.bytes_sent, transport, bytes as u64);
+    }
+
+    fn session_opened(&self, _transport: &'static str) {
+        self.sessions_active.fetch_add(1, Ordering::Relaxed);
+        self.sessions_opened.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn session_closed(&self, _reason: CloseReason) {
+        self.sessions_active.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn upgraded(&self, from: &'static str, to: &'static str) {
+        *self.upgrades.lock().unwrap().entry((from, to)).or_default() += 1;
+    }
+
+    fn heartbeat_timeout(&self) {
+        self.heartbeat_timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn decoding_error(&self, error: &DecodingError) {
+        increment(&self.decoding_errors, error_label(error), 1);
+    }
+
+    #[cfg(feature = "signing")]
+    fn signature_rejected(&self, error: &SignatureError) {
+        increment(&self.signature_rejections, signature_label(error), 1);
+    }
+}
+
+fn increment(counters: &Mutex<BTreeMap<&'static str, u64>>, label: &'static str, by: u64) {
+    *counters.lock().unwrap().entry(label).or_default() += by;
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn labelled(out: &mut String, name: &str, label: &str, counters: &Mutex<BTreeMap<&'static str, u64>>) {
+    for (value, count) in counters.lock().unwrap().iter() {
+        let _ = writeln!(out, "{}{{{}=\"{}\"}} {}", name, label, value, count);
+    }
+}
+
+/// Returns the label for a decoding error's variant.
+fn error_label(error: &DecodingError) -> &'static str {
+    match error {
+        DecodingError::Packet(_) => "packet",
+        DecodingError::Base64(_) => "base64",
+        DecodingError::MissingField => "missing_field",
+        DecodingError::InvalidFormat => "invalid_format",
+        DecodingError::UnknownError => "unknown",
+        DecodingError::PayloadDataMismatch => "payload_data_mismatch",
+    }
+}
+
+/// Returns the label for a signature error's variant.
+#[cfg(feature = "signing")]
+fn signature_label(error: &SignatureError) -> &'static str {
+    match error {
+        SignatureError::InvalidKey => "invalid_key",
+        SignatureError::Encoded => "encoded",
+        SignatureError::Unsigned => "unsigned",
+        SignatureError::UnknownKey(_) => "unknown_key",
+        SignatureError::Invalid => "invalid",
+        SignatureError::Replayed => "replayed",
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use http::header::CONTENT_TYPE;
+
+use crate::metrics::{Metrics, PrometheusMetrics};
+use crate::protocol::{DecodingError, Packet, PacketType, RawData};
+use crate::session::{CloseReason, Session, SessionConfig, SessionEvent};
+use crate::transport::memory::MemoryTransport;
+use crate::transport::polling::{PollingConfig, PollingTransport};
+
+#[test]
+fn renders_prometheus_text() {
+    let metrics = PrometheusMetrics::new();
+    metrics.packet_received("polling", &PacketType::Message, 5);
+    metrics.packet_received("polling", &PacketType::Message, 7);
+    metrics.packet_sent("polling", &PacketType::Ping, 3);
+    metrics.session_opened("polling");
+    metrics.upgraded("polling", "websocket");
+    metrics.heartbeat_timeout();
+    metrics.decoding_error(&DecodingError::InvalidFormat);
+
+    let text = metrics.render();
+    assert!(text.contains("# TYPE engine_packets_received_total counter\n"));
+    assert!(text.contains("engine_packets_received_total{type=\"message\"} 2\n"));
+    assert!(text.contains("engine_bytes_received_total{transport=\"polling\"} 12\n"));
+    assert!(text.contains("engine_packets_sent_total{type=\"ping\"} 1\n"));
+    assert!(text.contains("engine_bytes_sent_total{transport=\"polling\"} 3\n"));
+    assert!(text.contains("# TYPE engine_sessions_active gauge\nengine_sessions_active 1\n"));
+    assert!(text.contains("engine_upgrades_total{from=\"polling\",to=\"websocket\"} 1\n"));
+    assert!(text.contains("engine_heartbeat_timeouts_total 1\n"));
+    assert!(text.contains("engine_decoding_errors_total{error=\"invalid_format\"} 1\n"));
+}
+
+#[cfg(feature = "signing")]
+#[test]
+fn renders_signature_rejections() {
+    use crate::signing::SignatureError;
+
+    let metrics = PrometheusMetrics::new();
+    metrics.signature_rejected(&SignatureError::Replayed);
+    metrics.signature_rejected(&SignatureError::Replayed);
+    metrics.signature_rejected(&SignatureError::Unsigned);
+    let text = metrics.render();
+    assert!(text.contains("engine_signature_rejections_total{error=\"replayed\"} 2\n"));
+    assert!(text.contains("engine_signature_rejections_total{error=\"unsigned\"} 1\n"));
+}
+
+#[test]
+fn response_carries_text_format() {
+    let metrics = PrometheusMetrics::new();
+    let response = metrics.response();
+    assert_eq!(response.headers()[CONTENT_TYPE], "text/plain; version=0.0.4; charset=utf-8");
+    assert_eq!(response.body(), metrics.render().as_bytes());
+}
+
+#[tokio::test]
+async fn sessions_report_traffic() {
+    let metrics = Arc::new(PrometheusMetrics::new());
+    let config = SessionConfig::default().with_metrics(metrics.clone());
+    let (server, mut client) = MemoryTransport::pair();
+    let mut session = Session::accept(server, &config).await.unwrap();
+    assert_eq!(metrics.sessions_active(), 1);
+
+    let mut message = Packet::new(PacketType::Message);
+    message.with_data(RawData::Text("hello".into())).unwrap();
+    client.send(message.into()).await.unwrap();
+    assert_eq!(session.recv().await, Some(SessionEvent::Message(RawData::Text("hello".into()))));
+
+    client.send(Packet::new(PacketType::Close).into()).await.unwrap();
+    assert_eq!(session.recv().await, Some(SessionEvent::Close(CloseReason::TransportClose)));
+    assert_eq!(metrics.sessions_active(), 0);
+
+    let text = metrics.render();
+    assert!(text.contains("engine_packets_sent_total{type=\"open\"} 1\n"));
+    assert!(text.contains("engine_packets_received_total{type=\"message\"} 1\n"));
+    assert!(text.contains("engine_packets_received_total{type=\"close\"} 1\n"));
+    assert!(text.contains("engine_bytes_received_total{transport=\"memory\"}"));
+}
+
+#[tokio::test]
+async fn heartbeat_timeouts_are_counted() {
+    let metrics = Arc::new(PrometheusMetrics::new());
+    let config = SessionConfig::default()
+        .with_ping_interval(Duration::from_millis(20))
+        .with_ping_timeout(Duration::from_millis(20))
+        .with_metrics(metrics.clone());
+    let (server, mut client) = MemoryTransport::pair();
+    let mut session = Session::accept(server, &config).await.unwrap();
+    tokio::spawn(async move { while client.next().await.is_some() {} });
+
+    assert_eq!(session.recv().await, Some(SessionEvent::Close(CloseReason::PingTimeout)));
+    assert!(metrics.render().contains("engine_heartbeat_timeouts_total 1\n"));
+}
+
+#[tokio::test]
+async fn polling_bytes_are_taken_from_request_bodies() {
+    let metrics = Arc::new(PrometheusMetrics::new());
+    let config = SessionConfig::default().with_metrics(metrics.clone());
+    let (transport, handle) = PollingTransport::new(PollingConfig::default());
+    let mut session = Session::accept(transport, &config).await.unwrap();
+
+    let packets = ["hello", "world"].map(|text| {
+        let mut message = Packet::new(PacketType::Message);
+        message.with_data(RawData::Text(text.into())).unwrap();
+        message
+    });
+    let body = Packet::encode_payload(packets.to_vec(), false);
+    let len = body.len();
+    handle.post(body).unwrap();
+    for text in ["hello", "world"] {
+        assert_eq!(session.recv().await, Some(SessionEvent::Message(RawData::Text(text.into()))));
+    }
+    assert!(metrics.render().contains(&format!("engine_bytes_received_total{{transport=\"polling\"}} {len}\n")));
+}
+
+#[tokio::test]
+async fn upgrades_are_counted() {
+    let metrics = Arc::new(PrometheusMetrics::new());
+    let config = SessionConfig::default()
+        .with_upgrades(vec!["memory".into()])
+        .with_metrics(metrics.clone());
+    let (server, client) = MemoryTransport::pair();
+    let (server, client) = tokio::join!(Session::accept(server, &config), Session::connect(client));
+    let (server, client) = (server.unwrap(), client.unwrap());
+
+    let (server_side, client_side) = MemoryTransport::pair();
+    let (upgraded_server, upgraded_client) = tokio::join!(server.upgrade(server_side), client.upgrade(client_side));
+    upgraded_server.unwrap();
+    upgraded_client.unwrap();
+    assert!(metrics.render().contains("engine_upgrades_total{from=\"memory\",to=\"memory\"} 1\n"));
+}
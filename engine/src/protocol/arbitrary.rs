@@ -0,0 +1,51 @@
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::protocol::{Compression, Packet, PacketOptions, PacketType, RawData, Signature, MAX_PACKET_SIZE};
+
+/// Only produces options `PacketOptions::new` accepts: chunking is either unset or `1 <= sequence <= total_chunks`.
+impl<'a> Arbitrary<'a> for PacketOptions {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let chunking = match bool::arbitrary(u)? {
+            true => {
+                let total_chunks = u.int_in_range(1..=u16::MAX)?;
+                let sequence = u.int_in_range(1..=total_chunks)?;
+                (Some(sequence), Some(total_chunks))
+            }
+            false => (None, None),
+        };
+        let options = PacketOptions::new(Option::<Compression>::arbitrary(u)?, bool::arbitrary(u)?, chunking.0, chunking.1)
+            .map_err(|_| arbitrary::Error::IncorrectFormat)?;
+        Ok(match Option::<Signature>::arbitrary(u)? {
+            Some(signature) => options.with_signature(signature),
+            None => options,
+        })
+    }
+}
+
+/// Only produces packets `Packet::with_data` accepts: data is cut to `MAX_PACKET_SIZE`.
+impl<'a> Arbitrary<'a> for Packet {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut packet = Packet::new(PacketType::arbitrary(u)?);
+        if let Some(options) = Option::<PacketOptions>::arbitrary(u)? {
+            packet.with_options(options);
+        }
+        if let Some(data) = Option::<RawData>::arbitrary(u)? {
+            let data = match data {
+                RawData::Binary(mut binary) => {
+                    binary.truncate(MAX_PACKET_SIZE);
+                    RawData::Binary(binary)
+                }
+                RawData::Text(mut text) => {
+                    let mut end = text.len().min(MAX_PACKET_SIZE);
+                    while !text.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    text.truncate(end);
+                    RawData::Text(text)
+                }
+            };
+            packet.with_data(data).map_err(|_| arbitrary::Error::IncorrectFormat)?;
+        }
+        Ok(packet)
+    }
+}
@@ -1,11 +1,27 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
 pub(crate) const _PROTOCOL: u8 = 4;
 
 pub(crate) const PLAIN_TEXT_MASK: u8 = 0x00;
 pub(crate) const BINARY_MASK: u8 = 0x80;
 
+/// Separates the packets of an Engine.IO v4 text payload.
+pub(crate) const RECORD_SEPARATOR: char = '\x1e';
+/// Marks a base64 binary message in the Engine.IO v4 text encoding.
+pub(crate) const V4_BINARY_PREFIX: char = 'b';
+
+/// Bits of the binary options flags byte.
+pub(crate) const ENCRYPTED_FLAG: u8 = 0x01;
+pub(crate) const SIGNED_FLAG: u8 = 0x02;
+/// Binary options length without and with the signature block (key id, counter and signature).
+pub(crate) const OPTIONS_LEN: usize = 6;
+pub(crate) const SIGNED_OPTIONS_LEN: usize = OPTIONS_LEN + 4 + 8 + crate::protocol::Signature::LEN;
+
 pub type BinaryType = Vec<u8>;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum RawData {
     Text(String),
     Binary(BinaryType)
@@ -17,6 +33,9 @@ impl RawData {
             RawData::Text(s) => s.len(),
         }
     }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
     pub fn is_binary(&self) -> bool{
         match self {
             RawData::Binary(_) => true,
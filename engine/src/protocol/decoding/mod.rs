@@ -1,4 +1,10 @@
 pub(crate) mod options;
+pub(crate) mod stream;
+pub(crate) mod v4;
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
 
 use base64::{Engine as _, engine::general_purpose};
 use crate::protocol::{
@@ -10,15 +16,23 @@ use crate::protocol::{
     BinaryType,
     constants::BINARY_MASK,
     constants::PLAIN_TEXT_MASK,
+    constants::OPTIONS_LEN,
+    constants::SIGNED_FLAG,
+    constants::SIGNED_OPTIONS_LEN,
     DecodingError
 };
 
 impl Packet {
     pub fn decode(encoded_packet: RawData) -> Result<Self, DecodingError> {
-        match encoded_packet {
+        let decoded = match encoded_packet {
             RawData::Binary(data) => Self::decode_binary(data),
             RawData::Text(s) => Self::decode_text(s),
+        };
+        match &decoded {
+            Ok(_packet) => { trace!(packet_type = ?_packet._type(), "decoded packet"); }
+            Err(_error) => { debug!(error = %_error, "failed to decode packet"); }
         }
+        decoded
     }
 
     fn decode_binary(encoded: BinaryType) -> Result<Self, DecodingError> {
@@ -27,16 +41,17 @@ impl Packet {
 
         let mut encoded = encoded.into_iter();
 
-        let _type = PacketType::try_from(encoded.next().unwrap())
-            .map_err(|e| DecodingError::Packet(e))?;
+        let mut header = || encoded.next().ok_or(DecodingError::MissingField);
+        let _type = PacketType::try_from(header()?)
+            .map_err(DecodingError::Packet)?;
         let mut packet = Packet::new(_type);
 
-        let has_options = match encoded.next().unwrap() {
+        let has_options = match header()? {
             0 => false,
             1 => true,
             _ => return Err(DecodingError::InvalidFormat),
         };
-        let has_data = match encoded.next().unwrap() {
+        let has_data = match header()? {
             0 => false,
             1 => true,
             _ => return Err(DecodingError::InvalidFormat),
@@ -45,8 +60,11 @@ impl Packet {
         if !has_options && !has_data { return Ok(packet); }
 
         if has_options {
-            let options = encoded.by_ref().take(6).collect::<Vec<u8>>();
-            if options.len() < 6 { return Err(DecodingError::Packet(PacketError::InvalidPacketOptions)); }
+            let mut options = encoded.by_ref().take(OPTIONS_LEN).collect::<Vec<u8>>();
+            if options.len() < OPTIONS_LEN { return Err(DecodingError::Packet(PacketError::InvalidPacketOptions)); }
+            if options[1] & SIGNED_FLAG != 0 {
+                options.extend(encoded.by_ref().take(SIGNED_OPTIONS_LEN - OPTIONS_LEN));
+            }
             let opts = PacketOptions::decode(
                 RawData::Binary(options)
             )?;
@@ -54,35 +72,34 @@ impl Packet {
         }
 
         if has_data {
-            let data_type = encoded.next().unwrap();
+            let data_type = encoded.next().ok_or(DecodingError::MissingField)?;
             let data: RawData = match data_type {
                 BINARY_MASK => Ok(RawData::Binary(encoded.collect())),
                 PLAIN_TEXT_MASK => Ok(RawData::Text(String::from_utf8_lossy(&encoded.collect::<Vec<u8>>()).into())),
                 _ => Err(DecodingError::InvalidFormat),
             }?;
             packet.with_data(data)
-                .map_err(|e| DecodingError::Packet(e))?;
+                .map_err(DecodingError::Packet)?;
         }
         Ok(packet)
     }
 
     fn decode_text(encoded: String) -> Result<Self, DecodingError> {
-        if encoded.len() < 3 { return Err(DecodingError::MissingField); }
-
         let mut chars = encoded.chars();
-        let _type = match PacketType::try_from(chars.next().unwrap_or('X')) {
+        let mut header = || chars.next().ok_or(DecodingError::MissingField);
+        let _type = match PacketType::try_from(header()?) {
             Ok(t) => t,
             Err(_) => return Err(DecodingError::Packet(PacketError::InvalidPacketType)),
         };
         let mut packet = Packet::new(_type);
 
-        let has_options = match chars.next().unwrap(){
+        let has_options = match header()? {
             '0' => false,
             '1' => true,
             _ => return Err(DecodingError::InvalidFormat),
         };
 
-        let has_data = match chars.next().unwrap(){
+        let has_data = match header()? {
             '0' => false,
             '1' => true,
             _ => return Err(DecodingError::InvalidFormat),
@@ -97,7 +114,8 @@ impl Packet {
                     Some(c) => options.push(c),
                 }
             }
-            if options.len() < 7 || options.len() > 11 { return Err(DecodingError::Packet(PacketError::InvalidPacketOptions)); }
+            // From "0:0:0:0" to "4:1:65535:65535", plus up to ":4294967295:18446744073709551615:<86 base64 characters>" if signed.
+            if options.len() < 7 || options.len() > 134 { return Err(DecodingError::Packet(PacketError::InvalidPacketOptions)); }
             let options = PacketOptions::decode(RawData::Text(options))?;
             packet.with_options(options);
 
@@ -112,12 +130,12 @@ impl Packet {
             'b' => {
                 match general_purpose::STANDARD.decode(data) {
                     Ok(bytes) => packet.with_data(RawData::Binary(bytes))
-                        .map_err(|e| DecodingError::Packet(e))?,
+                        .map_err(DecodingError::Packet)?,
                     Err(e) => return Err(DecodingError::Base64(e)),
                 };
             },
             't' => {
-                packet.with_data(RawData::Text(data.to_owned())).map_err(|e| DecodingError::Packet(e))?;
+                packet.with_data(RawData::Text(data.to_owned())).map_err(DecodingError::Packet)?;
             },
             _ => return Err(DecodingError::InvalidFormat)
         };
@@ -127,7 +145,12 @@ impl Packet {
 
     /// Decodes a payload of packets.
     pub fn decode_payload(encoded: RawData) -> Result<Vec<Self>, DecodingError> {
-        let mut payload = Vec::<Self>::new();
+        Ok(Self::decode_payload_sized(encoded)?.into_iter().map(|(packet, _)| packet).collect())
+    }
+
+    /// Decodes a payload of packets, pairing each with the bytes it took up in the payload, length prefix included.
+    pub fn decode_payload_sized(encoded: RawData) -> Result<Vec<(Self, usize)>, DecodingError> {
+        let mut payload = Vec::<(Self, usize)>::new();
 
         match encoded {
             RawData::Binary(bin) => {
@@ -147,26 +170,26 @@ impl Packet {
                     if chunk.len() < len { return Err(DecodingError::PayloadDataMismatch); }
 
                     let decoded = Self::decode(RawData::Binary(chunk))?;
-                    payload.push(decoded);
+                    payload.push((decoded, 4 + len));
                 }
                 Ok(payload)
             },
             RawData::Text(txt) => {
-                if txt.is_empty() { return Ok(payload); }
-                let mut chars = txt.chars().peekable();
-
-                loop {
-                    if chars.by_ref().peek().is_none() { break; }
-
-                    let len_str: String = chars.by_ref().take(8).collect();
+                // Lengths count bytes, so a multi-byte character must not be split.
+                let mut rest = txt.as_str();
+                while !rest.is_empty() {
+                    let len_str = rest.get(..8)
+                        .filter(|len| len.bytes().all(|b| b.is_ascii_digit()))
+                        .ok_or(DecodingError::PayloadDataMismatch)?;
                     let len = len_str.parse::<usize>()
                         .map_err(|_| DecodingError::PayloadDataMismatch)?;
 
-                    let chunk: String = chars.by_ref().take(len).collect();
-                    if chunk.len() < len { return Err(DecodingError::PayloadDataMismatch); }
+                    let chunk = rest.get(8..8usize.saturating_add(len))
+                        .ok_or(DecodingError::PayloadDataMismatch)?;
+                    rest = &rest[8 + len..];
 
                     let decoded = Self::decode(RawData::Text(chunk.into()))?;
-                    payload.push(decoded);
+                    payload.push((decoded, 8 + len));
                 }
                 Ok(payload)
             }
@@ -1,9 +1,20 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use base64::{Engine as _, engine::general_purpose};
+
 use crate::protocol::{
+    Compression,
     PacketError,
     PacketOptions,
     RawData,
     BinaryType,
-    DecodingError
+    DecodingError,
+    Signature,
+    constants::ENCRYPTED_FLAG,
+    constants::SIGNED_FLAG,
+    constants::OPTIONS_LEN,
+    constants::SIGNED_OPTIONS_LEN,
 };
 
 impl PacketOptions {
@@ -16,19 +27,34 @@ impl PacketOptions {
     }
 
     /// Decodes PacketOptions from a compact byte array.
-    /// Format: [compress(1), encrypted(1), sequence(2), total_chunks(2)]
+    /// Format: [compression(1), flags(1), sequence(2), total_chunks(2)], then [key_id(4), counter(8), signature(64)] if signed
     pub fn decode_binary(bytes: BinaryType) -> Result<Self, DecodingError> {
-        if bytes.len() != 6 || bytes[0] > 1 || bytes[1] > 1 {
+        let expected = match bytes.get(1) {
+            Some(&flags) if flags & !(ENCRYPTED_FLAG | SIGNED_FLAG) != 0 => None,
+            Some(&flags) if flags & SIGNED_FLAG != 0 => Some(SIGNED_OPTIONS_LEN),
+            Some(_) => Some(OPTIONS_LEN),
+            None => None,
+        };
+        if expected != Some(bytes.len()) {
             return Err(DecodingError::Packet(PacketError::InvalidPacketOptions));
         }
         let mut options = PacketOptions::default();
 
-        if bytes[0] == 1 {
-            options = options.with_compression();
+        if bytes[0] != 0 {
+            options = options.with_compression(Compression::try_from(bytes[0]).map_err(DecodingError::Packet)?);
         }
-        if bytes[1] == 1 {
+        if bytes[1] & ENCRYPTED_FLAG != 0 {
             options = options.with_encryption();
         }
+        if let Some(block) = bytes.get(OPTIONS_LEN..) {
+            if let Some((key_id, block)) = block.split_first_chunk::<4>() {
+                let (counter, signature) = block.split_first_chunk::<8>()
+                    .ok_or(DecodingError::Packet(PacketError::InvalidPacketOptions))?;
+                let signature = signature.try_into()
+                    .map_err(|_| DecodingError::Packet(PacketError::InvalidPacketOptions))?;
+                options = options.with_signature(Signature::new(u32::from_be_bytes(*key_id), u64::from_be_bytes(*counter), signature));
+            }
+        }
 
         let sequence = u16::from_be_bytes([bytes[2], bytes[3]]);
         let total_chunks = u16::from_be_bytes([bytes[4], bytes[5]]);
@@ -36,25 +62,37 @@ impl PacketOptions {
             return Ok(options);
         }
         options.with_chunking(sequence, total_chunks)
-            .map_err(|e| DecodingError::Packet(e))?;
+            .map_err(DecodingError::Packet)?;
 
         Ok(options)
     }
 
     /// Decodes PacketOptions from a compact string.
-    /// Format: "compress:encrypted:sequence:total_chunks"
+    /// Format: "compression:encrypted:sequence:total_chunks", plus ":key_id:counter:signature" if signed
     pub fn decode_text(s: String) -> Result<Self, DecodingError> {
         let parts: Vec<_> = s.split(':').collect();
-        if parts.len() != 4 { return Err(DecodingError::Packet(PacketError::InvalidPacketOptions)); }
+        if parts.len() != 4 && parts.len() != 7 { return Err(DecodingError::Packet(PacketError::InvalidPacketOptions)); }
         let mut options = PacketOptions::default();
 
-        let compress = match parts[0] {
-            "0" => false,
-            "1" => true,
+        if let [.., key_id, counter, signature] = parts[4..] {
+            let key_id = key_id.parse::<u32>()
+                .map_err(|_| DecodingError::Packet(PacketError::InvalidPacketOptions))?;
+            let counter = counter.parse::<u64>()
+                .map_err(|_| DecodingError::Packet(PacketError::InvalidPacketOptions))?;
+            let mut bytes = [0; Signature::LEN];
+            match general_purpose::STANDARD_NO_PAD.decode_slice(signature, &mut bytes) {
+                Ok(Signature::LEN) => options = options.with_signature(Signature::new(key_id, counter, bytes)),
+                _ => return Err(DecodingError::Packet(PacketError::InvalidPacketOptions)),
+            }
+        }
+
+        let compression = match parts[0].as_bytes() {
+            b"0" => None,
+            [id] => Some(Compression::try_from(id.wrapping_sub(b'0')).map_err(DecodingError::Packet)?),
             _ => return Err(DecodingError::Packet(PacketError::InvalidPacketOptions)),
         };
-        if compress {
-            options = options.with_compression();
+        if let Some(compression) = compression {
+            options = options.with_compression(compression);
         }
 
         let encrypt = match parts[1] {
@@ -75,7 +113,7 @@ impl PacketOptions {
             return Ok(options);
         }
         options.with_chunking(sequence, total_chunks)
-            .map_err(|e| DecodingError::Packet(e))?;
+            .map_err(DecodingError::Packet)?;
 
         Ok(options)
     }
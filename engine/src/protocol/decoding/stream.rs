@@ -1,29 +1,31 @@
-use std::collections::VecDeque;
-use std::pin::Pin;
-use std::task::{Context, Poll};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use bytes::{Buf, BytesMut};
 use futures::Stream;
 use pin_project::pin_project;
 
-use crate::constants::*;
-use crate::packet::Packet;
+use crate::protocol::{
+    Packet,
+    RawData,
+    BinaryType,
+    DecodingError,
+    MAX_PACKET_SIZE,
+};
+
+/// Largest frame payload: a binary-encoded packet adds at most 86 header bytes to its data, 76 of them for a signature.
+const MAX_FRAME_LENGTH: usize = MAX_PACKET_SIZE + 86;
 
 #[pin_project]
 #[derive(Debug)]
 pub struct PacketDecoderStream<S> {
     #[pin]
     stream: S,
-    chunks: VecDeque<BinaryType>,
-    state: State,
-    expected_length: usize,
-    is_binary: bool,
-}
-
-#[derive(Debug, Clone, Copy)]
-enum State {
-    ReadHeader,
-    ReadExtendedLength16,
-    ReadExtendedLength64,
-    ReadPayload,
+    /// Received bytes not yet decoded; frames are parsed from its front.
+    buffer: BytesMut,
+    /// Set once the inner stream ends or a frame header is unusable.
+    done: bool,
+    /// Size of the frame last yielded, header included.
+    frame_len: usize,
 }
 
 impl<S> PacketDecoderStream<S>
@@ -33,104 +35,84 @@ where
     pub fn new(stream: S) -> Self {
         Self {
             stream,
-            chunks: VecDeque::new(),
-            state: State::ReadHeader,
-            expected_length: 0,
-            is_binary: false,
+            buffer: BytesMut::new(),
+            done: false,
+            frame_len: 0,
         }
     }
 
-}
+    /// Returns the size of the frame last yielded, header included.
+    pub fn frame_len(&self) -> usize {
+        self.frame_len
+    }
 
-fn total_length(chunks: &VecDeque<BinaryType>) -> usize {
-    chunks.iter().map(|chunk| chunk.len()).sum()
-}
+    /// Returns a reference to the underlying chunk stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
 
-fn concat_chunks(chunks: &mut VecDeque<BinaryType>, size: usize) -> BinaryType {
-    let mut buffer = Vec::with_capacity(size);
-    while buffer.len() < size {
-        if let Some(mut chunk) = chunks.pop_front() {
-            let take_len = (size - buffer.len()).min(chunk.len());
-            buffer.extend_from_slice(&chunk[..take_len]);
+    /// Returns a mutable reference to the underlying chunk stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+}
 
-            if take_len < chunk.len() {
-                // Push back remaining part of the chunk
-                chunks.push_front(chunk.split_off(take_len));
-            }
-        } else {
-            break; // No more chunks left
+/// Reads the frame header at the front of `buffer` without consuming it.
+/// Returns the header and payload lengths, or `None` if the header is incomplete.
+/// [Header (1 byte), Extended length (0, 2 or 8 bytes), Packet (variable)]
+fn frame_header(buffer: &[u8]) -> Option<(usize, u64)> {
+    let first = *buffer.first()?;
+    match first & 0x7f {
+        126 => {
+            let length = buffer.get(1..3)?;
+            Some((3, u16::from_be_bytes([length[0], length[1]]) as u64))
+        }
+        127 => {
+            let length: [u8; 8] = buffer.get(1..9)?.try_into().ok()?;
+            Some((9, u64::from_be_bytes(length)))
         }
+        length => Some((1, length as u64)),
     }
-
-    buffer
 }
 
-
 impl<S> Stream for PacketDecoderStream<S>
 where
     S: Stream<Item = BinaryType>,
 {
-    type Item = Packet;
+    type Item = Result<Packet, DecodingError>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let this = self.project();
-        let chunk = match this.stream.poll_next(cx) {
-            Poll::Ready(Some(chunk)) => chunk,
-            Poll::Ready(None) => return Poll::Ready(None),
-            Poll::Pending => return Poll::Pending,
-        };
-        this.chunks.push_back(chunk);
-
+        let mut this = self.project();
 
         loop {
-            match this.state {
-                State::ReadHeader => {
-                    if total_length(this.chunks) < 1 {
-                        break Poll::Pending;
-                    }
-                    let header = concat_chunks(this.chunks, 1);
-                    *this.is_binary = (header[0] & BINARY_MASK) != PLAIN_TEXT_MASK;
-                    *this.expected_length = (header[0] & 0x7f) as usize;
-                    *this.state = if *this.expected_length < 126 {
-                        State::ReadPayload
-                    } else if *this.expected_length == 126 {
-                        State::ReadExtendedLength16
-                    } else {
-                        State::ReadExtendedLength64
-                    };
-                }
-                State::ReadExtendedLength16 => {
-                    if total_length(this.chunks) < 2 {
-                        break Poll::Pending;
-                    }
-                    let header = concat_chunks(this.chunks, 2);
-                    *this.expected_length = ((header[0] as usize) << 8) | (header[1] as usize);
-                    *this.state = State::ReadPayload;
-                }
-                State::ReadExtendedLength64 => {
-                    if total_length(this.chunks) < 8 {
-                        break Poll::Pending;
+            if *this.done {
+                return Poll::Ready(None);
+            }
+            // Every frame already buffered is yielded before the inner stream is polled again.
+            if let Some((header_length, payload_length)) = frame_header(this.buffer) {
+                let payload_length = match usize::try_from(payload_length) {
+                    Ok(length) if length <= MAX_FRAME_LENGTH => length,
+                    _ => {
+                        // An oversized length leaves no way to find the next frame.
+                        *this.done = true;
+                        this.buffer.clear();
+                        return Poll::Ready(Some(Err(DecodingError::InvalidFormat)));
                     }
-                    let header = concat_chunks(this.chunks, 8);
-                    *this.expected_length = (header[0] as usize) << 56
-                        | (header[1] as usize) << 48
-                        | (header[2] as usize) << 40
-                        | (header[3] as usize) << 32
-                        | (header[4] as usize) << 24
-                        | (header[5] as usize) << 16
-                        | (header[6] as usize) << 8
-                        | header[7] as usize;
-                    *this.state = State::ReadPayload;
+                };
+                if this.buffer.len() >= header_length + payload_length {
+                    *this.frame_len = header_length + payload_length;
+                    this.buffer.advance(header_length);
+                    let payload = this.buffer.split_to(payload_length);
+                    return Poll::Ready(Some(Packet::decode(RawData::Binary(payload.into()))));
                 }
-                State::ReadPayload => {
-                    if total_length(this.chunks) < *this.expected_length {
-                        break Poll::Pending;
-                    }
-                    let payload = concat_chunks(this.chunks, *this.expected_length);
-                    let decoded_packet = Packet::decode(RawData::Binary(payload));
-                    *this.state = State::ReadHeader;
-                    break Poll::Ready(Some(decoded_packet));
+            }
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(chunk)) => this.buffer.extend_from_slice(&chunk),
+                Poll::Ready(None) => {
+                    *this.done = true;
+                    return Poll::Ready(None);
                 }
+                Poll::Pending => return Poll::Pending,
             }
         }
     }
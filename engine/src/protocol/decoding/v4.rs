@@ -0,0 +1,58 @@
+use alloc::vec::Vec;
+
+use base64::{Engine as _, engine::general_purpose};
+
+use crate::protocol::{
+    Packet,
+    PacketType,
+    PacketError,
+    RawData,
+    constants::RECORD_SEPARATOR,
+    constants::V4_BINARY_PREFIX,
+    DecodingError
+};
+
+impl Packet {
+    /// Decodes a packet in the Engine.IO v4 text encoding; a `b` prefix marks a base64 binary message.
+    pub fn decode_v4(encoded: &str) -> Result<Self, DecodingError> {
+        let mut chars = encoded.chars();
+        let packet = match chars.next().ok_or(DecodingError::MissingField)? {
+            V4_BINARY_PREFIX => {
+                let data = general_purpose::STANDARD.decode(chars.as_str())
+                    .map_err(DecodingError::Base64)?;
+                let mut packet = Packet::new(PacketType::Message);
+                packet.with_data(RawData::Binary(data)).map_err(DecodingError::Packet)?;
+                packet
+            }
+            c => {
+                let _type = PacketType::try_from(c)
+                    .map_err(|_| DecodingError::Packet(PacketError::InvalidPacketType))?;
+                let mut packet = Packet::new(_type);
+                if !chars.as_str().is_empty() {
+                    packet.with_data(RawData::Text(chars.as_str().into())).map_err(DecodingError::Packet)?;
+                }
+                packet
+            }
+        };
+        trace!(packet_type = ?packet._type(), "decoded v4 packet");
+        Ok(packet)
+    }
+
+    /// Decodes a payload of packets in the Engine.IO v4 text encoding.
+    pub fn decode_payload_v4(encoded: &str) -> Result<Vec<Self>, DecodingError> {
+        Ok(Self::decode_payload_v4_sized(encoded)?.into_iter().map(|(packet, _)| packet).collect())
+    }
+
+    /// Decodes a v4 payload, pairing each packet with the bytes it took up in the payload, separator included.
+    pub fn decode_payload_v4_sized(encoded: &str) -> Result<Vec<(Self, usize)>, DecodingError> {
+        if encoded.is_empty() { return Ok(Vec::new()); }
+
+        let mut payload = Vec::<(Self, usize)>::new();
+        let mut records = encoded.split(RECORD_SEPARATOR).peekable();
+        while let Some(record) = records.next() {
+            let separator = if records.peek().is_some() { RECORD_SEPARATOR.len_utf8() } else { 0 };
+            payload.push((Self::decode_v4(record)?, record.len() + separator));
+        }
+        Ok(payload)
+    }
+}
@@ -0,0 +1,81 @@
+use std::sync::{Arc, OnceLock};
+
+use crate::protocol::{BinaryType, Packet, PacketType};
+
+/// A packet whose encodings are computed at most once and shared by every clone.
+/// Build one per broadcast and hand clones to each session: the text or binary form is encoded
+/// by the first transport that needs it and reused by the rest.
+#[derive(Debug, Clone)]
+pub struct EncodedPacket {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    packet: Packet,
+    text: OnceLock<String>,
+    binary: OnceLock<BinaryType>,
+    v4: OnceLock<String>,
+}
+
+impl EncodedPacket {
+    /// Wraps a packet; nothing is encoded until an encoding is asked for.
+    pub fn new(packet: Packet) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                packet,
+                text: OnceLock::new(),
+                binary: OnceLock::new(),
+                v4: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Returns the packet.
+    pub fn packet(&self) -> &Packet {
+        &self.inner.packet
+    }
+
+    /// Returns the packet type.
+    pub fn _type(&self) -> &PacketType {
+        self.inner.packet._type()
+    }
+
+    /// Returns the text encoding, encoding the packet on first use.
+    pub fn text(&self) -> &str {
+        self.inner.text.get_or_init(|| self.inner.packet.encode_text())
+    }
+
+    /// Returns the binary encoding, encoding the packet on first use.
+    pub fn binary(&self) -> &[u8] {
+        self.inner.binary.get_or_init(|| self.inner.packet.encode_binary())
+    }
+
+    /// Returns the Engine.IO v4 encoding, encoding the packet on first use.
+    pub fn v4(&self) -> &str {
+        self.inner.v4.get_or_init(|| self.inner.packet.encode_v4())
+    }
+
+    /// Returns the size of the packet in the given encoding.
+    pub fn len(&self, supports_binary: bool) -> usize {
+        match supports_binary {
+            true => self.binary().len(),
+            false => self.text().len(),
+        }
+    }
+}
+
+impl From<Packet> for EncodedPacket {
+    fn from(packet: Packet) -> Self {
+        Self::new(packet)
+    }
+}
+
+/// Compares the packets; cached encodings are derived from them.
+impl PartialEq for EncodedPacket {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner) || self.packet() == other.packet()
+    }
+}
+
+impl Eq for EncodedPacket {}
@@ -1,4 +1,14 @@
+#[cfg(feature = "std")]
+pub(crate) mod encoded;
 pub(crate) mod options;
+pub(crate) mod payload;
+pub(crate) mod stream;
+pub(crate) mod v4;
+
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
 
 use base64::{Engine as _, engine::general_purpose};
 
@@ -13,6 +23,7 @@ use crate::protocol::{
 impl Packet {
     /// Encodes the packet as either binary or text, depending on supports_binary.
     pub fn encode(self, supports_binary: bool) -> RawData {
+        trace!(packet_type = ?self._type(), binary = supports_binary, "encoding packet");
         match supports_binary {
             true => RawData::Binary(self.encode_binary()),
             false => RawData::Text(self.encode_text()),
@@ -21,7 +32,7 @@ impl Packet {
 
     /// Encodes the packet as binary.
     /// [PacketType (1 byte), PacketOptions (6 bytes), Data prefix (1 byte), Data (variable)]
-    fn encode_binary(self) -> BinaryType {
+    pub(crate) fn encode_binary(&self) -> BinaryType {
         let mut bin = Vec::<u8>::new();
         bin.push(self._type().to_owned().into());
         bin.push(
@@ -36,7 +47,7 @@ impl Packet {
                 RawData::Binary(bytes) => bin.extend_from_slice(&bytes),
                 _ => {
                     debug_assert!(false, "PacketOptions.encode(true) did not return RawData::Binary");
-                    eprintln!("Warning: PacketOptions did not encode!");
+                    warn!("packet options did not encode");
                 }
             }
         }
@@ -57,7 +68,7 @@ impl Packet {
 
     /// Encodes the packet as text.
     /// Format: "<packet_type><options><data_prefix><data>"
-    fn encode_text(self) -> String {
+    pub(crate) fn encode_text(&self) -> String {
         let mut encoded = String::new();
         encoded.push(self._type().to_owned().into());
         encoded.push(
@@ -72,7 +83,7 @@ impl Packet {
                 RawData::Text(text) => encoded.push_str(&text),
                 _ => {
                     debug_assert!(false, "PacketOptions.encode(false) did not return RawData::Text");
-                    eprintln!("Warning: PacketOptions did not encode!");
+                    warn!("packet options did not encode");
                 }
             }
         }
@@ -82,7 +93,7 @@ impl Packet {
             match data {
                 RawData::Binary(data) => {
                     encoded.push('b');
-                    encoded.push_str(&general_purpose::URL_SAFE.encode(data));
+                    encoded.push_str(&general_purpose::STANDARD.encode(data));
                 },
                 RawData::Text(text) => {
                     encoded.push('t');
@@ -1,6 +1,14 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use base64::{Engine as _, engine::general_purpose};
+
 use crate::protocol::{
     PacketOptions,
-    RawData
+    RawData,
+    constants::ENCRYPTED_FLAG,
+    constants::SIGNED_FLAG,
 };
 
 impl PacketOptions {
@@ -15,8 +23,15 @@ impl PacketOptions {
     /// Encodes PacketOptions as a compact byte array.
     fn encode_binary(&self) -> Vec<u8> {
         let mut buffer = Vec::new();
-        buffer.push(self.compress() as u8);
-        buffer.push(self.encrypt() as u8);
+        buffer.push(self.compression().map_or(0, u8::from));
+        let mut flags = 0;
+        if self.encrypt() {
+            flags |= ENCRYPTED_FLAG;
+        }
+        if self.signature().is_some() {
+            flags |= SIGNED_FLAG;
+        }
+        buffer.push(flags);
 
         // Encode sequence and total_chunks as u16 (2 bytes each)
         buffer.extend_from_slice(
@@ -25,18 +40,28 @@ impl PacketOptions {
         buffer.extend_from_slice(
             &(self.total_chunks().unwrap_or(0)).to_be_bytes()
         );
+        if let Some(signature) = self.signature() {
+            buffer.extend_from_slice(&signature.key_id().to_be_bytes());
+            buffer.extend_from_slice(&signature.counter().to_be_bytes());
+            buffer.extend_from_slice(signature.bytes());
+        }
 
         buffer
     }
 
     /// Encodes PacketOptions as a compact string (e.g., "1:0:10:20").
+    /// A signature adds its key id, counter and base64 bytes (e.g., "1:0:10:20:7:3:<signature>").
     fn encode_text(&self) -> String {
-        format!(
+        let mut encoded = format!(
             "{}:{}:{}:{}",
-            self.compress() as u8,
+            self.compression().map_or(0, u8::from),
             self.encrypt() as u8,
             self.sequence().unwrap_or(0),
             self.total_chunks().unwrap_or(0),
-        )
+        );
+        if let Some(signature) = self.signature() {
+            encoded.push_str(&format!(":{}:{}:{}", signature.key_id(), signature.counter(), general_purpose::STANDARD_NO_PAD.encode(signature.bytes())));
+        }
+        encoded
     }
 }
\ No newline at end of file
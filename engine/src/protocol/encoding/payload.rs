@@ -0,0 +1,157 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use crate::protocol::EncodedPacket;
+use crate::protocol::{
+    Packet,
+    RawData,
+    BinaryType,
+    constants::RECORD_SEPARATOR,
+};
+
+/// Incrementally builds a payload in the `Packet::encode_payload` or `Packet::encode_payload_v4` format,
+/// bounded by a maximum size.
+#[derive(Debug, Clone)]
+pub struct PayloadBuilder {
+    supports_binary: bool,
+    /// Whether packets use the Engine.IO v4 encoding; v4 payloads are always text.
+    v4: bool,
+    max_payload: usize,
+    binary: BinaryType,
+    text: String,
+    count: usize,
+}
+
+impl PayloadBuilder {
+    /// Creates an empty payload limited to `max_payload` encoded bytes.
+    pub fn new(supports_binary: bool, max_payload: usize) -> Self {
+        Self {
+            supports_binary,
+            v4: false,
+            max_payload,
+            binary: Vec::new(),
+            text: String::new(),
+            count: 0,
+        }
+    }
+
+    /// Creates an empty Engine.IO v4 payload limited to `max_payload` encoded bytes.
+    pub fn v4(max_payload: usize) -> Self {
+        Self {
+            v4: true,
+            ..Self::new(false, max_payload)
+        }
+    }
+
+    /// Returns the encoded size of the payload so far, in bytes.
+    pub fn len(&self) -> usize {
+        match self.supports_binary {
+            true => self.binary.len(),
+            false => self.text.len(),
+        }
+    }
+
+    /// Returns whether no packets have been added.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns the number of packets added.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Adds a packet if it fits within the maximum size, otherwise hands it back.
+    /// The first packet is always accepted, so an oversized packet travels alone.
+    // The caller gets its own packet back, so there is nothing to gain from boxing it.
+    #[allow(clippy::result_large_err)]
+    pub fn try_push(&mut self, packet: Packet) -> Result<(), Packet> {
+        let pushed = match (self.v4, self.supports_binary) {
+            (true, _) => self.push_v4(&packet.encode_v4()),
+            (false, true) => self.push_binary(&packet.encode_binary()),
+            (false, false) => self.push_text(&packet.encode_text()),
+        };
+        match pushed {
+            true => Ok(()),
+            false => Err(packet),
+        }
+    }
+
+    /// Adds an encoded packet, reusing its cached encoding, if it fits within the maximum size.
+    /// Otherwise hands it back, like `try_push`.
+    #[cfg(feature = "std")]
+    pub fn try_push_encoded(&mut self, packet: EncodedPacket) -> Result<(), EncodedPacket> {
+        let pushed = match (self.v4, self.supports_binary) {
+            (true, _) => self.push_v4(packet.v4()),
+            (false, true) => self.push_binary(packet.binary()),
+            (false, false) => self.push_text(packet.text()),
+        };
+        match pushed {
+            true => Ok(()),
+            false => Err(packet),
+        }
+    }
+
+    fn push_binary(&mut self, encoded: &[u8]) -> bool {
+        if !self.is_empty() && self.len() + 4 + encoded.len() > self.max_payload {
+            return false;
+        }
+        self.binary.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+        self.binary.extend_from_slice(encoded);
+        self.count += 1;
+        true
+    }
+
+    fn push_text(&mut self, encoded: &str) -> bool {
+        if !self.is_empty() && self.len() + 8 + encoded.len() > self.max_payload {
+            return false;
+        }
+        self.text.push_str(&format!("{:08}", encoded.len()));
+        self.text.push_str(encoded);
+        self.count += 1;
+        true
+    }
+
+    fn push_v4(&mut self, encoded: &str) -> bool {
+        let separator = if self.is_empty() { 0 } else { RECORD_SEPARATOR.len_utf8() };
+        if !self.is_empty() && self.len() + separator + encoded.len() > self.max_payload {
+            return false;
+        }
+        if separator > 0 {
+            self.text.push(RECORD_SEPARATOR);
+        }
+        self.text.push_str(encoded);
+        self.count += 1;
+        true
+    }
+
+    /// Returns the encoded payload.
+    pub fn finish(self) -> RawData {
+        match self.supports_binary {
+            true => RawData::Binary(self.binary),
+            false => RawData::Text(self.text),
+        }
+    }
+}
+
+impl Packet {
+    /// Encodes packets into as few payloads as possible, each at most `max_payload` bytes
+    /// unless it holds a single oversized packet.
+    pub fn encode_payloads(packets: Vec<Self>, supports_binary: bool, max_payload: usize) -> Vec<RawData> {
+        let mut payloads = Vec::new();
+        let mut builder = PayloadBuilder::new(supports_binary, max_payload);
+        for packet in packets {
+            if let Err(packet) = builder.try_push(packet) {
+                payloads.push(builder.finish());
+                builder = PayloadBuilder::new(supports_binary, max_payload);
+                builder.try_push(packet).ok();
+            }
+        }
+        if !builder.is_empty() {
+            payloads.push(builder.finish());
+        }
+        payloads
+    }
+}
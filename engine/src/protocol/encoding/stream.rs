@@ -1,10 +1,17 @@
-use std::pin::Pin;
-use std::task::{Context, Poll};
+use alloc::vec::Vec;
+use core::pin::Pin;
+use core::task::{Context, Poll};
 use futures::Stream;
 use pin_project::pin_project;
 
-use crate::constants::{RawData, BINARY_MASK, BinaryType};
-use crate::packet::Packet;
+#[cfg(feature = "runtime")]
+use crate::protocol::EncodedPacket;
+use crate::protocol::{
+    Packet,
+    RawData,
+    BinaryType,
+    constants::BINARY_MASK,
+};
 
 #[pin_project]
 pub struct PacketEncoderStream<S> {
@@ -21,6 +28,54 @@ where
     }
 }
 
+impl Packet {
+    /// Encodes the packet as a length-prefixed binary frame for stream transports.
+    /// [Header (1 byte), Extended length (0, 2 or 8 bytes), Packet (variable)]
+    pub(crate) fn encode_frame(self) -> BinaryType {
+        let is_binary = matches!(self.data(), Some(RawData::Binary(_)));
+        let mut frame = Vec::new();
+        write_frame(&mut frame, &self.encode_binary(), is_binary);
+        frame
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl EncodedPacket {
+    /// Appends the packet's stream frame to `out`, reusing the cached binary encoding.
+    pub(crate) fn write_frame(&self, out: &mut BinaryType) {
+        let is_binary = matches!(self.packet().data(), Some(RawData::Binary(_)));
+        write_frame(out, self.binary(), is_binary);
+    }
+}
+
+/// Appends a frame around an encoded packet: [Header (1 byte), Extended length (0, 2 or 8 bytes), Packet (variable)]
+fn write_frame(out: &mut BinaryType, encoded_packet: &[u8], is_binary: bool) {
+    // Add length header similar to WebSocket-like encoding
+    let payload_length = encoded_packet.len();
+    let start = out.len();
+    if payload_length < 126 {
+        out.push(payload_length as u8);
+    } else if payload_length < 65536 {
+        out.push(126);
+        out.push((payload_length >> 8) as u8);
+        out.push(payload_length as u8);
+    } else {
+        out.push(127);
+        out.push((payload_length >> 56) as u8);
+        out.push((payload_length >> 48) as u8);
+        out.push((payload_length >> 40) as u8);
+        out.push((payload_length >> 32) as u8);
+        out.push((payload_length >> 24) as u8);
+        out.push((payload_length >> 16) as u8);
+        out.push((payload_length >> 8) as u8);
+        out.push(payload_length as u8);
+    }
+    if is_binary {
+        out[start] |= BINARY_MASK;
+    }
+    out.extend_from_slice(encoded_packet);
+}
+
 impl<S> Stream for PacketEncoderStream<S>
 where
     S: Stream<Item = Packet>,
@@ -30,36 +85,7 @@ where
     fn poll_next(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.project();
         match this.stream.poll_next(ctx) {
-            Poll::Ready(Some(packet)) => {
-                let encoded_packet = packet.encode_binary();
-
-                // Add length header similar to WebSocket-like encoding
-                let payload_length = encoded_packet.len();
-                let mut header: BinaryType = Vec::new();
-                if payload_length < 126 {
-                    header.push(payload_length as u8);
-                } else if payload_length < 65536 {
-                    header.push(126);
-                    header.push((payload_length >> 8) as u8);
-                    header.push(payload_length as u8);
-                } else {
-                    header.push(127);
-                    header.push((payload_length >> 56) as u8);
-                    header.push((payload_length >> 48) as u8);
-                    header.push((payload_length >> 40) as u8);
-                    header.push((payload_length >> 32) as u8);
-                    header.push((payload_length >> 24) as u8);
-                    header.push((payload_length >> 16) as u8);
-                    header.push((payload_length >> 8) as u8);
-                    header.push(payload_length as u8);
-                }
-                if let Some(RawData::Binary(_)) = &packet.data{
-                    header[0] |= BINARY_MASK;
-                }
-                header.extend(encoded_packet);
-
-                Poll::Ready(Some(header))
-            }
+            Poll::Ready(Some(packet)) => Poll::Ready(Some(packet.encode_frame())),
             Poll::Ready(None) => Poll::Ready(None),
             Poll::Pending => Poll::Pending,
         }
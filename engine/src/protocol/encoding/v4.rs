@@ -0,0 +1,47 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use base64::{Engine as _, engine::general_purpose};
+
+use crate::protocol::{
+    Packet,
+    RawData,
+    constants::RECORD_SEPARATOR,
+    constants::V4_BINARY_PREFIX,
+};
+
+impl Packet {
+    /// Encodes the packet in the Engine.IO v4 text encoding.
+    /// Format: "<packet_type><data>", or "b<base64 data>" for binary data.
+    /// Packet options have no v4 encoding and are left out.
+    pub fn encode_v4(&self) -> String {
+        if self.options().is_some() {
+            debug!(packet_type = ?self._type(), "packet options dropped by the v4 encoding");
+        }
+        let mut encoded = String::new();
+        match self.data() {
+            Some(RawData::Binary(data)) => {
+                encoded.push(V4_BINARY_PREFIX);
+                encoded.push_str(&general_purpose::STANDARD.encode(data));
+            }
+            Some(RawData::Text(text)) => {
+                encoded.push(self._type().clone().into());
+                encoded.push_str(text);
+            }
+            None => encoded.push(self._type().clone().into()),
+        }
+        encoded
+    }
+
+    /// Encodes a payload of packets in the Engine.IO v4 text encoding, separated by `\x1e`.
+    pub fn encode_payload_v4(packets: Vec<Self>) -> String {
+        let mut payload = String::new();
+        for (i, packet) in packets.iter().enumerate() {
+            if i > 0 {
+                payload.push(RECORD_SEPARATOR);
+            }
+            payload.push_str(&packet.encode_v4());
+        }
+        payload
+    }
+}
@@ -1,4 +1,4 @@
-use std::fmt;
+use core::fmt;
 use base64::DecodeError;
 
 use crate::protocol::PacketError;
@@ -0,0 +1,147 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::{
+    Compression,
+    Packet,
+    PacketType,
+    RawData,
+    DecodingError,
+};
+
+/// Handshake data carried by the `Open` packet.
+/// Serialized as JSON: {"sid":"..","upgrades":[..],"pingInterval":..,"pingTimeout":..,"maxPayload":..}
+/// plus `"compression":[..]` and `"dictionary":..` when the server compresses packets,
+/// `"key":".."` when the session is encrypted, and `"pid":".."` when it can be resumed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Handshake {
+    /// The session id.
+    sid: String,
+    /// Transports the session may upgrade to.
+    upgrades: Vec<String>,
+    /// Interval between server pings, in milliseconds.
+    ping_interval: u64,
+    /// Time allowed for a pong reply, in milliseconds.
+    ping_timeout: u64,
+    /// Maximum number of bytes per payload.
+    max_payload: u64,
+    /// Compression algorithms the server accepts, most preferred first; the server compresses with the first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    compression: Vec<Compression>,
+    /// Id of the zstd dictionary both sides use with `Compression::Zstd`, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dictionary: Option<u32>,
+    /// The server's ephemeral X25519 public key, base64url-encoded, if the session is encrypted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+    /// Private session id a client presents to resume the session; unlike `sid`, it never appears in URLs or cookies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pid: Option<String>,
+}
+
+impl Handshake {
+    /// Creates a new handshake.
+    pub fn new(sid: String, upgrades: Vec<String>, ping_interval: u64, ping_timeout: u64, max_payload: u64) -> Self {
+        Self {
+            sid,
+            upgrades,
+            ping_interval,
+            ping_timeout,
+            max_payload,
+            compression: Vec::new(),
+            dictionary: None,
+            key: None,
+            pid: None,
+        }
+    }
+
+    /// Sets the compression algorithms the server accepts and the zstd dictionary id.
+    pub fn with_compression(mut self, compression: Vec<Compression>, dictionary: Option<u32>) -> Self {
+        self.compression = compression;
+        self.dictionary = dictionary;
+        self
+    }
+
+    /// Sets the server's public key for an encrypted session.
+    pub fn with_key(mut self, key: String) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Sets the private session id of a recoverable session.
+    pub fn with_pid(mut self, pid: String) -> Self {
+        self.pid = Some(pid);
+        self
+    }
+
+    /// Returns the session id.
+    pub fn sid(&self) -> &str {
+        &self.sid
+    }
+
+    /// Returns the transports the session may upgrade to.
+    pub fn upgrades(&self) -> &[String] {
+        &self.upgrades
+    }
+
+    /// Returns the ping interval in milliseconds.
+    pub fn ping_interval(&self) -> u64 {
+        self.ping_interval
+    }
+
+    /// Returns the ping timeout in milliseconds.
+    pub fn ping_timeout(&self) -> u64 {
+        self.ping_timeout
+    }
+
+    /// Returns the maximum payload size in bytes.
+    pub fn max_payload(&self) -> u64 {
+        self.max_payload
+    }
+
+    /// Returns the compression algorithms the server accepts, most preferred first.
+    pub fn compression(&self) -> &[Compression] {
+        &self.compression
+    }
+
+    /// Returns the id of the zstd dictionary, if any.
+    pub fn dictionary(&self) -> Option<u32> {
+        self.dictionary
+    }
+
+    /// Returns the server's public key, if the session is encrypted.
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// Returns the private session id, if the session can be resumed.
+    pub fn pid(&self) -> Option<&str> {
+        self.pid.as_deref()
+    }
+
+    /// Wraps the handshake in an `Open` packet.
+    pub fn to_packet(&self) -> Packet {
+        let mut packet = Packet::new(PacketType::Open);
+        let json = serde_json::to_string(self).unwrap_or_default();
+        // Handshake JSON is far below MAX_PACKET_SIZE.
+        packet.with_data(RawData::Text(json)).ok();
+        packet
+    }
+
+    /// Extracts the handshake from an `Open` packet.
+    pub fn from_packet(packet: &Packet) -> Result<Self, DecodingError> {
+        if packet._type() != &PacketType::Open {
+            return Err(DecodingError::InvalidFormat);
+        }
+        match packet.data() {
+            Some(RawData::Text(json)) => serde_json::from_str(json)
+                .map_err(|_| DecodingError::InvalidFormat),
+            Some(RawData::Binary(bytes)) => serde_json::from_slice(bytes)
+                .map_err(|_| DecodingError::InvalidFormat),
+            None => Err(DecodingError::MissingField),
+        }
+    }
+}
@@ -1,18 +1,26 @@
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
 mod constants;
 mod decoding;
 mod encoding;
 mod error;
+mod handshake;
 mod packet;
 
 #[cfg(test)]
-mod tests;
+pub(crate) mod tests;
 
 pub use error::{DecodingError, EncodingError};
 pub use packet::{
-    error::PacketError, options::PacketOptions, types::PacketType, Packet, MAX_PACKET_SIZE,
+    compression::Compression, error::PacketError, options::PacketOptions, signature::Signature, types::PacketType, Packet,
+    MAX_PACKET_SIZE,
 };
+pub use handshake::Handshake;
 
-// encoding_stream::PacketEncoderStream,
-// decoding_stream::PacketDecoderStream,
+#[cfg(feature = "std")]
+pub use encoding::encoded::EncodedPacket;
+pub use encoding::payload::PayloadBuilder;
+pub use encoding::stream::PacketEncoderStream;
+pub use decoding::stream::PacketDecoderStream;
 
 pub use constants::{BinaryType, RawData};
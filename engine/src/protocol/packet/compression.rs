@@ -0,0 +1,70 @@
+use core::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::PacketError;
+
+/// Compression algorithm applied to a packet's data, identified in the options header.
+/// Algorithms are named in lowercase in the handshake, e.g. `"zstd"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// Raw DEFLATE.
+    Deflate = 1,
+    /// DEFLATE in a gzip wrapper.
+    Gzip = 2,
+    /// Zstandard, optionally with a shared dictionary.
+    Zstd = 3,
+    /// Brotli.
+    Brotli = 4,
+}
+
+impl Compression {
+    /// Every algorithm, in the order servers prefer them by default.
+    pub const ALL: [Compression; 4] = [Compression::Zstd, Compression::Brotli, Compression::Gzip, Compression::Deflate];
+
+    /// Returns the algorithm's name, as used in the handshake.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Compression::Deflate => "deflate",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Brotli => "brotli",
+        }
+    }
+}
+
+impl TryFrom<&str> for Compression {
+    type Error = PacketError;
+
+    fn try_from(s: &str) -> Result<Self, PacketError> {
+        match s {
+            "deflate" => Ok(Self::Deflate),
+            "gzip" => Ok(Self::Gzip),
+            "zstd" => Ok(Self::Zstd),
+            "brotli" => Ok(Self::Brotli),
+            _ => Err(PacketError::InvalidPacketOptions),
+        }
+    }
+}
+
+impl TryFrom<u8> for Compression {
+    type Error = PacketError;
+
+    fn try_from(id: u8) -> Result<Self, PacketError> {
+        match id {
+            1 => Ok(Self::Deflate),
+            2 => Ok(Self::Gzip),
+            3 => Ok(Self::Zstd),
+            4 => Ok(Self::Brotli),
+            _ => Err(PacketError::InvalidPacketOptions),
+        }
+    }
+}
+
+impl From<Compression> for u8 {
+    fn from(compression: Compression) -> Self {
+        compression as u8
+    }
+}
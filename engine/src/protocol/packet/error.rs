@@ -12,9 +12,9 @@ pub enum PacketError {
 }
 
 
-impl std::error::Error for PacketError {}
-impl std::fmt::Display for PacketError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::error::Error for PacketError {}
+impl core::fmt::Display for PacketError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             PacketError::DataTooLarge => write!(f, "Packet data exceeds maximum allowed size"),
             PacketError::InvalidPacketType => write!(f, "Packet type is invalid or unknown"),
@@ -1,7 +1,11 @@
+pub(crate) mod compression;
 pub(crate) mod options;
+pub(crate) mod signature;
 pub(crate) mod types;
 pub(crate) mod error;
 
+use alloc::string::ToString;
+
 use crate::protocol::RawData;
 use options::PacketOptions;
 use types::PacketType;
@@ -1,25 +1,28 @@
-use crate::protocol::PacketError;
+use crate::protocol::{Compression, PacketError, Signature};
 
 /// Options for packet transmission. "Packet Headers"
 #[derive(PartialEq, Eq, Debug, Default, Copy, Clone)]
 pub struct PacketOptions {
-    /// Whether the packet should be compressed.
-    compress: bool,
+    /// Algorithm the packet data is compressed with, if any.
+    compression: Option<Compression>,
     /// Whether the packet should be encrypted.
     encrypt: bool,
     /// The sequence number of the packet (for chunked transfer).
     sequence: Option<u16>,
     /// The total number of chunks in the packet (for chunked transfer).
     total_chunks: Option<u16>,
+    /// Signature over the packet data, if signed.
+    signature: Option<Signature>,
 }
 
 impl PacketOptions {
     /// Creates a new `PacketOptions` instance with specified parameters.
-    pub fn new(compress: bool, encrypt: bool, sequence: Option<u16>, total_chunks: Option<u16>) -> Result<Self, PacketError> {
-        let mut options = Self::default();
-
-        options.compress = compress;
-        options.encrypt = encrypt;
+    pub fn new(compression: Option<Compression>, encrypt: bool, sequence: Option<u16>, total_chunks: Option<u16>) -> Result<Self, PacketError> {
+        let mut options = Self {
+            compression,
+            encrypt,
+            ..Self::default()
+        };
         if let (Some(seq), Some(total)) = (sequence, total_chunks) {
             options.with_chunking(seq, total)?;
         } else if sequence.is_some() || total_chunks.is_some() {
@@ -30,12 +33,17 @@ impl PacketOptions {
 
     /// Returns whether compression is enabled.
     pub fn compress(&self) -> bool {
-        self.compress
+        self.compression.is_some()
+    }
+
+    /// Returns the compression algorithm, if any.
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
     }
 
-    /// Enables compression for the packet.
-    pub fn with_compression(mut self) -> Self {
-        self.compress = true;
+    /// Marks the packet data as compressed with `compression`.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
         self
     }
 
@@ -50,6 +58,17 @@ impl PacketOptions {
         self
     }
 
+    /// Returns the signature over the packet data, if signed.
+    pub fn signature(&self) -> Option<&Signature> {
+        self.signature.as_ref()
+    }
+
+    /// Attaches a signature over the packet data.
+    pub fn with_signature(mut self, signature: Signature) -> Self {
+        self.signature = Some(signature);
+        self
+    }
+
     /// Returns the sequence number if chunking is enabled.
     pub fn sequence(&self) -> Option<u16> {
         self.sequence
@@ -63,7 +82,7 @@ impl PacketOptions {
     /// Sets chunking information for the packet.
     pub fn with_chunking(&mut self, sequence: u16, total_chunks: u16) -> Result<(), PacketError> {
         if sequence > total_chunks || sequence == 0 || total_chunks == 0 {
-            eprintln!("Invalid chunking parameters");
+            warn!(sequence, total_chunks, "invalid chunking parameters");
             return Err(PacketError::InvalidChunkingParameters);
         }
 
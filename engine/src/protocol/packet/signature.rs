@@ -0,0 +1,37 @@
+/// An Ed25519 signature over a `Message` packet's data, carried in the options header.
+/// The key id tells the receiver which public key to verify it with, and the counter orders the signer's messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Signature {
+    /// Id of the signing key, resolved to a public key by the receiver.
+    key_id: u32,
+    /// The signer's message counter, covered by the signature so receivers can refuse repeats.
+    counter: u64,
+    /// The signature itself.
+    bytes: [u8; Signature::LEN],
+}
+
+impl Signature {
+    /// Length of an Ed25519 signature in bytes.
+    pub const LEN: usize = 64;
+
+    /// Creates a signature made with the key `key_id` over the signer's `counter`th message.
+    pub fn new(key_id: u32, counter: u64, bytes: [u8; Signature::LEN]) -> Self {
+        Self { key_id, counter, bytes }
+    }
+
+    /// Returns the id of the signing key.
+    pub fn key_id(&self) -> u32 {
+        self.key_id
+    }
+
+    /// Returns the signer's message counter.
+    pub fn counter(&self) -> u64 {
+        self.counter
+    }
+
+    /// Returns the signature bytes.
+    pub fn bytes(&self) -> &[u8; Signature::LEN] {
+        &self.bytes
+    }
+}
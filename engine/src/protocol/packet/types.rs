@@ -1,10 +1,11 @@
-use std::convert::TryFrom;
+use core::convert::TryFrom;
 
 use crate::protocol::PacketError;
 
 /// Represents the type of packet.
 /// Each variant corresponds to a specific packet type in the protocol.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum PacketType {
     /// Open Connection.
     Open = 0,
@@ -1,9 +1,18 @@
 #[cfg(test)]
 mod options;
 
+#[cfg(test)]
+mod stream;
+
+#[cfg(test)]
+mod v4;
+
 use base64::{Engine as _, engine::general_purpose};
 use crate::protocol::{
+    Compression,
+    DecodingError,
     Packet,
+    PacketError,
     PacketOptions,
     PacketType,
     RawData,
@@ -41,7 +50,7 @@ fn decode_packet_with_options_no_data_binary() {
     let encoded = RawData::Binary(vec![PacketType::Message as u8, 1, 0, 1, 1, 0, 0, 0, 0]);
     let decoded = Packet::decode(encoded.clone()).unwrap();
     let mut expected = Packet::new(PacketType::Message);
-    let opts = PacketOptions::default().with_compression().with_encryption();
+    let opts = PacketOptions::default().with_compression(Compression::Deflate).with_encryption();
     expected.with_options(opts);
     assert_eq!(decoded, expected);
 }
@@ -51,7 +60,7 @@ fn decode_packet_with_options_no_data_text() {
     let encoded = RawData::Text(format!("{}101:1:0:0", char::from(PacketType::Message)));
     let decoded = Packet::decode(encoded.clone()).unwrap();
     let mut expected = Packet::new(PacketType::Message);
-    let opts = PacketOptions::default().with_compression().with_encryption();
+    let opts = PacketOptions::default().with_compression(Compression::Deflate).with_encryption();
     expected.with_options(opts);
     assert_eq!(decoded, expected);
 }
@@ -76,7 +85,7 @@ fn decode_packet_with_small_binary_data() {
 
 #[test]
 fn decode_packet_with_small_binary_data_cross_encoding() {
-    let base64 = general_purpose::URL_SAFE.encode(&[1, 2, 3]);
+    let base64 = general_purpose::STANDARD.encode([1, 2, 3]);
     let encoded = RawData::Text(format!("{}01-b{}", char::from(PacketType::Message), base64));
     let decoded = Packet::decode(encoded.clone()).unwrap();
     let (expected, _) = small_data_packet(true);
@@ -126,7 +135,7 @@ fn decode_packet_with_large_binary_data() {
 
 #[test]
 fn decode_packet_with_large_binary_data_cross_encoding() {
-    let base64 = general_purpose::URL_SAFE.encode(&vec![42; 1024]);
+    let base64 = general_purpose::STANDARD.encode(vec![42; 1024]);
     let encoded = RawData::Text(format!("{}01-b{}", char::from(PacketType::Message), base64));
     let decoded = Packet::decode(encoded.clone()).unwrap();
     let (expected, _) = large_data_packet(true);
@@ -156,7 +165,7 @@ fn decode_packet_with_large_text_data_cross_encoding() {
 fn packet_with_options_and_data(binary: bool) -> (Packet, RawData) {
     let mut packet = Packet::new(PacketType::Message);
 
-    let mut opts = PacketOptions::default().with_compression();
+    let mut opts = PacketOptions::default().with_compression(Compression::Deflate);
     opts.with_chunking(2, 4).ok();
     packet.with_options(opts);
 
@@ -179,7 +188,7 @@ fn decode_packet_with_options_and_data_binary() {
 
 #[test]
 fn decode_packet_with_options_and_data_binary_cross_encoding() {
-    let base64 = general_purpose::URL_SAFE.encode(&[9, 8, 7]);
+    let base64 = general_purpose::STANDARD.encode([9, 8, 7]);
     let encoded = RawData::Text(format!("{}111:0:2:4-b{}", char::from(PacketType::Message), base64));
     let decoded = Packet::decode(encoded.clone()).unwrap();
     let (expected, _) = packet_with_options_and_data(true);
@@ -197,7 +206,7 @@ fn decode_packet_with_options_and_data_text() {
 #[test]
 fn decode_packet_with_options_and_data_text_cross_encoding() {
     let mut expected = Packet::new(PacketType::Message);
-    let mut opts = PacketOptions::default().with_compression();
+    let mut opts = PacketOptions::default().with_compression(Compression::Deflate);
     opts.with_chunking(2, 4).ok();
     expected.with_options(opts);
     let data = RawData::Text("xyz".to_string());
@@ -225,3 +234,46 @@ fn decode_packet_over_data_limit_text() {
     assert!(result.is_err());
 }
 
+
+#[test]
+fn decode_truncated_binary_data_header() {
+    let encoded = RawData::Binary(vec![PacketType::Message as u8, 0, 1]);
+    assert_eq!(Packet::decode(encoded), Err(DecodingError::MissingField));
+}
+
+#[test]
+fn decode_short_multibyte_text() {
+    // Three bytes, but a single character.
+    assert_eq!(Packet::decode(RawData::Text("€".into())), Err(DecodingError::Packet(PacketError::InvalidPacketType)));
+    assert_eq!(Packet::decode(RawData::Text("4é".into())), Err(DecodingError::InvalidFormat));
+    assert_eq!(Packet::decode(RawData::Text("40".into())), Err(DecodingError::MissingField));
+}
+
+#[test]
+fn decode_text_options_with_largest_chunking() {
+    let mut expected = Packet::new(PacketType::Message);
+    let mut opts = PacketOptions::default().with_compression(Compression::Deflate).with_encryption();
+    opts.with_chunking(65535, 65535).unwrap();
+    expected.with_options(opts);
+    let decoded = Packet::decode(expected.clone().encode(false)).unwrap();
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+fn decode_text_payload_with_multibyte_data() {
+    // "401-t€" is 8 bytes but 6 characters.
+    let encoded = RawData::Text("00000008401-t€0000000340000000007401-té".into());
+    let mut euro = Packet::new(PacketType::Message);
+    euro.with_data(RawData::Text("€".into())).unwrap();
+    let mut accent = Packet::new(PacketType::Message);
+    accent.with_data(RawData::Text("é".into())).unwrap();
+    let decoded = Packet::decode_payload(encoded).unwrap();
+    assert_eq!(decoded, vec![euro, Packet::new(PacketType::Message), accent]);
+}
+
+#[test]
+fn decode_text_payload_rejects_split_character() {
+    for encoded in ["00000006401-t€", "00000007401-t€", "0000+005401-t", "0000"] {
+        assert_eq!(Packet::decode_payload(RawData::Text(encoded.into())), Err(DecodingError::PayloadDataMismatch), "{}", encoded);
+    }
+}
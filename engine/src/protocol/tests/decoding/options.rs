@@ -1,4 +1,4 @@
-use crate::protocol::{DecodingError, PacketError, PacketOptions, RawData};
+use crate::protocol::{Compression, DecodingError, PacketError, PacketOptions, RawData, Signature};
 
 #[test]
 fn decode_default_binary() {
@@ -19,7 +19,7 @@ fn decode_compress_binary() {
     let raw = RawData::Binary(vec![1, 0, 0, 0, 0, 0]);
     let opts = PacketOptions::decode(raw).expect("should decode compress binary");
     let mut expected = PacketOptions::default();
-    expected = expected.with_compression();
+    expected = expected.with_compression(Compression::Deflate);
     assert_eq!(opts, expected);
 }
 
@@ -28,7 +28,7 @@ fn decode_compress_text() {
     let raw = RawData::Text("1:0:0:0".into());
     let opts = PacketOptions::decode(raw).expect("should decode compress text");
     let mut expected = PacketOptions::default();
-    expected = expected.with_compression();
+    expected = expected.with_compression(Compression::Deflate);
     assert_eq!(opts, expected);
 }
 
@@ -55,7 +55,7 @@ fn decode_compress_and_encrypt_binary() {
     let raw = RawData::Binary(vec![1, 1, 0, 0, 0, 0]);
     let opts = PacketOptions::decode(raw).expect("should decode compress+encrypt binary");
     let mut expected = PacketOptions::default();
-    expected = expected.with_compression().with_encryption();
+    expected = expected.with_compression(Compression::Deflate).with_encryption();
     assert_eq!(opts, expected);
 }
 
@@ -64,7 +64,7 @@ fn decode_compress_and_encrypt_text() {
     let raw = RawData::Text("1:1:0:0".into());
     let opts = PacketOptions::decode(raw).expect("should decode compress+encrypt text");
     let mut expected = PacketOptions::default();
-    expected = expected.with_compression().with_encryption();
+    expected = expected.with_compression(Compression::Deflate).with_encryption();
     assert_eq!(opts, expected);
 }
 
@@ -93,7 +93,7 @@ fn decode_full_options_binary() {
     let raw = RawData::Binary(vec![1, 1, 0, 48, 4, 66]);
     let opts = PacketOptions::decode(raw).expect("should decode full options binary");
     let mut expected = PacketOptions::default()
-        .with_compression()
+        .with_compression(Compression::Deflate)
         .with_encryption();
     expected.with_chunking(48, 1090).unwrap();
     assert_eq!(opts, expected);
@@ -104,7 +104,7 @@ fn decode_full_options_text() {
     let raw = RawData::Text("1:1:97:65535".into());
     let opts = PacketOptions::decode(raw).expect("should decode full options text");
     let mut expected = PacketOptions::default()
-        .with_compression()
+        .with_compression(Compression::Deflate)
         .with_encryption();
     expected.with_chunking(97, u16::MAX).unwrap();
     assert_eq!(opts, expected);
@@ -131,3 +131,76 @@ fn decode_invalid_text_format() {
         DecodingError::Packet(PacketError::InvalidPacketOptions)
     ));
 }
+
+#[test]
+fn decode_compression_algorithm_ids() {
+    let raw = RawData::Binary(vec![3, 0, 0, 0, 0, 0]);
+    let opts = PacketOptions::decode(raw).unwrap();
+    assert_eq!(opts.compression(), Some(Compression::Zstd));
+
+    let raw = RawData::Text("4:0:0:0".into());
+    let opts = PacketOptions::decode(raw).unwrap();
+    assert_eq!(opts.compression(), Some(Compression::Brotli));
+}
+
+#[test]
+fn decode_unknown_compression_algorithm() {
+    for raw in [
+        RawData::Binary(vec![5, 0, 0, 0, 0, 0]),
+        RawData::Text("5:0:0:0".into()),
+        RawData::Text("01:0:0:0".into()),
+    ] {
+        assert_eq!(
+            PacketOptions::decode(raw),
+            Err(DecodingError::Packet(PacketError::InvalidPacketOptions))
+        );
+    }
+}
+
+#[test]
+fn decode_signed_binary() {
+    let mut raw = vec![0, 3, 0, 0, 0, 0, 0, 0, 1, 2, 0, 0, 0, 0, 0, 0, 0, 9];
+    raw.extend([7; Signature::LEN]);
+    let opts = PacketOptions::decode(RawData::Binary(raw)).expect("should decode signed binary");
+    let expected = PacketOptions::default()
+        .with_encryption()
+        .with_signature(Signature::new(258, 9, [7; Signature::LEN]));
+    assert_eq!(opts, expected);
+}
+
+#[test]
+fn decode_signed_text() {
+    let signature = "Bw".repeat(43);
+    let raw = RawData::Text(format!("0:0:0:0:258:9:{}", &signature[..86]));
+    let opts = PacketOptions::decode(raw).expect("should decode signed text");
+    assert_eq!(opts.signature().map(Signature::key_id), Some(258));
+    assert_eq!(opts.signature().map(Signature::counter), Some(9));
+}
+
+#[test]
+fn decode_invalid_signature() {
+    let mut truncated = vec![0, 2, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1];
+    truncated.extend([7; Signature::LEN - 1]);
+    let mut unsigned_with_block = vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1];
+    unsigned_with_block.extend([7; Signature::LEN]);
+    let mut without_counter = vec![0, 2, 0, 0, 0, 0, 0, 0, 0, 1];
+    without_counter.extend([7; Signature::LEN]);
+    for raw in [
+        RawData::Binary(vec![0, 2, 0, 0, 0, 0]),
+        RawData::Binary(vec![0, 4, 0, 0, 0, 0]),
+        RawData::Binary(truncated),
+        RawData::Binary(unsigned_with_block),
+        RawData::Binary(without_counter),
+        RawData::Text("0:0:0:0:1".into()),
+        RawData::Text(format!("0:0:0:0:1:{}", "A".repeat(86))),
+        RawData::Text("0:0:0:0:x:0:AAAA".into()),
+        RawData::Text(format!("0:0:0:0:1:x:{}", "A".repeat(86))),
+        RawData::Text("0:0:0:0:1:0:AAAA".into()),
+        RawData::Text(format!("0:0:0:0:1:0:{}", "*".repeat(86))),
+    ] {
+        assert_eq!(
+            PacketOptions::decode(raw),
+            Err(DecodingError::Packet(PacketError::InvalidPacketOptions))
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use futures::{stream, FutureExt, StreamExt};
+
+use crate::protocol::{
+    DecodingError,
+    Packet,
+    PacketDecoderStream,
+    PacketType,
+    RawData,
+};
+
+fn packets() -> Vec<Packet> {
+    let mut text = Packet::new(PacketType::Message);
+    text.with_data(RawData::Text("x".repeat(300))).unwrap();
+    let mut binary = Packet::new(PacketType::Message);
+    binary.with_data(RawData::Binary(vec![7; 70_000])).unwrap();
+    vec![Packet::new(PacketType::Ping), text, binary, Packet::new(PacketType::Close)]
+}
+
+fn frames() -> Vec<u8> {
+    packets().into_iter().flat_map(Packet::encode_frame).collect()
+}
+
+async fn decode_chunks(chunks: Vec<Vec<u8>>) -> Vec<Packet> {
+    PacketDecoderStream::new(stream::iter(chunks))
+        .map(Result::unwrap)
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn decodes_one_frame_per_chunk() {
+    let chunks = packets().into_iter().map(Packet::encode_frame).collect();
+    assert_eq!(decode_chunks(chunks).await, packets());
+}
+
+#[tokio::test]
+async fn decodes_all_frames_in_a_single_chunk() {
+    assert_eq!(decode_chunks(vec![frames()]).await, packets());
+}
+
+#[tokio::test]
+async fn decodes_frames_split_byte_by_byte() {
+    let small = packets()[..2].to_vec();
+    let chunks = small.iter()
+        .cloned()
+        .flat_map(Packet::encode_frame)
+        .map(|b| vec![b])
+        .collect();
+    assert_eq!(decode_chunks(chunks).await, small);
+}
+
+#[tokio::test]
+async fn decodes_frames_split_across_chunks() {
+    let chunks = frames().chunks(1000).map(<[u8]>::to_vec).collect();
+    assert_eq!(decode_chunks(chunks).await, packets());
+}
+
+#[tokio::test]
+async fn truncated_frame_ends_stream() {
+    let mut bytes = frames();
+    bytes.truncate(bytes.len() - 1);
+    let decoded = decode_chunks(vec![bytes]).await;
+    assert_eq!(decoded, packets()[..3].to_vec());
+}
+
+#[tokio::test]
+async fn invalid_frame_yields_error() {
+    let chunks = vec![vec![3, 7, 0, 0]];
+    let decoded: Vec<_> = PacketDecoderStream::new(stream::iter(chunks)).collect().await;
+    assert_eq!(decoded.len(), 1);
+    assert!(decoded[0].is_err());
+}
+
+#[test]
+fn buffered_frames_decode_without_polling_again() {
+    // The inner stream never yields after the first chunk, so every packet must come from the buffer.
+    let chunks = stream::iter(vec![frames()]).chain(stream::pending());
+    let mut decoder = PacketDecoderStream::new(chunks);
+    for packet in packets() {
+        assert_eq!(decoder.next().now_or_never(), Some(Some(Ok(packet))));
+    }
+    assert_eq!(decoder.next().now_or_never(), None);
+}
+
+#[tokio::test]
+async fn oversized_frame_ends_stream() {
+    let mut bytes = vec![127];
+    bytes.extend_from_slice(&u64::MAX.to_be_bytes());
+    bytes.extend(Packet::new(PacketType::Ping).encode_frame());
+    let decoded: Vec<_> = PacketDecoderStream::new(stream::iter(vec![bytes])).collect().await;
+    assert_eq!(decoded, vec![Err(DecodingError::InvalidFormat)]);
+}
@@ -0,0 +1,47 @@
+use crate::protocol::{DecodingError, Packet, PacketError, PacketType, RawData};
+
+fn message(data: RawData) -> Packet {
+    let mut packet = Packet::new(PacketType::Message);
+    packet.with_data(data).unwrap();
+    packet
+}
+
+#[test]
+fn decodes_packets_with_and_without_data() {
+    assert_eq!(Packet::decode_v4("6").unwrap(), Packet::new(PacketType::Noop));
+    assert_eq!(Packet::decode_v4("4hello").unwrap(), message(RawData::Text("hello".into())));
+    assert_eq!(Packet::decode_v4("bAQIDBA==").unwrap(), message(RawData::Binary(vec![1, 2, 3, 4])));
+
+    let mut probe = Packet::new(PacketType::Ping);
+    probe.with_data(RawData::Text("probe".into())).unwrap();
+    assert_eq!(Packet::decode_v4("2probe").unwrap(), probe);
+}
+
+#[test]
+fn rejects_malformed_packets() {
+    assert!(matches!(Packet::decode_v4(""), Err(DecodingError::MissingField)));
+    assert!(matches!(Packet::decode_v4("abc"), Err(DecodingError::Packet(PacketError::InvalidPacketType))));
+    assert!(matches!(Packet::decode_v4("b!!"), Err(DecodingError::Base64(_))));
+}
+
+#[test]
+fn payload_sizes_include_separators() {
+    let payload = Packet::decode_payload_v4_sized("4€\x1ebAQIDBA==\x1e3").unwrap();
+    assert_eq!(payload, [
+        (message(RawData::Text("€".into())), 5),
+        (message(RawData::Binary(vec![1, 2, 3, 4])), 10),
+        (Packet::new(PacketType::Pong), 1),
+    ]);
+    assert!(Packet::decode_payload_v4("").unwrap().is_empty());
+    assert!(Packet::decode_payload_v4("4a\x1ez").is_err());
+}
+
+#[test]
+fn round_trips_through_the_v4_encoding() {
+    let packets = vec![
+        message(RawData::Text("one".into())),
+        message(RawData::Binary(vec![0, 255])),
+        Packet::new(PacketType::Close),
+    ];
+    assert_eq!(Packet::decode_payload_v4(&Packet::encode_payload_v4(packets.clone())).unwrap(), packets);
+}
@@ -0,0 +1,63 @@
+use crate::protocol::{EncodedPacket, Packet, PacketType, PayloadBuilder, RawData};
+use crate::protocol::tests::message;
+
+#[test]
+fn encodings_match_packet() {
+    for data in [RawData::Text("héllo".into()), RawData::Binary(vec![0, 1, 255])] {
+        let packet = message(data);
+        let encoded = EncodedPacket::new(packet.clone());
+        assert_eq!(encoded.text(), packet.encode_text());
+        assert_eq!(encoded.binary(), packet.encode_binary());
+        assert_eq!(encoded.len(false), packet.encode_text().len());
+        assert_eq!(encoded.len(true), packet.encode_binary().len());
+        assert_eq!(encoded._type(), &PacketType::Message);
+    }
+}
+
+#[test]
+fn clones_share_encodings() {
+    let encoded = EncodedPacket::from(message(RawData::Text("broadcast".into())));
+    let clone = encoded.clone();
+    assert!(std::ptr::eq(encoded.text(), clone.text()));
+    assert!(std::ptr::eq(encoded.binary(), clone.binary()));
+}
+
+#[test]
+fn equality_follows_packet() {
+    let a = EncodedPacket::new(message(RawData::Text("a".into())));
+    assert_eq!(a, a.clone());
+    assert_eq!(a, EncodedPacket::new(message(RawData::Text("a".into()))));
+    assert_ne!(a, EncodedPacket::new(message(RawData::Text("b".into()))));
+}
+
+#[test]
+fn builder_accepts_encoded_packets() {
+    for supports_binary in [true, false] {
+        let packets = vec![message(RawData::Text("a".into())), message(RawData::Binary(vec![1, 2]))];
+        let mut builder = PayloadBuilder::new(supports_binary, usize::MAX);
+        for packet in packets.iter().cloned() {
+            builder.try_push_encoded(packet.into()).unwrap();
+        }
+        assert_eq!(builder.finish(), Packet::encode_payload(packets, supports_binary));
+    }
+}
+
+#[test]
+fn builder_hands_back_encoded_packet_past_limit() {
+    let mut builder = PayloadBuilder::new(false, 20);
+    builder.try_push_encoded(message(RawData::Text("first".into())).into()).unwrap();
+    let second = EncodedPacket::new(message(RawData::Text("second".into())));
+    assert_eq!(builder.try_push_encoded(second.clone()), Err(second));
+    assert_eq!(builder.count(), 1);
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn frame_matches_encode_frame() {
+    for data in [RawData::Text("x".repeat(200)), RawData::Binary(vec![7; 70_000])] {
+        let packet = message(data);
+        let mut frame = Vec::new();
+        EncodedPacket::new(packet.clone()).write_frame(&mut frame);
+        assert_eq!(frame, packet.encode_frame());
+    }
+}
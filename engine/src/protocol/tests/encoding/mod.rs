@@ -1,8 +1,21 @@
+#[cfg(all(test, feature = "std"))]
+mod encoded;
+
 #[cfg(test)]
 mod options;
 
+#[cfg(test)]
+mod payload;
+
+#[cfg(test)]
+mod stream;
+
+#[cfg(test)]
+mod v4;
+
 use base64::{Engine as _, engine::general_purpose};
 use crate::protocol::{
+    Compression,
     Packet,
     PacketOptions,
     PacketType,
@@ -39,7 +52,7 @@ fn non_data_or_option_text_packets() {
 #[test]
 fn packet_with_options_no_data_binary() {
     let mut packet = Packet::new(PacketType::Message);
-    let opts = PacketOptions::default().with_compression().with_encryption();
+    let opts = PacketOptions::default().with_compression(Compression::Deflate).with_encryption();
     packet.with_options(opts);
 
     let encoded = packet.encode(true);
@@ -52,7 +65,7 @@ fn packet_with_options_no_data_binary() {
 #[test]
 fn packet_with_options_no_data_text() {
     let mut packet = Packet::new(PacketType::Message);
-    let opts = PacketOptions::default().with_compression().with_encryption();
+    let opts = PacketOptions::default().with_compression(Compression::Deflate).with_encryption();
     packet.with_options(opts);
 
     let encoded = packet.encode(false);
@@ -91,7 +104,7 @@ fn packet_with_small_binary_data_cross_encoding() {
     // Text encoding (should be base64)
     let encoded_text = packet.encode(false);
 
-    let base64 = general_purpose::URL_SAFE.encode(&[1, 2, 3]);
+    let base64 = general_purpose::STANDARD.encode([1, 2, 3]);
     assert_eq!(
         encoded_text,
         RawData::Text(format!("{}01-b{}", char::from(PacketType::Message), base64))
@@ -150,7 +163,7 @@ fn packet_with_large_binary_data_cross_encoding() {
     // Text encoding (should be base64)
     let encoded_text = packet.encode(false);
 
-    let base64 = general_purpose::URL_SAFE.encode(&vec![42; 1024]);
+    let base64 = general_purpose::STANDARD.encode(vec![42; 1024]);
     assert_eq!(
         encoded_text,
         RawData::Text(format!("{}01-b{}", char::from(PacketType::Message), base64))
@@ -184,7 +197,7 @@ fn packet_with_large_text_data_cross_encoding() {
 fn packet_with_options_and_data(binary: bool) -> Packet {
     let mut packet = Packet::new(PacketType::Message);
 
-    let mut opts = PacketOptions::default().with_compression();
+    let mut opts = PacketOptions::default().with_compression(Compression::Deflate);
     opts.with_chunking(2, 4).ok();
     packet.with_options(opts);
 
@@ -215,7 +228,7 @@ fn packet_with_options_and_data_binary_cross_encoding() {
     // Text encoding (should be base64)
     let encoded_text = packet.encode(false);
 
-    let base64 = general_purpose::URL_SAFE.encode(&[9, 8, 7]);
+    let base64 = general_purpose::STANDARD.encode([9, 8, 7]);
     assert_eq!(
         encoded_text,
         RawData::Text(format!("{}111:0:2:4-b{}", char::from(PacketType::Message), base64))
@@ -259,3 +272,11 @@ fn packet_over_data_limit_text() {
     let result = packet.with_data(data);
     assert!(result.is_err());
 }
+
+#[test]
+fn binary_data_text_encoding_uses_standard_base64() {
+    let mut packet = Packet::new(PacketType::Message);
+    packet.with_data(RawData::Binary(vec![0xfb, 0xff, 0xbf])).unwrap();
+    let encoded = packet.encode(false);
+    assert_eq!(encoded, RawData::Text(format!("{}01-b+/+/", char::from(PacketType::Message))));
+}
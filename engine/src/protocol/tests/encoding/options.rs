@@ -1,6 +1,8 @@
 use crate::protocol::{
+    Compression,
     RawData,
     PacketOptions,
+    Signature,
 };
 
 #[test]
@@ -17,14 +19,14 @@ fn default_text() {
 
 #[test]
 fn compress_binary() {
-    let opts = PacketOptions::default().with_compression();
+    let opts = PacketOptions::default().with_compression(Compression::Deflate);
     let encoded = opts.encode(true);
     assert_eq!(encoded, RawData::Binary(vec![1, 0, 0, 0, 0, 0]));
 }
 
 #[test]
 fn compress_text() {
-    let opts = PacketOptions::default().with_compression();
+    let opts = PacketOptions::default().with_compression(Compression::Deflate);
     let encoded = opts.encode(false);
     assert_eq!(encoded, RawData::Text("1:0:0:0".into()));
 }
@@ -45,7 +47,7 @@ fn encrypt_text() {
 
 #[test]
 fn compress_and_encrypt_binary() {
-    let opts = PacketOptions::default().with_compression().with_encryption();
+    let opts = PacketOptions::default().with_compression(Compression::Deflate).with_encryption();
     let encoded = opts.encode(true);
     assert_eq!(encoded, RawData::Binary(vec![1, 1, 0, 0, 0, 0]));
 }
@@ -53,7 +55,7 @@ fn compress_and_encrypt_binary() {
 #[test]
 fn compress_and_encrypt_text() {
     let opts = PacketOptions::default()
-      .with_compression()
+      .with_compression(Compression::Deflate)
       .with_encryption();
     let encoded = opts.encode(false);
     assert_eq!(encoded, RawData::Text("1:1:0:0".into()));
@@ -79,7 +81,7 @@ fn chunk_data_text() {
 #[test]
 fn full_options_binary() {
     let mut opts = PacketOptions::default()
-      .with_compression()
+      .with_compression(Compression::Deflate)
       .with_encryption();
     opts.with_chunking(48, 1090).ok();
     let encoded = opts.encode(true);
@@ -89,9 +91,26 @@ fn full_options_binary() {
 #[test]
 fn full_options_text() {
     let mut opts = PacketOptions::default()
-      .with_compression()
+      .with_compression(Compression::Deflate)
       .with_encryption();
     opts.with_chunking(97, u16::MAX).ok();
     let encoded = opts.encode(false);
     assert_eq!(encoded, RawData::Text("1:1:97:65535".into()));
-}
\ No newline at end of file
+}
+
+#[test]
+fn signed_binary() {
+    let opts = PacketOptions::default().with_signature(Signature::new(258, 9, [7; Signature::LEN]));
+    let mut expected = vec![0, 2, 0, 0, 0, 0, 0, 0, 1, 2, 0, 0, 0, 0, 0, 0, 0, 9];
+    expected.extend([7; Signature::LEN]);
+    assert_eq!(opts.encode(true), RawData::Binary(expected));
+}
+
+#[test]
+fn signed_text() {
+    let opts = PacketOptions::default()
+      .with_encryption()
+      .with_signature(Signature::new(258, 9, [0; Signature::LEN]));
+    let encoded = opts.encode(false);
+    assert_eq!(encoded, RawData::Text(format!("0:1:0:0:258:9:{}", "A".repeat(86))));
+}
@@ -0,0 +1,80 @@
+use crate::protocol::{Packet, PacketType, PayloadBuilder, RawData};
+
+fn messages(count: usize, size: usize) -> Vec<Packet> {
+    (0..count).map(|n| {
+        let mut packet = Packet::new(PacketType::Message);
+        packet.with_data(RawData::Text(n.to_string().repeat(size))).unwrap();
+        packet
+    }).collect()
+}
+
+#[test]
+fn builder_matches_encode_payload() {
+    for supports_binary in [true, false] {
+        let mut builder = PayloadBuilder::new(supports_binary, usize::MAX);
+        for packet in messages(5, 10) {
+            builder.try_push(packet).unwrap();
+        }
+        assert_eq!(builder.count(), 5);
+        assert_eq!(builder.finish(), Packet::encode_payload(messages(5, 10), supports_binary));
+    }
+}
+
+#[test]
+fn v4_builder_matches_encode_payload_v4() {
+    let mut builder = PayloadBuilder::v4(usize::MAX);
+    for packet in messages(5, 10) {
+        builder.try_push(packet).unwrap();
+    }
+    assert_eq!(builder.finish(), RawData::Text(Packet::encode_payload_v4(messages(5, 10))));
+
+    // Each v4 packet is 1 (type) + 10 (data) bytes, plus a separator after the first.
+    let mut builder = PayloadBuilder::v4(23);
+    let mut packets = messages(3, 10).into_iter();
+    builder.try_push(packets.next().unwrap()).unwrap();
+    builder.try_push(packets.next().unwrap()).unwrap();
+    assert_eq!(builder.len(), 23);
+    let third = packets.next().unwrap();
+    assert_eq!(builder.try_push(third.clone()), Err(third));
+}
+
+#[test]
+fn builder_rejects_packet_past_limit() {
+    // Each text packet is 8 (prefix) + 5 (header) + 10 (data) bytes.
+    let mut builder = PayloadBuilder::new(false, 50);
+    let mut packets = messages(3, 10).into_iter();
+    builder.try_push(packets.next().unwrap()).unwrap();
+    builder.try_push(packets.next().unwrap()).unwrap();
+    assert_eq!(builder.len(), 46);
+
+    let third = packets.next().unwrap();
+    assert_eq!(builder.try_push(third.clone()), Err(third));
+    assert_eq!(builder.count(), 2);
+}
+
+#[test]
+fn builder_accepts_oversized_first_packet() {
+    let mut builder = PayloadBuilder::new(true, 10);
+    assert!(builder.is_empty());
+    builder.try_push(messages(1, 100).remove(0)).unwrap();
+    assert!(builder.len() > 10);
+}
+
+#[test]
+fn encode_payloads_splits_at_limit() {
+    for supports_binary in [true, false] {
+        let payloads = Packet::encode_payloads(messages(10, 100), supports_binary, 250);
+        assert_eq!(payloads.len(), 5);
+        assert!(payloads.iter().all(|p| p.len() <= 250));
+
+        let decoded: Vec<_> = payloads.into_iter()
+            .flat_map(|p| Packet::decode_payload(p).unwrap())
+            .collect();
+        assert_eq!(decoded, messages(10, 100));
+    }
+}
+
+#[test]
+fn encode_payloads_empty() {
+    assert!(Packet::encode_payloads(Vec::new(), true, 100).is_empty());
+}
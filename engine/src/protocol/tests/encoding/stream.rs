@@ -0,0 +1,55 @@
+use futures::{stream, StreamExt};
+
+use crate::protocol::{
+    Packet,
+    PacketEncoderStream,
+    PacketType,
+    RawData,
+    constants::BINARY_MASK,
+};
+
+fn data_packet(data: RawData) -> Packet {
+    let mut packet = Packet::new(PacketType::Message);
+    packet.with_data(data).unwrap();
+    packet
+}
+
+#[test]
+fn short_frame_header() {
+    let frame = Packet::new(PacketType::Ping).encode_frame();
+    assert_eq!(frame, vec![3, PacketType::Ping as u8, 0, 0]);
+}
+
+#[test]
+fn binary_frame_header_is_masked() {
+    let frame = data_packet(RawData::Binary(vec![1, 2, 3])).encode_frame();
+    assert_eq!(frame[0], 7 | BINARY_MASK);
+    assert_eq!(frame.len(), 8);
+}
+
+#[test]
+fn extended_16_frame_header() {
+    let frame = data_packet(RawData::Text("x".repeat(200))).encode_frame();
+    let length = 4 + 200;
+    assert_eq!(&frame[..3], &[126, (length >> 8) as u8, length as u8]);
+    assert_eq!(frame.len(), 3 + length);
+}
+
+#[test]
+fn extended_64_frame_header() {
+    let frame = data_packet(RawData::Text("x".repeat(70_000))).encode_frame();
+    let length = 4 + 70_000u64;
+    assert_eq!(frame[0], 127);
+    assert_eq!(&frame[1..9], &length.to_be_bytes());
+    assert_eq!(frame.len(), 9 + length as usize);
+}
+
+#[tokio::test]
+async fn stream_encodes_each_packet() {
+    let packets = vec![Packet::new(PacketType::Ping), Packet::new(PacketType::Pong)];
+    let frames: Vec<_> = PacketEncoderStream::new(stream::iter(packets.clone()))
+        .collect()
+        .await;
+    let expected: Vec<_> = packets.into_iter().map(Packet::encode_frame).collect();
+    assert_eq!(frames, expected);
+}
@@ -0,0 +1,42 @@
+use crate::protocol::{Compression, Packet, PacketOptions, PacketType, RawData};
+
+#[test]
+fn packets_are_their_type_followed_by_their_data() {
+    assert_eq!(Packet::new(PacketType::Ping).encode_v4(), "2");
+    assert_eq!(Packet::new(PacketType::Close).encode_v4(), "1");
+
+    let mut probe = Packet::new(PacketType::Pong);
+    probe.with_data(RawData::Text("probe".into())).unwrap();
+    assert_eq!(probe.encode_v4(), "3probe");
+
+    let mut message = Packet::new(PacketType::Message);
+    message.with_data(RawData::Text("héllo".into())).unwrap();
+    assert_eq!(message.encode_v4(), "4héllo");
+}
+
+#[test]
+fn binary_messages_are_base64_behind_a_b() {
+    let mut message = Packet::new(PacketType::Message);
+    message.with_data(RawData::Binary(vec![1, 2, 3, 4])).unwrap();
+    assert_eq!(message.encode_v4(), "bAQIDBA==");
+}
+
+#[test]
+fn options_are_left_out() {
+    let mut message = Packet::new(PacketType::Message);
+    message.with_data(RawData::Text("hi".into())).unwrap();
+    message.with_options(PacketOptions::default().with_compression(Compression::Gzip));
+    assert_eq!(message.encode_v4(), "4hi");
+}
+
+#[test]
+fn payloads_are_joined_by_record_separators() {
+    let packets = ["€", "é"].map(|text| {
+        let mut packet = Packet::new(PacketType::Message);
+        packet.with_data(RawData::Text(text.into())).unwrap();
+        packet
+    });
+    assert_eq!(Packet::encode_payload_v4(packets.to_vec()), "4€\x1e4é");
+    assert_eq!(Packet::encode_payload_v4(vec![packets[0].clone()]), "4€");
+    assert_eq!(Packet::encode_payload_v4(Vec::new()), "");
+}
@@ -0,0 +1,78 @@
+use crate::protocol::{Compression, DecodingError, Handshake, Packet, PacketType, RawData};
+
+fn handshake() -> Handshake {
+    Handshake::new("abc123".into(), vec!["websocket".into()], 25000, 20000, 1_000_000)
+}
+
+#[test]
+fn to_packet_is_open_with_json() {
+    let packet = handshake().to_packet();
+    assert_eq!(packet._type(), &PacketType::Open);
+    assert_eq!(
+        packet.data(),
+        Some(&RawData::Text(
+            r#"{"sid":"abc123","upgrades":["websocket"],"pingInterval":25000,"pingTimeout":20000,"maxPayload":1000000}"#.into()
+        ))
+    );
+}
+
+#[test]
+fn compression_is_advertised_by_name() {
+    let handshake = handshake().with_compression(vec![Compression::Zstd, Compression::Gzip], Some(7));
+    let packet = handshake.to_packet();
+    assert_eq!(
+        packet.data(),
+        Some(&RawData::Text(
+            r#"{"sid":"abc123","upgrades":["websocket"],"pingInterval":25000,"pingTimeout":20000,"maxPayload":1000000,"compression":["zstd","gzip"],"dictionary":7}"#.into()
+        ))
+    );
+    assert_eq!(Handshake::from_packet(&packet), Ok(handshake));
+}
+
+#[test]
+fn key_is_sent_for_encrypted_sessions() {
+    let handshake = handshake().with_key("c2VydmVyLWtleQ".into());
+    let packet = handshake.to_packet();
+    assert_eq!(
+        packet.data(),
+        Some(&RawData::Text(
+            r#"{"sid":"abc123","upgrades":["websocket"],"pingInterval":25000,"pingTimeout":20000,"maxPayload":1000000,"key":"c2VydmVyLWtleQ"}"#.into()
+        ))
+    );
+    assert_eq!(Handshake::from_packet(&packet).unwrap().key(), Some("c2VydmVyLWtleQ"));
+}
+
+#[test]
+fn from_packet_round_trip() {
+    let packet = handshake().to_packet();
+    assert_eq!(Handshake::from_packet(&packet), Ok(handshake()));
+}
+
+#[test]
+fn from_packet_round_trip_through_encoding() {
+    for supports_binary in [true, false] {
+        let encoded = handshake().to_packet().encode(supports_binary);
+        let decoded = Packet::decode(encoded).unwrap();
+        assert_eq!(Handshake::from_packet(&decoded), Ok(handshake()));
+    }
+}
+
+#[test]
+fn from_packet_rejects_other_types() {
+    let mut packet = Packet::new(PacketType::Message);
+    packet.with_data(RawData::Text("{}".into())).unwrap();
+    assert_eq!(Handshake::from_packet(&packet), Err(DecodingError::InvalidFormat));
+}
+
+#[test]
+fn from_packet_requires_data() {
+    let packet = Packet::new(PacketType::Open);
+    assert_eq!(Handshake::from_packet(&packet), Err(DecodingError::MissingField));
+}
+
+#[test]
+fn from_packet_rejects_missing_fields() {
+    let mut packet = Packet::new(PacketType::Open);
+    packet.with_data(RawData::Text(r#"{"sid":"abc123"}"#.into())).unwrap();
+    assert_eq!(Handshake::from_packet(&packet), Err(DecodingError::InvalidFormat));
+}
@@ -5,4 +5,27 @@ mod packet;
 mod encoding;
 
 #[cfg(test)]
-mod decoding;
\ No newline at end of file
+mod decoding;
+
+#[cfg(test)]
+mod handshake;
+
+#[cfg(test)]
+mod round_trip;
+
+#[cfg(feature = "std")]
+use crate::protocol::{Packet, PacketType, RawData};
+
+/// Builds a `Message` packet carrying `data`, for tests across the crate.
+#[cfg(feature = "std")]
+pub(crate) fn message(data: RawData) -> Packet {
+    let mut packet = Packet::new(PacketType::Message);
+    packet.with_data(data).unwrap();
+    packet
+}
+
+/// Builds a `Message` packet carrying `text`.
+#[cfg(feature = "runtime")]
+pub(crate) fn text_message(text: &str) -> Packet {
+    message(RawData::Text(text.into()))
+}
@@ -4,12 +4,12 @@ mod types;
 #[cfg(test)]
 mod options;
 
-use crate::protocol::{RawData, Packet, PacketError, PacketOptions, PacketType, MAX_PACKET_SIZE};
+use crate::protocol::{Compression, RawData, Packet, PacketError, PacketOptions, PacketType, MAX_PACKET_SIZE};
 
 #[test]
 fn valid_full_packet_creation() {
     let packet_type = PacketType::Message;
-    let options = PacketOptions::default().with_compression();
+    let options = PacketOptions::default().with_compression(Compression::Deflate);
     let data = RawData::Binary(vec![1, 2, 3, 4, 5]);
 
     let mut packet = Packet::new(packet_type.clone());
@@ -40,7 +40,7 @@ fn valid_packet_creation_without_data() {
     let options = PacketOptions::default();
 
     let mut packet = Packet::new(packet_type.clone());
-    packet.with_options(options.clone());
+    packet.with_options(options);
 
     assert_eq!(packet._type(), &PacketType::Message);
     assert_eq!(packet.options(), Some(&options));
@@ -91,7 +91,7 @@ fn invalid_packet_creation_too_large() {
 fn packet_setters_and_getters() {
     let mut packet = Packet::new(PacketType::Ping);
     let options = PacketOptions::default();
-    packet.with_options(options.clone());
+    packet.with_options(options);
     assert_eq!(packet.options(), Some(&options));
 
     let data = RawData::Text("test".to_string());
@@ -107,7 +107,7 @@ fn packet_clone_validation() {
     let mut cloned_packet = packet.clone();
 
     assert_eq!(packet, cloned_packet);
-    cloned_packet.with_options(PacketOptions::default().with_compression());
+    cloned_packet.with_options(PacketOptions::default().with_compression(Compression::Deflate));
     assert_ne!(packet, cloned_packet);
 }
 
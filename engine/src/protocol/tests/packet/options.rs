@@ -1,4 +1,4 @@
-use crate::protocol::{PacketOptions, PacketError};
+use crate::protocol::{Compression, PacketOptions, PacketError};
 
 #[test]
 fn default_has_options_disabled() {
@@ -12,7 +12,7 @@ fn default_has_options_disabled() {
 #[test]
 fn enable_compression() {
     let opts = PacketOptions::default()
-      .with_compression();
+      .with_compression(Compression::Deflate);
     assert!(opts.compress());
     assert!(!opts.encrypt());
     assert_eq!(opts.sequence(), None);
@@ -32,7 +32,7 @@ fn enable_encryption() {
 #[test]
 fn enable_both_compression_and_encryption() {
     let opts = PacketOptions::default()
-      .with_compression()
+      .with_compression(Compression::Deflate)
       .with_encryption();
     assert!(opts.compress());
     assert!(opts.encrypt());
@@ -60,7 +60,7 @@ fn invalid_chunking_seq_gt_total() {
 #[test]
 fn valid_new_packet_options_1() {
     let opts = PacketOptions::new(
-      true,
+      Some(Compression::Deflate),
       true,
       Some(1),
       Some(2)
@@ -74,7 +74,7 @@ fn valid_new_packet_options_1() {
 #[test]
 fn valid_new_packet_options_2() {
     let opts = PacketOptions::new(
-      false,
+      None,
       true,
       None,
       None
@@ -88,7 +88,7 @@ fn valid_new_packet_options_2() {
 #[test]
 fn invalid_new_options_0_chunks() {
     let opts = PacketOptions::new(
-      true,
+      Some(Compression::Deflate),
       true,
       Some(0),
       Some(0)
@@ -99,7 +99,7 @@ fn invalid_new_options_0_chunks() {
 #[test]
 fn invalid_new_options_seq_gt_total() {
     let opts = PacketOptions::new(
-      true,
+      Some(Compression::Deflate),
       true,
       Some(21),
       Some(12)
@@ -110,7 +110,7 @@ fn invalid_new_options_seq_gt_total() {
 #[test]
 fn invalid_new_options_full_seq_empty_chunks() {
     let opts = PacketOptions::new(
-      true,
+      Some(Compression::Deflate),
       false,
       Some(12),
       None
@@ -119,11 +119,21 @@ fn invalid_new_options_full_seq_empty_chunks() {
 }
 
 #[test]
+#[allow(clippy::clone_on_copy)]
 fn options_are_copy_and_clone() {
-    let opts = PacketOptions::default().with_compression();
+    let opts = PacketOptions::default().with_compression(Compression::Deflate);
 
     let opts2 = opts;
     let opts3 = opts2.clone();
     assert_eq!(opts, opts2);
     assert_eq!(opts2, opts3);
-}
\ No newline at end of file
+}
+#[test]
+fn compression_ids_and_names_round_trip() {
+    for compression in Compression::ALL {
+        assert_eq!(Compression::try_from(u8::from(compression)), Ok(compression));
+        assert_eq!(Compression::try_from(compression.name()), Ok(compression));
+    }
+    assert_eq!(Compression::try_from(0u8), Err(PacketError::InvalidPacketOptions));
+    assert_eq!(Compression::try_from("lz4"), Err(PacketError::InvalidPacketOptions));
+}
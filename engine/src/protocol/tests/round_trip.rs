@@ -0,0 +1,87 @@
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use crate::protocol::{Compression, Packet, PacketOptions, PacketType, RawData, Signature};
+
+fn packet_type() -> impl Strategy<Value = PacketType> {
+    prop_oneof![
+        Just(PacketType::Open),
+        Just(PacketType::Close),
+        Just(PacketType::Ping),
+        Just(PacketType::Pong),
+        Just(PacketType::Message),
+        Just(PacketType::Upgrade),
+        Just(PacketType::Noop),
+        Just(PacketType::Error),
+    ]
+}
+
+fn options() -> impl Strategy<Value = PacketOptions> {
+    let chunking = prop::option::of((1..=u16::MAX).prop_flat_map(|total| (1..=total, Just(total))));
+    let compression = prop::option::of(prop::sample::select(Compression::ALL.to_vec()));
+    let signature = prop::option::of((any::<u32>(), any::<u64>(), vec(any::<u8>(), Signature::LEN)))
+        .prop_map(|signature| signature.map(|(key_id, counter, bytes)| Signature::new(key_id, counter, bytes.try_into().unwrap())));
+    (compression, any::<bool>(), chunking, signature).prop_map(|(compression, encrypt, chunking, signature)| {
+        let options = PacketOptions::new(compression, encrypt, chunking.map(|(sequence, _)| sequence), chunking.map(|(_, total)| total))
+            .unwrap();
+        match signature {
+            Some(signature) => options.with_signature(signature),
+            None => options,
+        }
+    })
+}
+
+/// Any string, with extra weight on the characters the text encodings treat specially and on multi-byte characters.
+fn text() -> impl Strategy<Value = String> {
+    prop_oneof![
+        any::<String>(),
+        "[-:0-9tb\u{1e}é€😀]{0,32}",
+    ]
+}
+
+fn data() -> impl Strategy<Value = RawData> {
+    prop_oneof![
+        text().prop_map(RawData::Text),
+        vec(any::<u8>(), 0..64).prop_map(RawData::Binary),
+    ]
+}
+
+fn packet() -> impl Strategy<Value = Packet> {
+    (packet_type(), prop::option::of(options()), prop::option::of(data())).prop_map(|(packet_type, options, data)| {
+        let mut packet = Packet::new(packet_type);
+        if let Some(options) = options {
+            packet.with_options(options);
+        }
+        if let Some(data) = data {
+            packet.with_data(data).unwrap();
+        }
+        packet
+    })
+}
+
+proptest! {
+    #[test]
+    fn options_round_trip(options in options(), binary in any::<bool>()) {
+        prop_assert_eq!(PacketOptions::decode(options.encode(binary)), Ok(options));
+    }
+
+    #[test]
+    fn packet_round_trips_as_text(packet in packet()) {
+        prop_assert_eq!(Packet::decode(packet.clone().encode(false)), Ok(packet));
+    }
+
+    #[test]
+    fn packet_round_trips_as_binary(packet in packet()) {
+        prop_assert_eq!(Packet::decode(packet.clone().encode(true)), Ok(packet));
+    }
+
+    #[test]
+    fn payload_round_trips_as_text(packets in vec(packet(), 0..8)) {
+        prop_assert_eq!(Packet::decode_payload(Packet::encode_payload(packets.clone(), false)), Ok(packets));
+    }
+
+    #[test]
+    fn payload_round_trips_as_binary(packets in vec(packet(), 0..8)) {
+        prop_assert_eq!(Packet::decode_payload(Packet::encode_payload(packets.clone(), true)), Ok(packets));
+    }
+}
@@ -0,0 +1,84 @@
+//! Runtime abstraction for the engine's background work.
+//!
+//! Sessions need to spawn their driver task, read a clock and sleep; everything else is runtime-neutral.
+//! The runtime is picked at compile time: `tokio` by default, or `smol` when built without `tokio`.
+//!
+//! Only the executor, timers and I/O are swapped. The engine still depends on `tokio` for its `sync` channels,
+//! `Notify` and `select!`, which run on any executor, so a `smol` build compiles `tokio` without starting its runtime.
+
+#[cfg(feature = "smol")]
+mod smol_runtime;
+#[cfg(feature = "tokio")]
+mod tokio_runtime;
+
+#[cfg(test)]
+mod tests;
+
+use std::future::Future;
+use std::pin::pin;
+use std::time::{Duration, Instant};
+
+use futures::future::{self, Either};
+
+#[cfg(feature = "smol")]
+pub use smol_runtime::{SmolRuntime, SmolSleep};
+#[cfg(feature = "tokio")]
+pub use tokio_runtime::TokioRuntime;
+
+#[cfg(not(any(feature = "tokio", feature = "smol")))]
+compile_error!("the `runtime` feature needs a runtime: enable `tokio` or `smol`");
+
+/// Runtime the engine runs on. With both runtimes enabled, `tokio` wins.
+#[cfg(feature = "tokio")]
+pub type DefaultRuntime = TokioRuntime;
+/// Runtime the engine runs on. With both runtimes enabled, `tokio` wins.
+#[cfg(all(feature = "smol", not(feature = "tokio")))]
+pub type DefaultRuntime = SmolRuntime;
+
+/// Task spawning and timers.
+pub trait Runtime: Send + Sync + 'static {
+    /// Future returned by `sleep_until`.
+    type Sleep: Future<Output = ()> + Send + 'static;
+
+    /// Runs a future to completion in the background.
+    fn spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static;
+
+    /// Returns the current time on the runtime's clock.
+    fn now() -> Instant;
+
+    /// Returns a future that completes at `deadline`.
+    fn sleep_until(deadline: Instant) -> Self::Sleep;
+}
+
+/// Spawns a task on the default runtime.
+pub(crate) fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    DefaultRuntime::spawn(future)
+}
+
+/// Returns the current time on the default runtime's clock.
+pub(crate) fn now() -> Instant {
+    DefaultRuntime::now()
+}
+
+/// Completes at `deadline`.
+pub(crate) fn sleep_until(deadline: Instant) -> <DefaultRuntime as Runtime>::Sleep {
+    DefaultRuntime::sleep_until(deadline)
+}
+
+/// Completes after `duration`.
+pub(crate) fn sleep(duration: Duration) -> <DefaultRuntime as Runtime>::Sleep {
+    sleep_until(now() + duration)
+}
+
+/// Runs `future` for at most `duration`. Returns `None` if it did not finish in time.
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    match future::select(pin!(future), pin!(sleep(duration))).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
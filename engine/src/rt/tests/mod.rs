@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use futures::channel::oneshot;
+
+use crate::rt::{self, Runtime};
+
+#[cfg(feature = "tokio")]
+#[tokio::test(start_paused = true)]
+async fn tokio_clock_follows_paused_time() {
+    let start = rt::now();
+    tokio::time::advance(Duration::from_secs(5)).await;
+    assert_eq!(rt::now() - start, Duration::from_secs(5));
+
+    rt::sleep(Duration::from_secs(60)).await;
+    assert!(rt::now() - start >= Duration::from_secs(65));
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test(start_paused = true)]
+async fn timeout_returns_output_or_none() {
+    use std::future::pending;
+
+    assert_eq!(rt::timeout(Duration::from_millis(10), async { 3 }).await, Some(3));
+    assert_eq!(rt::timeout(Duration::from_millis(10), pending::<()>()).await, None);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn spawned_tasks_run() {
+    use crate::rt::DefaultRuntime;
+
+    let (tx, rx) = oneshot::channel();
+    DefaultRuntime::spawn(async move {
+        let _ = tx.send(7);
+    });
+    assert_eq!(rx.await, Ok(7));
+}
+
+#[cfg(feature = "smol")]
+#[test]
+fn smol_runtime_spawns_and_sleeps() {
+    use crate::rt::SmolRuntime;
+
+    smol::block_on(async {
+        let (tx, rx) = oneshot::channel();
+        SmolRuntime::spawn(async move {
+            SmolRuntime::sleep_until(SmolRuntime::now() + Duration::from_millis(10)).await;
+            let _ = tx.send(7);
+        });
+        assert_eq!(rx.await, Ok(7));
+    });
+}
+
+/// Sessions run with no tokio runtime at all when smol is the default.
+#[cfg(all(feature = "smol", not(feature = "tokio")))]
+#[test]
+fn sessions_run_on_smol() {
+    use crate::protocol::RawData;
+    use crate::session::{Session, SessionConfig, SessionEvent};
+    use crate::transport::memory::MemoryTransport;
+
+    smol::block_on(async {
+        let config = SessionConfig::default()
+            .with_ping_interval(Duration::from_millis(20))
+            .with_ping_timeout(Duration::from_millis(20));
+        let (server, client) = MemoryTransport::pair();
+        let (server, client) = tokio::join!(Session::accept(server, &config), Session::connect(client));
+        let (mut server, mut client) = (server.unwrap(), client.unwrap());
+
+        // Outlive several heartbeats before exchanging a message.
+        rt::sleep(Duration::from_millis(100)).await;
+        server.send(RawData::Text("smol".into())).await.unwrap();
+        assert_eq!(client.recv().await, Some(SessionEvent::Message(RawData::Text("smol".into()))));
+        client.send(RawData::Text("back".into())).await.unwrap();
+        assert_eq!(server.recv().await, Some(SessionEvent::Message(RawData::Text("back".into()))));
+    });
+}
@@ -0,0 +1,85 @@
+use std::sync::{Arc, OnceLock};
+
+use futures::channel::{mpsc, oneshot};
+use futures::StreamExt;
+
+use crate::protocol::EncodedPacket;
+use crate::rt;
+use crate::server::registry::Registry;
+
+/// One broadcast, queued on every worker.
+struct Job {
+    packet: EncodedPacket,
+    /// Receives the number of sessions the worker queued the packet for.
+    reached: oneshot::Sender<usize>,
+}
+
+/// Fans broadcasts out over a pool of worker tasks, started on first use.
+/// Shard `i` is always written by worker `i % workers` and each worker runs its jobs in order,
+/// so every session receives broadcasts in the order they were made.
+#[derive(Debug)]
+pub(crate) struct Broadcaster {
+    workers: usize,
+    queues: OnceLock<Vec<mpsc::UnboundedSender<Job>>>,
+}
+
+impl Broadcaster {
+    /// Creates a pool of `workers` workers, at least one.
+    pub(crate) fn new(workers: usize) -> Self {
+        Self {
+            workers: workers.max(1),
+            queues: OnceLock::new(),
+        }
+    }
+
+    /// Returns the number of workers.
+    pub(crate) fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Queues `packet` on every session in `registry` without waiting on full send buffers.
+    /// Returns the number of sessions that accepted it.
+    pub(crate) async fn broadcast(&self, registry: &Arc<Registry>, packet: EncodedPacket) -> usize {
+        let queues = self.queues.get_or_init(|| self.spawn(registry));
+        let replies: Vec<_> = queues.iter()
+            .filter_map(|queue| {
+                let (reached, reply) = oneshot::channel();
+                queue.unbounded_send(Job { packet: packet.clone(), reached }).ok()?;
+                Some(reply)
+            })
+            .collect();
+        futures::future::join_all(replies).await
+            .into_iter()
+            .filter_map(Result::ok)
+            .sum()
+    }
+
+    fn spawn(&self, registry: &Arc<Registry>) -> Vec<mpsc::UnboundedSender<Job>> {
+        // A worker without a shard would have nothing to do.
+        let workers = self.workers.min(registry.shard_count());
+        (0..workers)
+            .map(|worker| {
+                let (queue, mut jobs) = mpsc::unbounded::<Job>();
+                let registry = registry.clone();
+                rt::spawn(async move {
+                    while let Some(job) = jobs.next().await {
+                        let mut reached = 0;
+                        for index in (worker..registry.shard_count()).step_by(workers) {
+                            // Snapshot the handles so the shard is not locked while sending.
+                            let sessions: Vec<_> = registry.shard(index).values()
+                                .map(|entry| entry.session.clone())
+                                .collect();
+                            for session in sessions {
+                                if session.try_send_encoded(job.packet.clone()).is_ok() {
+                                    reached += 1;
+                                }
+                            }
+                        }
+                        let _ = job.reached.send(reached);
+                    }
+                });
+                queue
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+
+use http::HeaderValue;
+
+/// `SameSite` attribute of the session cookie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SameSite {
+    /// Sent only with same-site requests.
+    Strict,
+    /// Sent with same-site requests and top-level navigations.
+    #[default]
+    Lax,
+    /// Sent with every request; implies `Secure`.
+    None,
+}
+
+impl fmt::Display for SameSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SameSite::Strict => write!(f, "Strict"),
+            SameSite::Lax => write!(f, "Lax"),
+            SameSite::None => write!(f, "None"),
+        }
+    }
+}
+
+/// Settings for the cookie carrying the session id, used by load balancers for sticky sessions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CookieConfig {
+    /// Cookie name.
+    name: String,
+    /// Cookie path.
+    path: String,
+    /// Whether scripts are denied access to the cookie.
+    http_only: bool,
+    /// Cross-site policy.
+    same_site: SameSite,
+    /// Whether the cookie is only sent over HTTPS.
+    secure: bool,
+}
+
+impl Default for CookieConfig {
+    fn default() -> Self {
+        Self {
+            name: "io".into(),
+            path: "/".into(),
+            http_only: true,
+            same_site: SameSite::default(),
+            secure: false,
+        }
+    }
+}
+
+impl CookieConfig {
+    /// Returns the cookie name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sets the cookie name.
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Returns the cookie path.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Sets the cookie path.
+    pub fn with_path(mut self, path: String) -> Self {
+        self.path = path;
+        self
+    }
+
+    /// Returns whether the cookie is HTTP-only.
+    pub fn http_only(&self) -> bool {
+        self.http_only
+    }
+
+    /// Sets whether the cookie is HTTP-only.
+    pub fn with_http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    /// Returns the `SameSite` policy.
+    pub fn same_site(&self) -> SameSite {
+        self.same_site
+    }
+
+    /// Sets the `SameSite` policy.
+    pub fn with_same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    /// Returns whether the cookie is HTTPS-only.
+    pub fn secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Sets whether the cookie is HTTPS-only.
+    pub fn with_secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Builds the `Set-Cookie` value for a session id, or `None` if the name or path is not header-safe.
+    pub(crate) fn header(&self, sid: &str) -> Option<HeaderValue> {
+        let mut cookie = format!("{}={}; Path={}", self.name, sid, self.path);
+        if self.http_only {
+            cookie.push_str("; HttpOnly");
+        }
+        cookie.push_str(&format!("; SameSite={}", self.same_site));
+        if self.secure || self.same_site == SameSite::None {
+            cookie.push_str("; Secure");
+        }
+        HeaderValue::from_str(&cookie).ok()
+    }
+}
@@ -0,0 +1,195 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use http::{header, HeaderMap, HeaderValue};
+
+/// An origin, or family of origins, allowed to make cross-origin requests.
+#[derive(Clone)]
+pub enum AllowedOrigin {
+    /// Every origin.
+    Any,
+    /// One origin, compared exactly, such as `https://example.com`.
+    Exact(String),
+    /// Origins matching a pattern where `*` stands for any run of characters, such as `https://*.example.com`.
+    Wildcard(String),
+    /// Origins accepted by a predicate.
+    Predicate(Arc<dyn Fn(&str) -> bool + Send + Sync>),
+}
+
+impl fmt::Debug for AllowedOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AllowedOrigin::Any => write!(f, "Any"),
+            AllowedOrigin::Exact(origin) => f.debug_tuple("Exact").field(origin).finish(),
+            AllowedOrigin::Wildcard(pattern) => f.debug_tuple("Wildcard").field(pattern).finish(),
+            AllowedOrigin::Predicate(_) => write!(f, "Predicate(..)"),
+        }
+    }
+}
+
+impl AllowedOrigin {
+    /// Creates a predicate origin rule.
+    pub fn predicate<F: Fn(&str) -> bool + Send + Sync + 'static>(predicate: F) -> Self {
+        AllowedOrigin::Predicate(Arc::new(predicate))
+    }
+
+    /// Returns whether the rule accepts every origin: `Any`, or a wildcard made only of `*`.
+    fn is_any(&self) -> bool {
+        match self {
+            AllowedOrigin::Any => true,
+            AllowedOrigin::Wildcard(pattern) => pattern.chars().all(|c| c == '*'),
+            AllowedOrigin::Exact(_) | AllowedOrigin::Predicate(_) => false,
+        }
+    }
+
+    /// Returns whether the rule names `origin` closely enough to trust it with credentials:
+    /// an `Exact` match, or a `Wildcard` match other than a bare `*`.
+    fn trusts(&self, origin: &str) -> bool {
+        match self {
+            AllowedOrigin::Exact(_) | AllowedOrigin::Wildcard(_) => !self.is_any() && self.matches(origin),
+            AllowedOrigin::Any | AllowedOrigin::Predicate(_) => false,
+        }
+    }
+
+    /// Returns whether the rule accepts an origin.
+    pub fn matches(&self, origin: &str) -> bool {
+        match self {
+            AllowedOrigin::Any => true,
+            AllowedOrigin::Exact(allowed) => allowed == origin,
+            AllowedOrigin::Wildcard(pattern) => wildcard_match(pattern, origin),
+            AllowedOrigin::Predicate(predicate) => predicate(origin),
+        }
+    }
+}
+
+/// Cross-origin resource sharing settings for engine HTTP requests.
+#[derive(Debug, Clone, Default)]
+pub struct CorsConfig {
+    /// Origin rules; an origin is allowed if any rule matches.
+    origins: Vec<AllowedOrigin>,
+    /// Whether browsers may send cookies and credentials.
+    credentials: bool,
+    /// Request headers allowed in preflight; empty echoes the requested headers.
+    allowed_headers: Vec<String>,
+    /// How long browsers may cache a preflight response.
+    max_age: Option<Duration>,
+}
+
+impl CorsConfig {
+    /// Creates a configuration that allows no origins.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the origin rules.
+    pub fn origins(&self) -> &[AllowedOrigin] {
+        &self.origins
+    }
+
+    /// Adds an origin rule.
+    pub fn with_origin(mut self, origin: AllowedOrigin) -> Self {
+        self.origins.push(origin);
+        self
+    }
+
+    /// Returns whether credentials are allowed.
+    pub fn credentials(&self) -> bool {
+        self.credentials
+    }
+
+    /// Sets whether credentials are allowed. They are only granted to origins matched by an `Exact` rule or a
+    /// `Wildcard` other than a bare `*`; other allowed origins are answered without them.
+    pub fn with_credentials(mut self, credentials: bool) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Returns the request headers allowed in preflight.
+    pub fn allowed_headers(&self) -> &[String] {
+        &self.allowed_headers
+    }
+
+    /// Sets the request headers allowed in preflight.
+    pub fn with_allowed_headers(mut self, allowed_headers: Vec<String>) -> Self {
+        self.allowed_headers = allowed_headers;
+        self
+    }
+
+    /// Returns the preflight cache duration.
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+
+    /// Sets the preflight cache duration.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Returns whether an origin is allowed.
+    pub fn allows(&self, origin: &str) -> bool {
+        self.origins.iter().any(|rule| rule.matches(origin))
+    }
+
+    /// Returns whether credentials are allowed for `origin`.
+    pub fn allows_credentials(&self, origin: &str) -> bool {
+        self.credentials && self.origins.iter().any(|rule| rule.trusts(origin))
+    }
+
+    /// Adds the headers for a response to a request from an allowed `origin`.
+    /// Credentials are left out, and the refusal logged, unless a trusted rule names the origin.
+    pub(crate) fn apply(&self, origin: &HeaderValue, headers: &mut HeaderMap) {
+        let credentials = origin.to_str().is_ok_and(|origin| self.allows_credentials(origin));
+        if self.credentials && !credentials {
+            warn!(?origin, "credentials refused for an origin not listed by an exact or wildcard rule");
+        }
+        match !credentials && self.origins.iter().any(AllowedOrigin::is_any) {
+            true => headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*")),
+            false => {
+                headers.append(header::VARY, HeaderValue::from_static("Origin"));
+                headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone())
+            }
+        };
+        if credentials {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+    }
+
+    /// Adds the preflight headers for a request from an allowed `origin`.
+    pub(crate) fn apply_preflight(&self, origin: &HeaderValue, request: &HeaderMap, headers: &mut HeaderMap) {
+        self.apply(origin, headers);
+        headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, HeaderValue::from_static("GET, POST, OPTIONS"));
+
+        let allowed_headers = match self.allowed_headers.is_empty() {
+            true => request.get(header::ACCESS_CONTROL_REQUEST_HEADERS).cloned(),
+            false => HeaderValue::from_str(&self.allowed_headers.join(", ")).ok(),
+        };
+        if let Some(allowed_headers) = allowed_headers {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allowed_headers);
+        }
+        if let Some(max_age) = self.max_age {
+            headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from(max_age.as_secs()));
+        }
+    }
+}
+
+/// Matches `value` against `pattern`, where `*` stands for any run of characters.
+fn wildcard_match(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = value.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+
+use http::StatusCode;
+
+/// Engine.io error codes returned in rejected HTTP responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// The `transport` query parameter is missing or not supported.
+    UnknownTransport,
+    /// The `sid` query parameter does not match an open session.
+    UnknownSid,
+    /// A handshake was attempted with a method other than `GET`.
+    BadHandshakeMethod,
+    /// The request is malformed.
+    BadRequest,
+    /// The request was refused.
+    Forbidden,
+    /// The `EIO` query parameter is missing or not supported.
+    UnsupportedProtocolVersion,
+}
+
+impl ErrorCode {
+    /// Returns the numeric code sent to clients.
+    pub fn code(&self) -> u8 {
+        match self {
+            ErrorCode::UnknownTransport => 0,
+            ErrorCode::UnknownSid => 1,
+            ErrorCode::BadHandshakeMethod => 2,
+            ErrorCode::BadRequest => 3,
+            ErrorCode::Forbidden => 4,
+            ErrorCode::UnsupportedProtocolVersion => 5,
+        }
+    }
+
+    /// Returns the HTTP status used when no other status is given.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorCode::UnknownTransport => write!(f, "Transport unknown"),
+            ErrorCode::UnknownSid => write!(f, "Session ID unknown"),
+            ErrorCode::BadHandshakeMethod => write!(f, "Bad handshake method"),
+            ErrorCode::BadRequest => write!(f, "Bad request"),
+            ErrorCode::Forbidden => write!(f, "Forbidden"),
+            ErrorCode::UnsupportedProtocolVersion => write!(f, "Unsupported protocol version"),
+        }
+    }
+}
+
+/// A refused HTTP request: the error code sent to the client and the response status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rejection {
+    code: ErrorCode,
+    status: StatusCode,
+}
+
+impl Rejection {
+    /// Creates a rejection with an explicit HTTP status.
+    pub fn new(code: ErrorCode, status: StatusCode) -> Self {
+        Self { code, status }
+    }
+
+    /// Returns the error code.
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    /// Returns the HTTP status.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl From<ErrorCode> for Rejection {
+    fn from(code: ErrorCode) -> Self {
+        Self::new(code, code.status())
+    }
+}
+
+impl std::error::Error for Rejection {}
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Request rejected ({}): {}", self.status, self.code)
+    }
+}
@@ -0,0 +1,472 @@
+mod broadcast;
+mod cookie;
+mod cors;
+mod error;
+mod registry;
+mod request;
+#[cfg(feature = "tower")]
+mod service;
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use http::{header, Method, Request, Response, StatusCode};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+#[cfg(feature = "compression")]
+use crate::protocol::Compression;
+use crate::protocol::{EncodedPacket, RawData};
+use crate::rt;
+use crate::session::{CloseReason, Recovery, RecoveryConfig, Session, SessionConfig, SessionError, SessionHandle};
+use broadcast::Broadcaster;
+use registry::{Entry, Registry};
+use crate::transport::polling::{PollingConfig, PollingTransport};
+use crate::transport::{Transport, TransportError};
+
+pub use cookie::{CookieConfig, SameSite};
+pub use cors::{AllowedOrigin, CorsConfig};
+pub use error::{ErrorCode, Rejection};
+pub use request::HandshakeRequest;
+#[cfg(feature = "tower")]
+pub use service::{EngineBody, EngineLayer, EngineService};
+
+/// Engine protocol revision served by `EngineServer`.
+const PROTOCOL_VERSION: &str = "4";
+/// `codec` query value that selects the engine's own packet encoding over Engine.IO v4.
+const ENGINE_CODEC: &str = "engine";
+/// Session registry shards unless set with `with_shards`.
+const DEFAULT_SHARDS: usize = 64;
+
+type AllowRequest = Box<dyn Fn(HandshakeRequest) -> BoxFuture<'static, Result<(), Rejection>> + Send + Sync>;
+
+/// Serves engine sessions over HTTP long-polling.
+/// The HTTP layer hands each request to `handle`; new sessions are collected with `accept`.
+/// Payloads use the Engine.IO v4 encoding unless the handshake asks for the engine's own with `codec=engine`,
+/// which carries packet options: compression and encryption are only negotiated for those sessions.
+pub struct EngineServer {
+    /// Settings for every session the server opens.
+    config: SessionConfig,
+    /// Authorizes handshakes before a session id is issued.
+    allow_request: Option<AllowRequest>,
+    /// Cross-origin settings; `None` leaves CORS headers off.
+    cors: Option<CorsConfig>,
+    /// Sticky-session cookie settings; `None` sets no cookie.
+    cookie: Option<CookieConfig>,
+    /// Registry of resumable sessions; `None` disables connection state recovery.
+    recovery: Option<Recovery>,
+    /// Open sessions, by session id.
+    sessions: Arc<Registry>,
+    /// Worker pool that fans broadcasts out across the registry's shards.
+    broadcaster: Broadcaster,
+    /// Newly opened sessions, waiting for `accept`; dropped on shutdown.
+    incoming_tx: Mutex<Option<UnboundedSender<Session>>>,
+    incoming_rx: tokio::sync::Mutex<UnboundedReceiver<Session>>,
+    /// Set once `shutdown` is called.
+    shutting_down: AtomicBool,
+}
+
+impl fmt::Debug for EngineServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EngineServer")
+            .field("config", &self.config)
+            .field("allow_request", &self.allow_request.is_some())
+            .field("cors", &self.cors)
+            .field("cookie", &self.cookie)
+            .field("recovery", &self.recovery.as_ref().map(Recovery::config))
+            .field("sessions", &self.session_count())
+            .field("shards", &self.shards())
+            .field("broadcast_workers", &self.broadcast_workers())
+            .finish()
+    }
+}
+
+impl EngineServer {
+    /// Creates a server that opens sessions with the given settings.
+    pub fn new(config: SessionConfig) -> Self {
+        let (incoming_tx, incoming_rx) = mpsc::unbounded_channel();
+        Self {
+            config,
+            allow_request: None,
+            cors: None,
+            cookie: None,
+            recovery: None,
+            sessions: Arc::new(Registry::new(DEFAULT_SHARDS)),
+            broadcaster: Broadcaster::new(std::thread::available_parallelism().map_or(1, NonZeroUsize::get)),
+            incoming_tx: Mutex::new(Some(incoming_tx)),
+            incoming_rx: tokio::sync::Mutex::new(incoming_rx),
+            shutting_down: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the session settings.
+    pub fn config(&self) -> &SessionConfig {
+        &self.config
+    }
+
+    /// Sets a hook that accepts or rejects each handshake before a session id is issued.
+    pub fn with_allow_request<F, Fut>(mut self, allow_request: F) -> Self
+    where
+        F: Fn(HandshakeRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Rejection>> + Send + 'static,
+    {
+        self.allow_request = Some(Box::new(move |request| allow_request(request).boxed()));
+        self
+    }
+
+    /// Returns the cross-origin settings.
+    pub fn cors(&self) -> Option<&CorsConfig> {
+        self.cors.as_ref()
+    }
+
+    /// Enables CORS: requests from other origins are rejected unless allowed by `cors`.
+    pub fn with_cors(mut self, cors: CorsConfig) -> Self {
+        self.cors = Some(cors);
+        self
+    }
+
+    /// Returns the sticky-session cookie settings.
+    pub fn cookie(&self) -> Option<&CookieConfig> {
+        self.cookie.as_ref()
+    }
+
+    /// Sets a cookie carrying the session id on handshake responses.
+    pub fn with_cookie(mut self, cookie: CookieConfig) -> Self {
+        self.cookie = Some(cookie);
+        self
+    }
+
+    /// Returns the connection state recovery settings.
+    pub fn recovery(&self) -> Option<&RecoveryConfig> {
+        self.recovery.as_ref().map(Recovery::config)
+    }
+
+    /// Enables connection state recovery: a session whose client disappears is kept for the recovery window,
+    /// and a handshake carrying `resume=<sid>&pid=<pid>&offset=<n>` picks it up again,
+    /// where `pid` is the private id from the session's handshake.
+    pub fn with_recovery(mut self, recovery: RecoveryConfig) -> Self {
+        self.recovery = Some(Recovery::new(recovery));
+        self
+    }
+
+    /// Returns the number of session registry shards.
+    pub fn shards(&self) -> usize {
+        self.sessions.shard_count()
+    }
+
+    /// Sets the number of shards the session registry is split into, at least one.
+    /// Requests for sessions in different shards never wait on each other's lock.
+    pub fn with_shards(mut self, shards: usize) -> Self {
+        self.sessions = Arc::new(Registry::new(shards));
+        self
+    }
+
+    /// Returns the number of broadcast workers.
+    pub fn broadcast_workers(&self) -> usize {
+        self.broadcaster.workers()
+    }
+
+    /// Sets the number of workers `broadcast` spreads its work over, at least one. Defaults to the available parallelism.
+    pub fn with_broadcast_workers(mut self, workers: usize) -> Self {
+        self.broadcaster = Broadcaster::new(workers);
+        self
+    }
+
+    /// Returns the number of open sessions.
+    pub fn session_count(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Queues a packet on every open session, applying each session's overflow policy if its send buffer is full.
+    /// Sessions receive broadcasts in the order they were made, and all of them share the packet's encoding.
+    /// Returns the number of sessions that accepted the packet.
+    pub async fn broadcast(&self, packet: EncodedPacket) -> usize {
+        self.broadcaster.broadcast(&self.sessions, packet).await
+    }
+
+    /// Moves a polling session onto `transport`, which must be listed in `SessionConfig::with_upgrades`.
+    /// The client probes the transport first, as in `Session::upgrade`; polling requests for the session fail afterwards.
+    pub async fn upgrade<T: Transport>(&self, sid: &str, transport: T) -> Result<(), SessionError> {
+        let session = self.sessions.session(sid)
+            .ok_or(SessionError::UpgradeFailed)?;
+        session.upgrade(transport).await
+    }
+
+    /// Waits for the next session opened by a handshake.
+    /// Returns `None` once the server has shut down and every opened session has been accepted.
+    pub async fn accept(&self) -> Option<Session> {
+        self.incoming_rx.lock().await.recv().await
+    }
+
+    /// Returns whether `shutdown` has been called.
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Acquire)
+    }
+
+    /// Stops accepting handshakes and closes every session with `CloseReason::ServerShutdown`.
+    /// Sessions get up to `grace_period` to flush their send buffers to clients; any still open are then force-closed.
+    pub async fn shutdown(&self, grace_period: Duration) {
+        self.shutting_down.store(true, Ordering::Release);
+        self.incoming_tx.lock().unwrap().take();
+
+        let sessions = self.sessions.sessions();
+        for session in &sessions {
+            session.close(CloseReason::ServerShutdown);
+        }
+
+        let closed = futures::future::join_all(sessions.iter().map(SessionHandle::closed));
+        if rt::timeout(grace_period, closed).await.is_none() {
+            for session in &sessions {
+                session.abort(CloseReason::ServerShutdown);
+            }
+            futures::future::join_all(sessions.iter().map(SessionHandle::closed)).await;
+        }
+    }
+
+    /// Answers one engine HTTP request.
+    pub async fn handle(&self, request: Request<Bytes>) -> Response<Bytes> {
+        let origin = request.headers().get(header::ORIGIN).cloned();
+        let (cors, origin) = match (&self.cors, origin) {
+            (Some(cors), Some(origin)) => {
+                if !origin.to_str().is_ok_and(|origin| cors.allows(origin)) {
+                    return rejection_response(ErrorCode::Forbidden.into());
+                }
+                (cors, origin)
+            }
+            _ => return self.respond(request).await,
+        };
+
+        if request.method() == Method::OPTIONS {
+            let mut response = Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Bytes::new())
+                .unwrap();
+            cors.apply_preflight(&origin, request.headers(), response.headers_mut());
+            return response;
+        }
+        let mut response = self.respond(request).await;
+        cors.apply(&origin, response.headers_mut());
+        response
+    }
+
+    async fn respond(&self, request: Request<Bytes>) -> Response<Bytes> {
+        match self.dispatch(request).await {
+            Ok(response) => response,
+            Err(rejection) => rejection_response(rejection),
+        }
+    }
+
+    async fn dispatch(&self, request: Request<Bytes>) -> Result<Response<Bytes>, Rejection> {
+        let handshake = HandshakeRequest::new(&request);
+        if handshake.query("EIO").as_deref() != Some(PROTOCOL_VERSION) {
+            return Err(ErrorCode::UnsupportedProtocolVersion.into());
+        }
+        if handshake.query("transport").as_deref() != Some("polling") {
+            return Err(ErrorCode::UnknownTransport.into());
+        }
+
+        let sid = match handshake.query("sid") {
+            Some(sid) => sid,
+            None => return self.open(handshake).await,
+        };
+        let handle = self.sessions.polling(&sid)
+            .ok_or(ErrorCode::UnknownSid)?;
+        match *request.method() {
+            Method::GET => match handle.poll().await {
+                Ok(payload) => Ok(payload_response(payload)),
+                Err(_) => {
+                    // A recoverable session outlives this transport until it closes.
+                    self.sessions.remove_closed(&sid);
+                    Err(ErrorCode::UnknownSid.into())
+                }
+            },
+            Method::POST => {
+                let body = request_payload(&request)?;
+                match handle.post(body) {
+                    Ok(()) => Ok(text_response(StatusCode::OK, "ok")),
+                    Err(TransportError::PayloadTooLarge) => Err(Rejection::new(ErrorCode::BadRequest, StatusCode::PAYLOAD_TOO_LARGE)),
+                    Err(TransportError::Closed) => Err(ErrorCode::UnknownSid.into()),
+                    Err(_) => Err(ErrorCode::BadRequest.into()),
+                }
+            }
+            _ => Err(ErrorCode::BadRequest.into()),
+        }
+    }
+
+    /// Runs the handshake: authorizes the request, opens a session and answers with its `Open` packet.
+    async fn open(&self, request: HandshakeRequest) -> Result<Response<Bytes>, Rejection> {
+        if request.method() != Method::GET {
+            return Err(ErrorCode::BadHandshakeMethod.into());
+        }
+        if self.is_shutting_down() {
+            return Err(Rejection::new(ErrorCode::BadRequest, StatusCode::SERVICE_UNAVAILABLE));
+        }
+        if let Some(allow_request) = &self.allow_request {
+            allow_request(request.clone()).await?;
+        }
+        if let Some(response) = self.resume(&request).await {
+            return response;
+        }
+
+        #[cfg(any(feature = "compression", feature = "encryption"))]
+        let config = &self.negotiate(&request);
+        #[cfg(not(any(feature = "compression", feature = "encryption")))]
+        let config = &self.config;
+        let (transport, handle) = PollingTransport::new(self.polling_config(&request));
+        let session = Session::accept_with(transport, config, self.recovery.as_ref()).await
+            .map_err(|_| Rejection::from(ErrorCode::BadRequest))?;
+        let sid = session.sid().to_string();
+        let entry = Entry {
+            polling: handle.clone(),
+            session: session.handle(),
+        };
+        self.sessions.insert(sid.clone(), entry);
+        self.forget_when_closed(&session, config.ping_timeout());
+        match self.incoming_tx.lock().unwrap().as_ref() {
+            Some(incoming) => {
+                let _ = incoming.send(session);
+            }
+            // Shutdown began during the handshake.
+            None => session.handle().close(CloseReason::ServerShutdown),
+        }
+
+        let payload = handle.poll().await
+            .map_err(|_| Rejection::from(ErrorCode::BadRequest))?;
+        Ok(self.handshake_response(&sid, payload))
+    }
+
+    /// Removes a session from the registry once it has closed, however it closed.
+    /// The entry outlives the session by `grace`, so a client polling again collects the `Close` packet.
+    fn forget_when_closed(&self, session: &Session, grace: Duration) {
+        let sessions = self.sessions.clone();
+        let sid = session.sid().to_string();
+        let handle = session.handle();
+        rt::spawn(async move {
+            handle.closed().await;
+            rt::sleep(grace).await;
+            sessions.remove(&sid);
+        });
+    }
+
+    /// Moves a recoverable session onto a new polling transport if the handshake asks to resume one.
+    /// Returns `None` if there is nothing to resume, so the caller opens a fresh session instead.
+    async fn resume(&self, request: &HandshakeRequest) -> Option<Result<Response<Bytes>, Rejection>> {
+        let recovery = self.recovery.as_ref()?;
+        let sid = request.query("resume")?;
+        let pid = request.query("pid")?;
+        let offset = request.query("offset")?.parse().ok()?;
+        let session = self.sessions.session(&sid)?;
+
+        let (transport, handle) = PollingTransport::new(self.polling_config(request));
+        recovery.resume(transport, &sid, &pid, offset).await.ok()?;
+        // The session now writes to `handle`; if nobody can poll it, end the session rather than lose its packets.
+        if self.sessions.set_polling(&sid, handle.clone()).is_none() {
+            session.close(CloseReason::TransportClose);
+            return Some(Err(ErrorCode::UnknownSid.into()));
+        }
+
+        let result = handle.poll().await
+            .map(|payload| self.handshake_response(&sid, payload))
+            .map_err(|_| ErrorCode::BadRequest.into());
+        Some(result)
+    }
+
+    /// Returns the session settings for a handshake, narrowed to what the client asked for.
+    /// The `compression` query lists algorithm names, comma-separated, and `dictionary` names its zstd dictionary id;
+    /// sessions whose client asks for no algorithm the server accepts are uncompressed.
+    /// The `key` query carries the client's X25519 public key for an encrypted session.
+    /// Both are ignored unless the handshake selects the engine's codec, since v4 packets cannot carry them.
+    #[cfg(any(feature = "compression", feature = "encryption"))]
+    fn negotiate(&self, request: &HandshakeRequest) -> SessionConfig {
+        let config = self.config.clone();
+        let engine_codec = uses_engine_codec(request);
+        #[cfg(feature = "compression")]
+        let config = {
+            let offered: Vec<_> = request.query("compression")
+                .filter(|_| engine_codec)
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|name| Compression::try_from(name).ok())
+                .collect();
+            let dictionary = request.query("dictionary").and_then(|id| id.parse().ok());
+            config.negotiate_compression(&offered, dictionary)
+        };
+        #[cfg(feature = "encryption")]
+        let config = config.negotiate_encryption(request.query("key").filter(|_| engine_codec));
+        config
+    }
+
+    /// Returns the polling settings for a handshake; a resuming client must ask for the codec it opened with.
+    fn polling_config(&self, request: &HandshakeRequest) -> PollingConfig {
+        PollingConfig::default()
+            .with_max_payload(self.config.max_payload())
+            .with_v4(!uses_engine_codec(request))
+    }
+
+    /// Answers a handshake with its first payload, setting the session cookie if enabled.
+    fn handshake_response(&self, sid: &str, payload: RawData) -> Response<Bytes> {
+        let mut response = payload_response(payload);
+        if let Some(cookie) = self.cookie.as_ref().and_then(|cookie| cookie.header(sid)) {
+            response.headers_mut().append(header::SET_COOKIE, cookie);
+        }
+        response
+    }
+}
+
+/// Returns whether a handshake selects the engine's own codec with `codec=engine`.
+fn uses_engine_codec(request: &HandshakeRequest) -> bool {
+    request.query("codec").as_deref() == Some(ENGINE_CODEC)
+}
+
+/// Reads a POST body as a payload; `application/octet-stream` bodies are binary.
+fn request_payload(request: &Request<Bytes>) -> Result<RawData, Rejection> {
+    let binary = request.headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/octet-stream"));
+    match binary {
+        true => Ok(RawData::Binary(request.body().to_vec())),
+        false => String::from_utf8(request.body().to_vec())
+            .map(RawData::Text)
+            .map_err(|_| ErrorCode::BadRequest.into()),
+    }
+}
+
+fn payload_response(payload: RawData) -> Response<Bytes> {
+    match payload {
+        RawData::Text(text) => text_response(StatusCode::OK, text),
+        RawData::Binary(binary) => Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .body(Bytes::from(binary))
+            .unwrap(),
+    }
+}
+
+fn text_response(status: StatusCode, body: impl Into<Bytes>) -> Response<Bytes> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain; charset=UTF-8")
+        .body(body.into())
+        .unwrap()
+}
+
+fn rejection_response(rejection: Rejection) -> Response<Bytes> {
+    let body = serde_json::json!({
+        "code": rejection.code().code(),
+        "message": rejection.code().to_string(),
+    });
+    Response::builder()
+        .status(rejection.status())
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Bytes::from(body.to_string()))
+        .unwrap()
+}
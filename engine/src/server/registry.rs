@@ -0,0 +1,92 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::session::SessionHandle;
+use crate::transport::polling::PollingHandle;
+
+/// An open session's polling side, and the handle used to close it.
+#[derive(Debug)]
+pub(crate) struct Entry {
+    pub(crate) polling: PollingHandle,
+    pub(crate) session: SessionHandle,
+}
+
+/// Open sessions by session id, split across shards so requests for different sessions rarely share a lock.
+#[derive(Debug)]
+pub(crate) struct Registry {
+    shards: Box<[Mutex<HashMap<String, Entry>>]>,
+    hasher: RandomState,
+}
+
+impl Registry {
+    /// Creates a registry with `shards` shards, at least one.
+    pub(crate) fn new(shards: usize) -> Self {
+        Self {
+            shards: (0..shards.max(1)).map(|_| Mutex::default()).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Returns the number of shards.
+    pub(crate) fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Locks one shard.
+    pub(crate) fn shard(&self, index: usize) -> MutexGuard<'_, HashMap<String, Entry>> {
+        self.shards[index].lock().unwrap()
+    }
+
+    /// Locks the shard that holds `sid`.
+    fn shard_of(&self, sid: &str) -> MutexGuard<'_, HashMap<String, Entry>> {
+        let index = self.hasher.hash_one(sid) as usize % self.shards.len();
+        self.shard(index)
+    }
+
+    /// Returns the number of open sessions.
+    pub(crate) fn len(&self) -> usize {
+        (0..self.shards.len()).map(|index| self.shard(index).len()).sum()
+    }
+
+    pub(crate) fn insert(&self, sid: String, entry: Entry) {
+        self.shard_of(&sid).insert(sid, entry);
+    }
+
+    /// Returns the polling side of a session.
+    pub(crate) fn polling(&self, sid: &str) -> Option<PollingHandle> {
+        self.shard_of(sid).get(sid).map(|entry| entry.polling.clone())
+    }
+
+    /// Returns the handle of a session.
+    pub(crate) fn session(&self, sid: &str) -> Option<SessionHandle> {
+        self.shard_of(sid).get(sid).map(|entry| entry.session.clone())
+    }
+
+    /// Moves a session onto a new polling transport. Returns `None` if the session is unknown.
+    pub(crate) fn set_polling(&self, sid: &str, polling: PollingHandle) -> Option<()> {
+        self.shard_of(sid).get_mut(sid)?.polling = polling;
+        Some(())
+    }
+
+    /// Forgets a session.
+    pub(crate) fn remove(&self, sid: &str) {
+        self.shard_of(sid).remove(sid);
+    }
+
+    /// Forgets a session once it has closed.
+    pub(crate) fn remove_closed(&self, sid: &str) {
+        let mut shard = self.shard_of(sid);
+        if shard.get(sid).is_some_and(|entry| entry.session.is_closed()) {
+            shard.remove(sid);
+        }
+    }
+
+    /// Returns a handle to every open session.
+    pub(crate) fn sessions(&self) -> Vec<SessionHandle> {
+        (0..self.shards.len())
+            .flat_map(|index| self.shard(index).values().map(|entry| entry.session.clone()).collect::<Vec<_>>())
+            .collect()
+    }
+}
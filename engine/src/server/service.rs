@@ -0,0 +1,122 @@
+use std::error::Error;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use http::{Request, Response, StatusCode};
+use http_body::Body;
+use http_body_util::{BodyExt, Either, Full, LengthLimitError, Limited};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::server::error::{ErrorCode, Rejection};
+use crate::server::{rejection_response, EngineServer};
+
+/// Path the engine is mounted on unless configured otherwise.
+const DEFAULT_PATH: &str = "/engine.io/";
+
+/// Response body of an `EngineService`: the engine's own, or the inner service's.
+pub type EngineBody<B> = Either<Full<Bytes>, B>;
+
+/// Mounts an `EngineServer` in front of another service.
+#[derive(Debug, Clone)]
+pub struct EngineLayer {
+    server: Arc<EngineServer>,
+    path: String,
+}
+
+impl EngineLayer {
+    /// Creates a layer serving the engine on `/engine.io/`.
+    pub fn new(server: Arc<EngineServer>) -> Self {
+        Self {
+            server,
+            path: DEFAULT_PATH.to_string(),
+        }
+    }
+
+    /// Returns the path the engine is served on.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Sets the path the engine is served on. Requests under it are handled by the engine.
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+}
+
+impl<S> Layer<S> for EngineLayer {
+    type Service = EngineService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        EngineService {
+            inner,
+            server: self.server.clone(),
+            path: self.path.clone(),
+        }
+    }
+}
+
+/// A `tower::Service` answering engine requests and passing every other request to `inner`.
+#[derive(Debug, Clone)]
+pub struct EngineService<S> {
+    inner: S,
+    server: Arc<EngineServer>,
+    path: String,
+}
+
+impl<S> EngineService<S> {
+    /// Returns whether the request targets the engine path. The trailing slash is optional.
+    fn is_engine(&self, request: &Request<impl Body>) -> bool {
+        let path = request.uri().path();
+        let mount = self.path.trim_end_matches('/');
+        path.strip_prefix(mount)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for EngineService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Body + Send + 'static,
+    ReqBody::Data: Send,
+    ReqBody::Error: Into<Box<dyn Error + Send + Sync>>,
+{
+    type Response = Response<EngineBody<ResBody>>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        if !self.is_engine(&request) {
+            // Call the instance that was polled ready, leaving a fresh clone in its place.
+            let clone = self.inner.clone();
+            let mut inner = std::mem::replace(&mut self.inner, clone);
+            return inner.call(request)
+                .map(|result| result.map(|response| response.map(Either::Right)))
+                .boxed();
+        }
+
+        let server = self.server.clone();
+        let limit = server.config().max_payload();
+        async move {
+            let (parts, body) = request.into_parts();
+            let response = match Limited::new(body, limit).collect().await {
+                Ok(body) => server.handle(Request::from_parts(parts, body.to_bytes())).await,
+                Err(error) if error.is::<LengthLimitError>() => {
+                    rejection_response(Rejection::new(ErrorCode::BadRequest, StatusCode::PAYLOAD_TOO_LARGE))
+                }
+                Err(_) => rejection_response(ErrorCode::BadRequest.into()),
+            };
+            Ok(response.map(|body| Either::Left(Full::new(body))))
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,75 @@
+use crate::protocol::{PacketType, RawData};
+use crate::protocol::tests::text_message;
+use crate::server::EngineServer;
+use crate::session::{Session, SessionConfig};
+use super::{get, open, packets, polling};
+
+/// Opens `count` sessions, returning each one's polling URI and the accepted session.
+async fn open_many(server: &EngineServer, count: usize) -> Vec<(String, Session)> {
+    let mut sessions = Vec::new();
+    for _ in 0..count {
+        let handshake = open(server).await;
+        sessions.push((polling(handshake.sid()), server.accept().await.unwrap()));
+    }
+    sessions
+}
+
+/// Polls until `count` messages arrive, returning their text.
+async fn poll_messages(server: &EngineServer, uri: &str, count: usize) -> Vec<String> {
+    let mut messages = Vec::new();
+    while messages.len() < count {
+        for packet in packets(&server.handle(get(uri)).await) {
+            if let Some(RawData::Text(text)) = packet.data().filter(|_| packet._type() == &PacketType::Message) {
+                messages.push(text.clone());
+            }
+        }
+    }
+    messages
+}
+
+#[test]
+fn shards_and_workers_are_at_least_one() {
+    let server = EngineServer::new(SessionConfig::default())
+        .with_shards(0)
+        .with_broadcast_workers(0);
+    assert_eq!(server.shards(), 1);
+    assert_eq!(server.broadcast_workers(), 1);
+}
+
+#[tokio::test]
+async fn broadcast_reaches_every_session() {
+    let server = EngineServer::new(SessionConfig::default()).with_shards(4);
+    let sessions = open_many(&server, 5).await;
+    assert_eq!(server.session_count(), 5);
+
+    assert_eq!(server.broadcast(text_message("hello").into()).await, 5);
+    for (uri, _) in &sessions {
+        assert_eq!(poll_messages(&server, uri, 1).await, vec!["hello"]);
+    }
+}
+
+#[tokio::test]
+async fn broadcast_skips_closed_sessions() {
+    let server = EngineServer::new(SessionConfig::default());
+    let mut sessions = open_many(&server, 2).await;
+    let (_, closed) = sessions.pop().unwrap();
+    closed.close();
+    assert_eq!(server.broadcast(text_message("hello").into()).await, 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn broadcasts_keep_their_order_per_session() {
+    let server = EngineServer::new(SessionConfig::default())
+        .with_shards(8)
+        .with_broadcast_workers(4);
+    let sessions = open_many(&server, 16).await;
+
+    let broadcasts = (0..50).map(|n| server.broadcast(text_message(&n.to_string()).into()));
+    let reached = futures::future::join_all(broadcasts).await;
+    assert!(reached.iter().all(|&reached| reached == 16));
+
+    let expected: Vec<String> = (0..50).map(|n| n.to_string()).collect();
+    for (uri, _) in &sessions {
+        assert_eq!(poll_messages(&server, uri, 50).await, expected);
+    }
+}
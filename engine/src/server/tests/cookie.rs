@@ -0,0 +1,51 @@
+use bytes::Bytes;
+use http::{header, Request};
+
+use crate::server::{CookieConfig, EngineServer, SameSite};
+use crate::session::SessionConfig;
+
+fn handshake() -> Request<Bytes> {
+    Request::builder()
+        .uri("/engine.io/?EIO=4&transport=polling")
+        .body(Bytes::new())
+        .unwrap()
+}
+
+#[test]
+fn default_cookie_is_http_only_lax() {
+    let cookie = CookieConfig::default();
+    assert_eq!(cookie.header("abc").unwrap(), "io=abc; Path=/; HttpOnly; SameSite=Lax");
+}
+
+#[test]
+fn same_site_none_forces_secure() {
+    let cookie = CookieConfig::default()
+        .with_path("/chat".into())
+        .with_http_only(false)
+        .with_same_site(SameSite::None);
+    assert_eq!(cookie.header("abc").unwrap(), "io=abc; Path=/chat; SameSite=None; Secure");
+}
+
+#[test]
+fn unsafe_cookie_is_skipped() {
+    let cookie = CookieConfig::default().with_path("/\n".into());
+    assert!(cookie.header("abc").is_none());
+}
+
+#[tokio::test]
+async fn handshake_sets_session_cookie() {
+    let server = EngineServer::new(SessionConfig::default())
+        .with_cookie(CookieConfig::default().with_same_site(SameSite::Strict));
+
+    let response = server.handle(handshake()).await;
+    let session = server.accept().await.unwrap();
+    let expected = format!("io={}; Path=/; HttpOnly; SameSite=Strict", session.sid());
+    assert_eq!(response.headers()[header::SET_COOKIE], expected.as_str());
+}
+
+#[tokio::test]
+async fn no_cookie_by_default() {
+    let server = EngineServer::new(SessionConfig::default());
+    let response = server.handle(handshake()).await;
+    assert!(response.headers().get(header::SET_COOKIE).is_none());
+}
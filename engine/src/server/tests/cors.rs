@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use http::{header, Method, Request, StatusCode};
+
+use crate::server::{AllowedOrigin, CorsConfig, EngineServer};
+use crate::session::SessionConfig;
+
+const HANDSHAKE: &str = "/engine.io/?EIO=4&transport=polling";
+
+fn request(method: Method, origin: &str) -> Request<Bytes> {
+    Request::builder()
+        .method(method)
+        .uri(HANDSHAKE)
+        .header(header::ORIGIN, origin)
+        .body(Bytes::new())
+        .unwrap()
+}
+
+#[test]
+fn origin_rules_match() {
+    assert!(AllowedOrigin::Any.matches("https://anything.test"));
+    assert!(AllowedOrigin::Exact("https://a.test".into()).matches("https://a.test"));
+    assert!(!AllowedOrigin::Exact("https://a.test".into()).matches("https://a.test:8080"));
+
+    let wildcard = AllowedOrigin::Wildcard("https://*.example.com".into());
+    assert!(wildcard.matches("https://app.example.com"));
+    assert!(wildcard.matches("https://a.b.example.com"));
+    assert!(!wildcard.matches("https://example.com"));
+    assert!(!wildcard.matches("http://app.example.com"));
+    assert!(!wildcard.matches("https://app.example.com.evil.test"));
+
+    let predicate = AllowedOrigin::predicate(|origin| origin.ends_with(".internal"));
+    assert!(predicate.matches("http://tools.internal"));
+    assert!(!predicate.matches("http://tools.external"));
+}
+
+#[test]
+fn config_allows_if_any_rule_matches() {
+    let cors = CorsConfig::new()
+        .with_origin(AllowedOrigin::Exact("https://a.test".into()))
+        .with_origin(AllowedOrigin::Wildcard("https://*.b.test".into()));
+    assert!(cors.allows("https://a.test"));
+    assert!(cors.allows("https://x.b.test"));
+    assert!(!cors.allows("https://c.test"));
+    assert!(!CorsConfig::new().allows("https://a.test"));
+}
+
+#[tokio::test]
+async fn allowed_origin_is_echoed_with_credentials() {
+    let cors = CorsConfig::new()
+        .with_origin(AllowedOrigin::Exact("https://app.test".into()))
+        .with_credentials(true);
+    let server = EngineServer::new(SessionConfig::default()).with_cors(cors);
+
+    let response = server.handle(request(Method::GET, "https://app.test")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers();
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.test");
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    assert_eq!(headers[header::VARY], "Origin");
+}
+
+#[tokio::test]
+async fn any_origin_without_credentials_uses_star() {
+    let server = EngineServer::new(SessionConfig::default())
+        .with_cors(CorsConfig::new().with_origin(AllowedOrigin::Any));
+
+    let response = server.handle(request(Method::GET, "https://app.test")).await;
+    assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).is_none());
+}
+
+#[tokio::test]
+async fn disallowed_origin_is_rejected() {
+    let server = EngineServer::new(SessionConfig::default())
+        .with_cors(CorsConfig::new().with_origin(AllowedOrigin::Exact("https://app.test".into())));
+
+    let response = server.handle(request(Method::GET, "https://evil.test")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    assert_eq!(server.session_count(), 0);
+}
+
+#[tokio::test]
+async fn preflight_lists_methods_and_headers() {
+    let cors = CorsConfig::new()
+        .with_origin(AllowedOrigin::Wildcard("https://*.app.test".into()))
+        .with_max_age(Duration::from_secs(600));
+    let server = EngineServer::new(SessionConfig::default()).with_cors(cors);
+
+    let mut preflight = request(Method::OPTIONS, "https://eu.app.test");
+    preflight.headers_mut().insert(header::ACCESS_CONTROL_REQUEST_HEADERS, "authorization".parse().unwrap());
+    let response = server.handle(preflight).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let headers = response.headers();
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://eu.app.test");
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "GET, POST, OPTIONS");
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_HEADERS], "authorization");
+    assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+    assert_eq!(server.session_count(), 0);
+}
+
+#[tokio::test]
+async fn credentials_are_only_granted_to_listed_origins() {
+    for rule in [AllowedOrigin::Any, AllowedOrigin::Wildcard("*".into()), AllowedOrigin::predicate(|_| true)] {
+        let cors = CorsConfig::new().with_origin(rule).with_credentials(true);
+        assert!(!cors.allows_credentials("https://evil.test"));
+        let server = EngineServer::new(SessionConfig::default()).with_cors(cors);
+
+        let response = server.handle(request(Method::GET, "https://evil.test")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).is_none());
+    }
+
+    // A listed origin keeps its credentials alongside a catch-all rule, and others fall back to `*`.
+    let cors = CorsConfig::new()
+        .with_origin(AllowedOrigin::Any)
+        .with_origin(AllowedOrigin::Wildcard("https://*.app.test".into()))
+        .with_credentials(true);
+    let server = EngineServer::new(SessionConfig::default()).with_cors(cors);
+    let response = server.handle(request(Method::GET, "https://eu.app.test")).await;
+    assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://eu.app.test");
+    assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    let response = server.handle(request(Method::GET, "https://evil.test")).await;
+    assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).is_none());
+}
+
+#[tokio::test]
+async fn configured_headers_override_requested_headers() {
+    let cors = CorsConfig::new()
+        .with_origin(AllowedOrigin::Any)
+        .with_allowed_headers(vec!["authorization".into(), "x-trace".into()]);
+    let server = EngineServer::new(SessionConfig::default()).with_cors(cors);
+
+    let mut preflight = request(Method::OPTIONS, "https://app.test");
+    preflight.headers_mut().insert(header::ACCESS_CONTROL_REQUEST_HEADERS, "x-other".parse().unwrap());
+    let response = server.handle(preflight).await;
+    assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_HEADERS], "authorization, x-trace");
+}
+
+#[tokio::test]
+async fn requests_without_origin_skip_cors() {
+    let server = EngineServer::new(SessionConfig::default())
+        .with_cors(CorsConfig::new());
+
+    let request = Request::builder().uri(HANDSHAKE).body(Bytes::new()).unwrap();
+    let response = server.handle(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+}
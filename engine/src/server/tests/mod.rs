@@ -0,0 +1,334 @@
+#[cfg(test)]
+mod broadcast;
+#[cfg(test)]
+mod cookie;
+#[cfg(test)]
+mod cors;
+#[cfg(test)]
+mod recovery;
+#[cfg(all(test, feature = "tower"))]
+mod service;
+#[cfg(test)]
+mod shutdown;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use bytes::Bytes;
+use http::{header, Method, Request, Response, StatusCode};
+
+use futures::{SinkExt, StreamExt};
+
+use crate::protocol::{Handshake, Packet, PacketType, RawData};
+use crate::server::{EngineServer, ErrorCode, HandshakeRequest, Rejection};
+use crate::session::{CloseReason, SessionConfig, SessionEvent};
+use crate::transport::memory::MemoryTransport;
+
+fn get(uri: &str) -> Request<Bytes> {
+    Request::builder().uri(uri).body(Bytes::new()).unwrap()
+}
+
+fn body_text(response: &Response<Bytes>) -> String {
+    String::from_utf8(response.body().to_vec()).unwrap()
+}
+
+/// Decodes a response body as an Engine.IO v4 payload.
+fn packets(response: &Response<Bytes>) -> Vec<Packet> {
+    Packet::decode_payload_v4(&body_text(response)).unwrap()
+}
+
+/// Returns the polling URI of an open session.
+fn polling(sid: &str) -> String {
+    format!("/engine.io/?EIO=4&transport=polling&sid={}", sid)
+}
+
+fn error_code(response: &Response<Bytes>) -> u64 {
+    let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    body["code"].as_u64().unwrap()
+}
+
+async fn open(server: &EngineServer) -> Handshake {
+    let response = server.handle(get("/engine.io/?EIO=4&transport=polling")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    Handshake::from_packet(&packets(&response)[0]).unwrap()
+}
+
+#[test]
+fn error_codes_match_engine_io() {
+    assert_eq!(ErrorCode::UnknownTransport.code(), 0);
+    assert_eq!(ErrorCode::UnknownSid.code(), 1);
+    assert_eq!(ErrorCode::BadHandshakeMethod.code(), 2);
+    assert_eq!(ErrorCode::BadRequest.code(), 3);
+    assert_eq!(ErrorCode::Forbidden.code(), 4);
+    assert_eq!(ErrorCode::UnsupportedProtocolVersion.code(), 5);
+    assert_eq!(Rejection::from(ErrorCode::Forbidden).status(), StatusCode::FORBIDDEN);
+    assert_eq!(Rejection::from(ErrorCode::UnknownSid).status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn handshake_request_exposes_query_cookies_and_address() {
+    let addr: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+    let mut request = Request::builder()
+        .uri("/engine.io/?EIO=4&transport=polling&token=a%20b+c&empty")
+        .header(header::COOKIE, "theme=dark; session=\"abc\"")
+        .header(header::COOKIE, "lang=en")
+        .body(Bytes::new())
+        .unwrap();
+    request.extensions_mut().insert(addr);
+
+    let request = HandshakeRequest::new(&request);
+    assert_eq!(request.method(), Method::GET);
+    assert_eq!(request.query("token").as_deref(), Some("a b c"));
+    assert_eq!(request.query("empty").as_deref(), Some(""));
+    assert_eq!(request.query("missing"), None);
+    assert_eq!(request.cookie("session").as_deref(), Some("abc"));
+    assert_eq!(request.cookie("lang").as_deref(), Some("en"));
+    assert_eq!(request.cookies().len(), 3);
+    assert_eq!(request.remote_addr(), Some(addr));
+}
+
+#[test]
+fn malformed_query_escapes_are_kept() {
+    let request = HandshakeRequest::new(&get("/?a=%zz&b=%4&c=%41"));
+    assert_eq!(request.query("a").as_deref(), Some("%zz"));
+    assert_eq!(request.query("b").as_deref(), Some("%4"));
+    assert_eq!(request.query("c").as_deref(), Some("A"));
+}
+
+#[tokio::test]
+async fn handshake_opens_session() {
+    let server = EngineServer::new(SessionConfig::default());
+    let handshake = open(&server).await;
+
+    let session = server.accept().await.unwrap();
+    assert_eq!(session.sid(), handshake.sid());
+    assert_eq!(session.transport(), "polling");
+    assert_eq!(server.session_count(), 1);
+}
+
+#[tokio::test]
+async fn allow_request_rejects_before_session_is_created() {
+    let server = EngineServer::new(SessionConfig::default())
+        .with_allow_request(|request: HandshakeRequest| async move {
+            match request.headers().get(header::AUTHORIZATION) {
+                Some(_) => Ok(()),
+                None => Err(Rejection::new(ErrorCode::Forbidden, StatusCode::UNAUTHORIZED)),
+            }
+        });
+
+    let response = server.handle(get("/engine.io/?EIO=4&transport=polling")).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(error_code(&response), 4);
+    assert_eq!(server.session_count(), 0);
+    assert!(tokio::time::timeout(Duration::from_millis(50), server.accept()).await.is_err());
+
+    let request = Request::builder()
+        .uri("/engine.io/?EIO=4&transport=polling")
+        .header(header::AUTHORIZATION, "Bearer token")
+        .body(Bytes::new())
+        .unwrap();
+    assert_eq!(server.handle(request).await.status(), StatusCode::OK);
+    assert_eq!(server.session_count(), 1);
+}
+
+#[tokio::test]
+async fn allow_request_sees_cookies_and_address() {
+    let server = EngineServer::new(SessionConfig::default())
+        .with_allow_request(|request: HandshakeRequest| async move {
+            let local = request.remote_addr().is_some_and(|addr| addr.ip().is_loopback());
+            match local && request.cookie("auth").as_deref() == Some("yes") {
+                true => Ok(()),
+                false => Err(ErrorCode::Forbidden.into()),
+            }
+        });
+
+    let mut request = Request::builder()
+        .uri("/engine.io/?EIO=4&transport=polling")
+        .header(header::COOKIE, "auth=yes")
+        .body(Bytes::new())
+        .unwrap();
+    request.extensions_mut().insert("127.0.0.1:9000".parse::<SocketAddr>().unwrap());
+    assert_eq!(server.handle(request).await.status(), StatusCode::OK);
+
+    let request = Request::builder()
+        .uri("/engine.io/?EIO=4&transport=polling")
+        .header(header::COOKIE, "auth=yes")
+        .body(Bytes::new())
+        .unwrap();
+    assert_eq!(server.handle(request).await.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn invalid_requests_are_rejected_with_error_codes() {
+    let server = EngineServer::new(SessionConfig::default());
+
+    let response = server.handle(get("/engine.io/?EIO=3&transport=polling")).await;
+    assert_eq!(error_code(&response), 5);
+    let response = server.handle(get("/engine.io/?EIO=4&transport=carrier-pigeon")).await;
+    assert_eq!(error_code(&response), 0);
+    let response = server.handle(get("/engine.io/?EIO=4&transport=polling&sid=nope")).await;
+    assert_eq!(error_code(&response), 1);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/engine.io/?EIO=4&transport=polling")
+        .body(Bytes::new())
+        .unwrap();
+    assert_eq!(error_code(&server.handle(request).await), 2);
+}
+
+#[tokio::test]
+async fn polling_exchanges_messages() {
+    let server = EngineServer::new(SessionConfig::default());
+    let handshake = open(&server).await;
+    let mut session = server.accept().await.unwrap();
+    let uri = polling(handshake.sid());
+
+    let mut packet = Packet::new(PacketType::Message);
+    packet.with_data(RawData::Text("hello".into())).unwrap();
+    let body = Packet::encode_payload_v4(vec![packet]);
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(&uri)
+        .body(Bytes::from(body))
+        .unwrap();
+    let response = server.handle(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_text(&response), "ok");
+    assert_eq!(session.recv().await, Some(SessionEvent::Message(RawData::Text("hello".into()))));
+
+    session.send(RawData::Text("world".into())).await.unwrap();
+    let response = server.handle(get(&uri)).await;
+    let packets = packets(&response);
+    assert_eq!(packets[0].data(), Some(&RawData::Text("world".into())));
+}
+
+#[tokio::test]
+async fn timed_out_session_is_forgotten_without_a_poll() {
+    let config = SessionConfig::default()
+        .with_ping_interval(Duration::from_millis(20))
+        .with_ping_timeout(Duration::from_millis(20));
+    let server = EngineServer::new(config);
+    open(&server).await;
+    let mut session = server.accept().await.unwrap();
+    assert_eq!(server.session_count(), 1);
+
+    // The client never polls again, so it never answers a ping.
+    assert_eq!(session.recv().await, Some(SessionEvent::Close(CloseReason::PingTimeout)));
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while server.session_count() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }).await.unwrap();
+}
+
+#[tokio::test]
+async fn closed_session_is_forgotten() {
+    let server = EngineServer::new(SessionConfig::default());
+    let handshake = open(&server).await;
+    let session = server.accept().await.unwrap();
+    let uri = polling(handshake.sid());
+
+    session.close();
+    let response = server.handle(get(&uri)).await;
+    assert_eq!(packets(&response)[0]._type(), &PacketType::Close);
+
+    // A poll that races the driver's last drain is released with a `Noop` before the sid is refused.
+    let mut response = server.handle(get(&uri)).await;
+    if response.status() == StatusCode::OK {
+        assert_eq!(packets(&response), [Packet::new(PacketType::Noop)]);
+        response = server.handle(get(&uri)).await;
+    }
+    assert_eq!(error_code(&response), 1);
+    assert_eq!(server.session_count(), 0);
+}
+
+#[cfg(feature = "compression")]
+#[tokio::test]
+async fn handshake_query_narrows_compression() {
+    use crate::compression::CompressionConfig;
+    use crate::protocol::Compression;
+
+    let config = SessionConfig::default()
+        .with_compression(CompressionConfig::default().with_zstd_dictionary(7, b"dictionary"));
+    let server = EngineServer::new(config);
+    let handshake = |query: &str| {
+        let server = &server;
+        let uri = format!("/engine.io/?EIO=4&transport=polling&codec=engine{query}");
+        async move {
+            let response = server.handle(get(&uri)).await;
+            let packets = Packet::decode_payload(RawData::Text(body_text(&response))).unwrap();
+            Handshake::from_packet(&packets[0]).unwrap()
+        }
+    };
+
+    // Sessions on the v4 codec cannot carry compressed packets.
+    let response = server.handle(get("/engine.io/?EIO=4&transport=polling&compression=gzip")).await;
+    assert!(Handshake::from_packet(&packets(&response)[0]).unwrap().compression().is_empty());
+
+    assert!(handshake("").await.compression().is_empty());
+    assert!(handshake("&compression=lz4").await.compression().is_empty());
+    let offered = handshake("&compression=gzip,zstd,deflate").await;
+    assert_eq!(offered.compression(), &[Compression::Gzip, Compression::Deflate]);
+    let offered = handshake("&compression=gzip,zstd&dictionary=7").await;
+    assert_eq!(offered.compression(), &[Compression::Zstd, Compression::Gzip]);
+    assert_eq!(offered.dictionary(), Some(7));
+}
+
+#[cfg(feature = "encryption")]
+#[tokio::test]
+async fn handshake_key_query_encrypts_the_session() {
+    use crate::encryption::{EncryptionConfig, KeyExchange};
+
+    let server = EngineServer::new(SessionConfig::default().with_encryption(EncryptionConfig::default()));
+    assert_eq!(open(&server).await.key(), None);
+
+    let exchange = KeyExchange::new().unwrap();
+    let response = server.handle(get(&format!("/engine.io/?EIO=4&transport=polling&key={}", exchange.public_key()))).await;
+    assert_eq!(Handshake::from_packet(&packets(&response)[0]).unwrap().key(), None);
+    let response = server.handle(get(&format!("/engine.io/?EIO=4&transport=polling&codec=engine&key={}", exchange.public_key()))).await;
+    let packets = Packet::decode_payload(RawData::Text(body_text(&response))).unwrap();
+    assert!(Handshake::from_packet(&packets[0]).unwrap().key().is_some());
+
+    let response = server.handle(get("/engine.io/?EIO=4&transport=polling&codec=engine&key=bad")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let required = EngineServer::new(SessionConfig::default().with_encryption(EncryptionConfig::default().with_required(true)));
+    let response = required.handle(get("/engine.io/?EIO=4&transport=polling")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(required.session_count(), 0);
+}
+
+#[tokio::test]
+async fn polling_sessions_upgrade_to_advertised_transports() {
+    let server = EngineServer::new(SessionConfig::default().with_upgrades(vec!["memory".into()]));
+    let handshake = open(&server).await;
+    assert_eq!(handshake.upgrades(), ["memory".to_string()]);
+    let session = server.accept().await.unwrap();
+    let uri = polling(handshake.sid());
+
+    let probe = |packet_type| {
+        let mut packet = Packet::new(packet_type);
+        packet.with_data(RawData::Text("probe".into())).unwrap();
+        packet
+    };
+    let (transport, mut client) = MemoryTransport::pair();
+    let client_side = async {
+        client.send(probe(PacketType::Ping).into()).await.unwrap();
+        assert_eq!(client.next().await.unwrap().unwrap(), probe(PacketType::Pong));
+        client.send(Packet::new(PacketType::Upgrade).into()).await.unwrap();
+    };
+    let (poll, upgraded, ()) = tokio::join!(server.handle(get(&uri)), server.upgrade(handshake.sid(), transport), client_side);
+    upgraded.unwrap();
+    assert_eq!(session.transport(), "memory");
+
+    // The poll waiting when the session moved is released with a `Noop`, and later polls are refused.
+    let packets = packets(&poll);
+    assert_eq!(packets, vec![Packet::new(PacketType::Noop)]);
+    let refused = server.handle(get(&uri)).await;
+    assert_eq!(error_code(&refused), u64::from(ErrorCode::UnknownSid.code()));
+
+    session.send(RawData::Text("upgraded".into())).await.unwrap();
+    assert_eq!(client.next().await.unwrap().unwrap().data(), Some(&RawData::Text("upgraded".into())));
+}
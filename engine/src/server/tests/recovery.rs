@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use crate::protocol::{Handshake, Packet, PacketType, RawData};
+use crate::server::EngineServer;
+use crate::session::{RecoveryConfig, SessionConfig, SessionState};
+use super::{get, packets, polling};
+
+/// Returns the handshake URI that resumes a session.
+fn resume_uri(sid: &str, pid: &str, offset: u64) -> String {
+    format!("/engine.io/?EIO=4&transport=polling&resume={}&pid={}&offset={}", sid, pid, offset)
+}
+
+fn fast_server() -> EngineServer {
+    let config = SessionConfig::default()
+        .with_ping_interval(Duration::from_millis(30))
+        .with_ping_timeout(Duration::from_millis(30));
+    EngineServer::new(config).with_recovery(RecoveryConfig::default())
+}
+
+#[tokio::test]
+async fn handshake_resumes_timed_out_session() {
+    let server = fast_server();
+    let response = server.handle(get("/engine.io/?EIO=4&transport=polling")).await;
+    let handshake = Handshake::from_packet(&packets(&response)[0]).unwrap();
+    let sid = handshake.sid().to_string();
+    let session = server.accept().await.unwrap();
+    let uri = polling(&sid);
+
+    session.send(RawData::Text("one".into())).await.unwrap();
+    assert_eq!(packets(&server.handle(get(&uri)).await)[0].data(), Some(&RawData::Text("one".into())));
+
+    // The client goes quiet long enough to miss its heartbeat, then comes back.
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    session.send(RawData::Text("two".into())).await.unwrap();
+    let resume = resume_uri(&sid, handshake.pid().unwrap(), 1);
+    let response = server.handle(get(&resume)).await;
+    let packets = packets(&response);
+    assert_eq!(Handshake::from_packet(&packets[0]).unwrap().sid(), sid);
+
+    // Replayed packets follow the handshake, in the same response or the next poll.
+    let mut replayed: Vec<Packet> = packets[1..].to_vec();
+    while !replayed.iter().any(|packet| packet._type() == &PacketType::Message) {
+        replayed.extend(super::packets(&server.handle(get(&uri)).await));
+    }
+    let message = replayed.iter().find(|packet| packet._type() == &PacketType::Message).unwrap();
+    assert_eq!(message.data(), Some(&RawData::Text("two".into())));
+    assert_eq!(session.state(), SessionState::Open);
+}
+
+#[tokio::test]
+async fn unknown_resume_opens_fresh_session() {
+    let server = fast_server();
+    let response = server.handle(get(&resume_uri("nope", "nope", 0))).await;
+    let handshake = Handshake::from_packet(&packets(&response)[0]).unwrap();
+    assert_ne!(handshake.sid(), "nope");
+    assert_eq!(server.accept().await.unwrap().sid(), handshake.sid());
+}
+
+#[tokio::test]
+async fn resume_needs_the_private_id() {
+    let server = fast_server();
+    let response = server.handle(get("/engine.io/?EIO=4&transport=polling")).await;
+    let handshake = Handshake::from_packet(&packets(&response)[0]).unwrap();
+    let session = server.accept().await.unwrap();
+
+    // The sid alone, as found in polling URLs and the cookie, resumes nothing.
+    for uri in [
+        format!("/engine.io/?EIO=4&transport=polling&resume={}&offset=0", handshake.sid()),
+        resume_uri(handshake.sid(), handshake.sid(), 0),
+    ] {
+        let response = server.handle(get(&uri)).await;
+        let fresh = Handshake::from_packet(&packets(&response)[0]).unwrap();
+        assert_ne!(fresh.sid(), handshake.sid());
+        server.accept().await.unwrap();
+    }
+    assert_eq!(session.state(), SessionState::Open);
+}
+
+#[tokio::test]
+async fn resume_without_a_registry_entry_opens_fresh_session() {
+    let server = fast_server();
+    let response = server.handle(get("/engine.io/?EIO=4&transport=polling")).await;
+    let handshake = Handshake::from_packet(&packets(&response)[0]).unwrap();
+    let session = server.accept().await.unwrap();
+
+    // A session still parked for recovery but gone from the registry cannot be polled, so it is not resumed.
+    server.sessions.remove(handshake.sid());
+    let response = server.handle(get(&resume_uri(handshake.sid(), handshake.pid().unwrap(), 0))).await;
+    let fresh = Handshake::from_packet(&packets(&response)[0]).unwrap();
+    assert_ne!(fresh.sid(), handshake.sid());
+    assert_eq!(session.state(), SessionState::Open);
+}
@@ -0,0 +1,97 @@
+use std::convert::Infallible;
+use std::future::{ready, Ready};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::future::poll_fn;
+use http::{Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::protocol::{Handshake, Packet};
+use crate::server::{EngineLayer, EngineServer};
+use crate::session::SessionConfig;
+
+/// Answers every request with `404 fallback`.
+#[derive(Debug, Clone)]
+struct Fallback;
+
+impl Service<Request<Full<Bytes>>> for Fallback {
+    type Response = Response<Full<Bytes>>;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _request: Request<Full<Bytes>>) -> Self::Future {
+        let mut response = Response::new(Full::new(Bytes::from_static(b"fallback")));
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        ready(Ok(response))
+    }
+}
+
+/// Sends one request through the service, returning the status and body.
+async fn call<S, B>(service: &mut S, uri: &str, body: &'static str) -> (StatusCode, String)
+where
+    S: Service<Request<Full<Bytes>>, Response = Response<B>, Error = Infallible>,
+    B: http_body::Body,
+    B::Error: std::fmt::Debug,
+{
+    poll_fn(|cx| service.poll_ready(cx)).await.unwrap();
+    let method = if body.is_empty() { "GET" } else { "POST" };
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .body(Full::new(Bytes::from_static(body.as_bytes())))
+        .unwrap();
+    let response = service.call(request).await.unwrap();
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn engine_path_is_served_by_the_engine() {
+    let server = Arc::new(EngineServer::new(SessionConfig::default()));
+    let mut service = EngineLayer::new(server.clone()).layer(Fallback);
+
+    let (status, body) = call(&mut service, "/engine.io/?EIO=4&transport=polling", "").await;
+    assert_eq!(status, StatusCode::OK);
+    let packets = Packet::decode_payload_v4(&body).unwrap();
+    let handshake = Handshake::from_packet(&packets[0]).unwrap();
+    assert_eq!(server.accept().await.unwrap().sid(), handshake.sid());
+}
+
+#[tokio::test]
+async fn other_paths_pass_through() {
+    let server = Arc::new(EngineServer::new(SessionConfig::default()));
+    let mut service = EngineLayer::new(server.clone()).layer(Fallback);
+
+    assert_eq!(call(&mut service, "/", "").await, (StatusCode::NOT_FOUND, "fallback".into()));
+    assert_eq!(call(&mut service, "/engine.iox/?EIO=4&transport=polling", "").await.0, StatusCode::NOT_FOUND);
+    assert_eq!(server.session_count(), 0);
+}
+
+#[tokio::test]
+async fn custom_path_is_honoured() {
+    let server = Arc::new(EngineServer::new(SessionConfig::default()));
+    let layer = EngineLayer::new(server.clone()).with_path("/socket");
+    assert_eq!(layer.path(), "/socket");
+    let mut service = layer.layer(Fallback);
+
+    assert_eq!(call(&mut service, "/socket?EIO=4&transport=polling", "").await.0, StatusCode::OK);
+    assert_eq!(call(&mut service, "/engine.io/?EIO=4&transport=polling", "").await.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn oversized_bodies_are_rejected() {
+    let server = Arc::new(EngineServer::new(SessionConfig::default().with_max_payload(4)));
+    let mut service = EngineLayer::new(server).layer(Fallback);
+
+    let (status, _) = call(&mut service, "/engine.io/?EIO=4&transport=polling&sid=x", "too large").await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+}
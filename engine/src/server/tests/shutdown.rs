@@ -0,0 +1,79 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use http::StatusCode;
+
+use crate::protocol::{PacketType, RawData};
+use crate::server::EngineServer;
+use crate::session::{CloseReason, Session, SessionConfig, SessionEvent};
+use super::{get, open, packets, polling};
+
+/// Opens a polling session, returning it with its polling URI.
+async fn open_session(server: &EngineServer) -> (Session, String) {
+    let handshake = open(server).await;
+    let session = server.accept().await.unwrap();
+    (session, polling(handshake.sid()))
+}
+
+#[tokio::test]
+async fn shutdown_rejects_new_handshakes() {
+    let server = EngineServer::new(SessionConfig::default());
+    server.shutdown(Duration::from_secs(1)).await;
+    assert!(server.is_shutting_down());
+
+    let response = server.handle(get("/engine.io/?EIO=4&transport=polling")).await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(server.accept().await.is_none());
+}
+
+#[tokio::test]
+async fn shutdown_flushes_buffered_messages_before_close() {
+    let server = EngineServer::new(SessionConfig::default());
+    let (mut session, uri) = open_session(&server).await;
+
+    session.send(RawData::Text("bye".into())).await.unwrap();
+    server.shutdown(Duration::from_secs(1)).await;
+
+    let packets = packets(&server.handle(get(&uri)).await);
+    assert_eq!(packets[0].data(), Some(&RawData::Text("bye".into())));
+    assert_eq!(packets.last().unwrap()._type(), &PacketType::Close);
+    assert_eq!(session.recv().await, Some(SessionEvent::Close(CloseReason::ServerShutdown)));
+}
+
+#[tokio::test]
+async fn shutdown_releases_pending_poll() {
+    let server = Arc::new(EngineServer::new(SessionConfig::default()));
+    let (_session, uri) = open_session(&server).await;
+
+    let pending = tokio::spawn({
+        let server = server.clone();
+        async move { server.handle(get(&uri)).await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(!pending.is_finished());
+
+    server.shutdown(Duration::from_secs(1)).await;
+    let response = pending.await.unwrap();
+    assert_eq!(packets(&response)[0]._type(), &PacketType::Close);
+}
+
+#[tokio::test]
+async fn shutdown_force_closes_after_grace_period() {
+    let server = EngineServer::new(SessionConfig::default());
+    let (mut session, _uri) = open_session(&server).await;
+
+    // Nobody polls, so the transport fills up and the driver stalls mid-flush.
+    for n in 0..100 {
+        session.send(RawData::Text(n.to_string())).await.unwrap();
+    }
+    let started = Instant::now();
+    server.shutdown(Duration::from_millis(100)).await;
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    let mut last = None;
+    while let Some(event) = session.recv().await {
+        last = Some(event);
+    }
+    assert_eq!(last, Some(SessionEvent::Close(CloseReason::ServerShutdown)));
+}
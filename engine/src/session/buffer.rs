@@ -0,0 +1,294 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use tokio::sync::Notify;
+
+use crate::protocol::{EncodedPacket, RawData};
+use crate::session::{CloseReason, SessionError};
+
+/// What `try_send` does when the send buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Reject the new packet with `SessionError::BufferFull`.
+    #[default]
+    Error,
+    /// Discard the oldest queued packet to make room.
+    DropOldest,
+    /// Silently discard the new packet.
+    DropNewest,
+    /// Reject the new packet and close the session.
+    Disconnect,
+}
+
+/// Outbound queue limits for a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendBufferConfig {
+    /// Queued packets at which the buffer counts as full.
+    high_water_mark: usize,
+    /// Queued packets at which a full buffer accepts sends again and raises `Drain`.
+    low_water_mark: usize,
+    /// Queued payload bytes at which the buffer counts as full.
+    high_water_bytes: usize,
+    /// Queued payload bytes a full buffer must also be down to before it accepts sends again.
+    low_water_bytes: usize,
+    /// Behaviour of `try_send` on a full buffer.
+    policy: OverflowPolicy,
+}
+
+impl Default for SendBufferConfig {
+    fn default() -> Self {
+        Self {
+            high_water_mark: 1024,
+            low_water_mark: 256,
+            high_water_bytes: 16 * 1024 * 1024,
+            low_water_bytes: 4 * 1024 * 1024,
+            policy: OverflowPolicy::default(),
+        }
+    }
+}
+
+impl SendBufferConfig {
+    /// Creates a buffer configuration. The low water mark is capped at the high water mark.
+    pub fn new(high_water_mark: usize, low_water_mark: usize, policy: OverflowPolicy) -> Self {
+        let high_water_mark = high_water_mark.max(1);
+        Self {
+            high_water_mark,
+            low_water_mark: low_water_mark.min(high_water_mark),
+            policy,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the byte water marks, counted over queued payloads. The low water mark is capped at the high water mark.
+    pub fn with_byte_water_marks(mut self, high_water_bytes: usize, low_water_bytes: usize) -> Self {
+        self.high_water_bytes = high_water_bytes.max(1);
+        self.low_water_bytes = low_water_bytes.min(self.high_water_bytes);
+        self
+    }
+
+    /// Returns the high water mark.
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water_mark
+    }
+
+    /// Returns the low water mark.
+    pub fn low_water_mark(&self) -> usize {
+        self.low_water_mark
+    }
+
+    /// Returns the byte high water mark.
+    pub fn high_water_bytes(&self) -> usize {
+        self.high_water_bytes
+    }
+
+    /// Returns the byte low water mark.
+    pub fn low_water_bytes(&self) -> usize {
+        self.low_water_bytes
+    }
+
+    /// Returns the overflow policy.
+    pub fn policy(&self) -> OverflowPolicy {
+        self.policy
+    }
+}
+
+/// Next item for the driver to write.
+#[derive(Debug)]
+pub(crate) enum Outbound {
+    /// A queued packet; `drained` is set when taking it brought a full buffer down to the low water mark.
+    Packet { packet: EncodedPacket, drained: bool },
+    /// The session is closing and every queued packet has been taken.
+    Close(CloseReason),
+    /// The session must close at once, abandoning queued packets.
+    Abort(CloseReason),
+}
+
+#[derive(Debug, Default)]
+struct State {
+    queue: VecDeque<EncodedPacket>,
+    /// Payload bytes held in `queue`.
+    bytes: usize,
+    /// Set on reaching the high water mark, cleared at the low water mark.
+    full: bool,
+    /// Set once the session is asked to close after flushing.
+    closing: Option<CloseReason>,
+    /// Set when the session must close without flushing.
+    aborted: Option<CloseReason>,
+    /// Set once the driver has stopped.
+    closed: bool,
+}
+
+/// Bounded outbound queue shared by a `Session` handle and its driver.
+#[derive(Debug)]
+pub(crate) struct SendBuffer {
+    config: SendBufferConfig,
+    state: Mutex<State>,
+    readable: Notify,
+    writable: Notify,
+    abort: Notify,
+}
+
+impl SendBuffer {
+    pub(crate) fn new(config: SendBufferConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::default()),
+            readable: Notify::new(),
+            writable: Notify::new(),
+            abort: Notify::new(),
+        }
+    }
+
+    /// Returns the number of queued packets.
+    pub(crate) fn len(&self) -> usize {
+        self.state.lock().unwrap().queue.len()
+    }
+
+    fn enqueue(&self, state: &mut State, packet: EncodedPacket) {
+        state.bytes += size(&packet);
+        state.queue.push_back(packet);
+        if state.queue.len() >= self.config.high_water_mark || state.bytes >= self.config.high_water_bytes {
+            state.full = true;
+        }
+        self.readable.notify_one();
+    }
+
+    fn dequeue(&self, state: &mut State) -> Option<EncodedPacket> {
+        let packet = state.queue.pop_front()?;
+        state.bytes -= size(&packet);
+        Some(packet)
+    }
+
+    /// Queues a packet, waiting while the buffer is full.
+    pub(crate) async fn push(&self, packet: EncodedPacket) -> Result<(), SessionError> {
+        loop {
+            let writable = self.writable.notified();
+            tokio::pin!(writable);
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.closing.is_some() || state.closed {
+                    return Err(SessionError::Closed);
+                }
+                if !state.full {
+                    self.enqueue(&mut state, packet);
+                    return Ok(());
+                }
+                writable.as_mut().enable();
+            }
+            writable.await;
+        }
+    }
+
+    /// Queues a packet without waiting, applying the overflow policy if the buffer is full.
+    pub(crate) fn try_push(&self, packet: EncodedPacket) -> Result<(), SessionError> {
+        let mut state = self.state.lock().unwrap();
+        if state.closing.is_some() || state.closed {
+            return Err(SessionError::Closed);
+        }
+        if !state.full {
+            self.enqueue(&mut state, packet);
+            return Ok(());
+        }
+        match self.config.policy {
+            OverflowPolicy::Error => Err(SessionError::BufferFull),
+            OverflowPolicy::DropOldest => {
+                // Evict until the new packet fits under both water marks, or nothing older is left.
+                let incoming = size(&packet);
+                while !state.queue.is_empty()
+                    && (state.queue.len() >= self.config.high_water_mark
+                        || state.bytes + incoming > self.config.high_water_bytes)
+                {
+                    self.dequeue(&mut state);
+                }
+                self.enqueue(&mut state, packet);
+                Ok(())
+            }
+            OverflowPolicy::DropNewest => Ok(()),
+            OverflowPolicy::Disconnect => {
+                self.abort_locked(&mut state, CloseReason::BufferOverflow);
+                Err(SessionError::BufferFull)
+            }
+        }
+    }
+
+    /// Asks the driver to flush queued packets and close. The first reason given wins.
+    pub(crate) fn close(&self, reason: CloseReason) {
+        let mut state = self.state.lock().unwrap();
+        state.closing.get_or_insert(reason);
+        self.readable.notify_one();
+        self.writable.notify_waiters();
+    }
+
+    /// Asks the driver to close at once, abandoning queued packets and any write in progress.
+    pub(crate) fn abort(&self, reason: CloseReason) {
+        let mut state = self.state.lock().unwrap();
+        self.abort_locked(&mut state, reason);
+    }
+
+    fn abort_locked(&self, state: &mut State, reason: CloseReason) {
+        state.aborted.get_or_insert(reason);
+        self.readable.notify_one();
+        self.writable.notify_waiters();
+        self.abort.notify_waiters();
+    }
+
+    /// Marks the buffer as closed once the driver stops, failing further sends.
+    pub(crate) fn shutdown(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        state.queue.clear();
+        state.bytes = 0;
+        self.writable.notify_waiters();
+    }
+
+    /// Waits until the session is aborted, returning the reason.
+    /// Lets the driver give up on a write the peer is not reading.
+    pub(crate) async fn aborted(&self) -> CloseReason {
+        loop {
+            let abort = self.abort.notified();
+            tokio::pin!(abort);
+            {
+                let state = self.state.lock().unwrap();
+                if let Some(reason) = state.aborted {
+                    return reason;
+                }
+                abort.as_mut().enable();
+            }
+            abort.await;
+        }
+    }
+
+    /// Waits for the next item to write.
+    pub(crate) async fn pop(&self) -> Outbound {
+        loop {
+            let readable = self.readable.notified();
+            tokio::pin!(readable);
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some(reason) = state.aborted {
+                    return Outbound::Abort(reason);
+                }
+                if let Some(packet) = self.dequeue(&mut state) {
+                    let drained = state.full
+                        && state.queue.len() <= self.config.low_water_mark
+                        && state.bytes <= self.config.low_water_bytes;
+                    if drained {
+                        state.full = false;
+                        self.writable.notify_waiters();
+                    }
+                    return Outbound::Packet { packet, drained };
+                }
+                if let Some(reason) = state.closing {
+                    return Outbound::Close(reason);
+                }
+                readable.as_mut().enable();
+            }
+            readable.await;
+        }
+    }
+}
+
+/// Bytes a packet counts against the byte water marks: its payload, before encoding.
+fn size(packet: &EncodedPacket) -> usize {
+    packet.packet().data().map_or(0, RawData::len)
+}
@@ -0,0 +1,230 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "compression")]
+use crate::compression::CompressionConfig;
+#[cfg(feature = "encryption")]
+use crate::encryption::EncryptionConfig;
+use crate::metrics::Metrics;
+#[cfg(feature = "compression")]
+use crate::protocol::Compression;
+use crate::protocol::Handshake;
+use crate::session::{RateLimitConfig, SendBufferConfig};
+#[cfg(feature = "signing")]
+use crate::signing::SignatureConfig;
+
+/// Server-side session settings, advertised to clients in the handshake.
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    /// Interval between server pings.
+    ping_interval: Duration,
+    /// Time allowed for a pong reply before the session is closed.
+    ping_timeout: Duration,
+    /// Maximum number of bytes per payload.
+    max_payload: usize,
+    /// Transports the session may upgrade to.
+    upgrades: Vec<String>,
+    /// Outbound queue limits.
+    send_buffer: SendBufferConfig,
+    /// Inbound limits; unlimited if unset.
+    rate_limit: Option<RateLimitConfig>,
+    /// Receiver for protocol metrics.
+    metrics: Option<Arc<dyn Metrics>>,
+    /// Message compression; messages are sent uncompressed if unset.
+    #[cfg(feature = "compression")]
+    compression: Option<CompressionConfig>,
+    /// Algorithm agreed with the client's handshake request; a server sends uncompressed messages without one.
+    #[cfg(feature = "compression")]
+    agreed_compression: Option<Compression>,
+    /// Message encryption; sessions are encrypted only if the client asks and this is set.
+    #[cfg(feature = "encryption")]
+    encryption: Option<EncryptionConfig>,
+    /// Message signature verification; signatures are not checked or surfaced if unset.
+    #[cfg(feature = "signing")]
+    signatures: Option<SignatureConfig>,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            ping_interval: Duration::from_millis(25_000),
+            ping_timeout: Duration::from_millis(20_000),
+            max_payload: 1_000_000,
+            upgrades: Vec::new(),
+            send_buffer: SendBufferConfig::default(),
+            rate_limit: None,
+            metrics: None,
+            #[cfg(feature = "compression")]
+            compression: None,
+            #[cfg(feature = "compression")]
+            agreed_compression: None,
+            #[cfg(feature = "encryption")]
+            encryption: None,
+            #[cfg(feature = "signing")]
+            signatures: None,
+        }
+    }
+}
+
+impl SessionConfig {
+    /// Returns the ping interval.
+    pub fn ping_interval(&self) -> Duration {
+        self.ping_interval
+    }
+
+    /// Sets the ping interval.
+    pub fn with_ping_interval(mut self, interval: Duration) -> Self {
+        self.ping_interval = interval;
+        self
+    }
+
+    /// Returns the ping timeout.
+    pub fn ping_timeout(&self) -> Duration {
+        self.ping_timeout
+    }
+
+    /// Sets the ping timeout.
+    pub fn with_ping_timeout(mut self, timeout: Duration) -> Self {
+        self.ping_timeout = timeout;
+        self
+    }
+
+    /// Returns the maximum payload size in bytes.
+    pub fn max_payload(&self) -> usize {
+        self.max_payload
+    }
+
+    /// Sets the maximum payload size in bytes.
+    pub fn with_max_payload(mut self, max_payload: usize) -> Self {
+        self.max_payload = max_payload;
+        self
+    }
+
+    /// Returns the transports sessions may upgrade to.
+    pub fn upgrades(&self) -> &[String] {
+        &self.upgrades
+    }
+
+    /// Sets the transports sessions may upgrade to.
+    pub fn with_upgrades(mut self, upgrades: Vec<String>) -> Self {
+        self.upgrades = upgrades;
+        self
+    }
+
+    /// Returns the outbound queue limits.
+    pub fn send_buffer(&self) -> SendBufferConfig {
+        self.send_buffer
+    }
+
+    /// Sets the outbound queue limits.
+    pub fn with_send_buffer(mut self, send_buffer: SendBufferConfig) -> Self {
+        self.send_buffer = send_buffer;
+        self
+    }
+
+    /// Returns the inbound limits, if any.
+    pub fn rate_limit(&self) -> Option<RateLimitConfig> {
+        self.rate_limit
+    }
+
+    /// Sets the inbound limits.
+    pub fn with_rate_limit(mut self, rate_limit: RateLimitConfig) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Returns the metrics receiver, if any.
+    pub fn metrics(&self) -> Option<&Arc<dyn Metrics>> {
+        self.metrics.as_ref()
+    }
+
+    /// Reports every session's traffic to `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Returns the message compression settings, if any.
+    #[cfg(feature = "compression")]
+    pub fn compression(&self) -> Option<&CompressionConfig> {
+        self.compression.as_ref()
+    }
+
+    /// Advertises `compression`'s algorithms in the handshake and decompresses messages that use them.
+    /// A server compresses its own messages only with an algorithm a client asked for in its handshake request.
+    /// On the client, messages are compressed with the first advertised algorithm `compression` also accepts.
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, compression: CompressionConfig) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Returns the message encryption settings, if any.
+    #[cfg(feature = "encryption")]
+    pub fn encryption(&self) -> Option<&EncryptionConfig> {
+        self.encryption.as_ref()
+    }
+
+    /// Encrypts sessions whose client sends a public key, with keys rotated as `encryption` says.
+    #[cfg(feature = "encryption")]
+    pub fn with_encryption(mut self, encryption: EncryptionConfig) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    /// Returns the message signature settings, if any.
+    #[cfg(feature = "signing")]
+    pub fn signatures(&self) -> Option<&SignatureConfig> {
+        self.signatures.as_ref()
+    }
+
+    /// Verifies signed messages before delivering them as `SessionEvent::Signed`; a message that fails is dropped
+    /// and reported through `Metrics::signature_rejected`.
+    #[cfg(feature = "signing")]
+    pub fn with_signatures(mut self, signatures: SignatureConfig) -> Self {
+        self.signatures = Some(signatures);
+        self
+    }
+
+    /// Returns a copy for a client that sent `key` with its handshake request, if any.
+    #[cfg(feature = "encryption")]
+    pub(crate) fn negotiate_encryption(mut self, key: Option<String>) -> Self {
+        if let (Some(encryption), Some(key)) = (self.encryption.as_mut(), key) {
+            *encryption = encryption.clone().with_peer_key(key);
+        }
+        self
+    }
+
+    /// Returns a copy limited to the compression algorithms a client offered, uncompressed if none are in common.
+    #[cfg(feature = "compression")]
+    pub(crate) fn negotiate_compression(mut self, offered: &[Compression], dictionary: Option<u32>) -> Self {
+        self.compression = self.compression
+            .and_then(|compression| compression.negotiate(offered, dictionary));
+        self.agreed_compression = self.compression.as_ref()
+            .and_then(|compression| compression.algorithms().first().copied());
+        self
+    }
+
+    /// Returns the algorithm agreed with the client, if any.
+    #[cfg(feature = "compression")]
+    pub(crate) fn agreed_compression(&self) -> Option<Compression> {
+        self.agreed_compression
+    }
+
+    /// Builds the handshake advertised for a new session.
+    pub fn handshake(&self, sid: String) -> Handshake {
+        let handshake = Handshake::new(
+            sid,
+            self.upgrades.clone(),
+            self.ping_interval.as_millis() as u64,
+            self.ping_timeout.as_millis() as u64,
+            self.max_payload as u64,
+        );
+        #[cfg(feature = "compression")]
+        let handshake = match &self.compression {
+            Some(compression) => handshake.with_compression(compression.algorithms().to_vec(), compression.dictionary_id()),
+            None => handshake,
+        };
+        handshake
+    }
+}
@@ -0,0 +1,573 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::{FutureExt, SinkExt, StreamExt};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+#[cfg(feature = "compression")]
+use crate::compression::CompressionConfig;
+use crate::metrics::{self, Metrics};
+use crate::protocol::{EncodedPacket, Packet, PacketType, RawData};
+use crate::rt;
+use crate::session::buffer::{Outbound, SendBuffer};
+use crate::session::limit::{RateLimitConfig, RateLimitPolicy, RateLimiter};
+use crate::session::recovery::{Recovery, ReplayLog, Resume};
+use crate::session::state::Lifecycle;
+use crate::session::upgrade::Upgrade;
+use crate::session::{CloseReason, SessionError, SessionEvent, SessionState, TransitionReason};
+#[cfg(feature = "signing")]
+use crate::signing::SignatureConfig;
+use crate::transport::{Transport, TransportError};
+
+#[cfg(feature = "encryption")]
+pub(crate) use crate::encryption::SecureChannel;
+
+/// Stands in for an encrypted session's keys; without the `encryption` feature no session has any.
+#[cfg(not(feature = "encryption"))]
+pub(crate) enum SecureChannel {}
+
+#[cfg(not(feature = "encryption"))]
+impl SecureChannel {
+    pub(crate) const OVERHEAD: usize = 0;
+
+    fn seal(&mut self, _: &Packet) -> Result<Packet, std::convert::Infallible> {
+        match *self {}
+    }
+
+    fn open(&mut self, _: Packet) -> Result<Packet, std::convert::Infallible> {
+        match *self {}
+    }
+}
+
+/// Which side of the heartbeat a session drives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Role {
+    /// Sends pings and expects pongs.
+    Server,
+    /// Answers pings and expects them on schedule.
+    Client,
+}
+
+/// Heartbeat timer state.
+struct Heartbeat {
+    role: Role,
+    interval: Duration,
+    timeout: Duration,
+    deadline: Instant,
+    awaiting_pong: bool,
+}
+
+impl Heartbeat {
+    fn new(role: Role, interval: Duration, timeout: Duration) -> Self {
+        let mut heartbeat = Self {
+            role,
+            interval,
+            timeout,
+            deadline: rt::now(),
+            awaiting_pong: false,
+        };
+        heartbeat.reset();
+        heartbeat
+    }
+
+    /// Restarts the timer after a ping (client) or pong (server) is received.
+    fn reset(&mut self) {
+        self.awaiting_pong = false;
+        self.deadline = match self.role {
+            Role::Server => rt::now() + self.interval,
+            Role::Client => rt::now() + self.interval + self.timeout,
+        };
+    }
+
+    /// Called when the deadline passes.
+    /// Returns a ping to send, or `None` if the peer timed out.
+    fn expire(&mut self) -> Option<Packet> {
+        if self.role == Role::Client || self.awaiting_pong {
+            return None;
+        }
+        self.awaiting_pong = true;
+        self.deadline = rt::now() + self.timeout;
+        Some(Packet::new(PacketType::Ping))
+    }
+}
+
+/// Shared state a driver reports to.
+pub(crate) struct Context {
+    pub(crate) role: Role,
+    pub(crate) interval: Duration,
+    pub(crate) timeout: Duration,
+    pub(crate) buffer: Arc<SendBuffer>,
+    pub(crate) lifecycle: Arc<Lifecycle>,
+    pub(crate) events: UnboundedSender<SessionEvent>,
+    /// `Message` packets received so far; the client's offset.
+    pub(crate) received: Arc<AtomicU64>,
+    /// Name of the current transport, as reported by `Session::transport`.
+    pub(crate) transport: Arc<Mutex<&'static str>>,
+    /// Inbound limits, if any.
+    pub(crate) rate_limit: Option<RateLimitConfig>,
+    /// Receiver for protocol metrics.
+    pub(crate) metrics: Option<Arc<dyn Metrics>>,
+    /// Algorithms inbound messages may be compressed with; compressed messages are passed through as-is if unset.
+    #[cfg(feature = "compression")]
+    pub(crate) compression: Option<CompressionConfig>,
+    /// Verifier for signed inbound messages; signatures are ignored if unset.
+    #[cfg(feature = "signing")]
+    pub(crate) signatures: Option<SignatureConfig>,
+}
+
+/// Recovery state of a session accepted through `Recovery`.
+pub(crate) struct Recoverable {
+    pub(crate) registry: Recovery,
+    pub(crate) sid: String,
+    /// Handshake resent on every resumed transport.
+    pub(crate) open: EncodedPacket,
+    pub(crate) resumes: UnboundedReceiver<Resume>,
+    pub(crate) log: ReplayLog,
+}
+
+impl Recoverable {
+    /// Returns the packets to replay for a resume, or rejects it if they are no longer available.
+    fn validate(&self, resume: Resume) -> Option<(Resume, Vec<EncodedPacket>)> {
+        match self.log.replay(resume.offset) {
+            Some(packets) => Some((resume, packets)),
+            None => {
+                let _ = resume.reply.send(Err(SessionError::NotRecoverable));
+                None
+            }
+        }
+    }
+}
+
+/// Why the connected phase ended.
+enum Stop {
+    /// The session is over.
+    Closed(CloseReason),
+    /// The transport was lost; a recoverable session may still be resumed.
+    Lost(CloseReason),
+    /// The client resumed on a new transport.
+    Resumed(Resume, Vec<EncodedPacket>),
+}
+
+/// Drives a session: writes the send buffer, dispatches inbound packets and runs the heartbeat.
+/// A recoverable session outlives its transport for the recovery window.
+pub(crate) async fn run(
+    mut transport: Box<dyn Transport>,
+    ctx: Context,
+    mut recovery: Option<Recoverable>,
+    mut upgrades: UnboundedReceiver<Upgrade>,
+    mut channel: Option<SecureChannel>,
+) {
+    let mut replay = VecDeque::new();
+    let mut limiter = ctx.rate_limit.as_ref().map(RateLimiter::new);
+
+    let reason = loop {
+        let (resume, packets) = match connected(&mut transport, &ctx, &mut recovery, &mut upgrades, &mut replay, &mut limiter, &mut channel).await {
+            Stop::Closed(reason) => break reason,
+            Stop::Resumed(resume, packets) => {
+                close(&mut transport, &ctx.buffer).await;
+                (resume, packets)
+            }
+            Stop::Lost(reason) => {
+                debug!(%reason, "transport lost");
+                let Some(recovery) = recovery.as_mut() else {
+                    break reason;
+                };
+                close(&mut transport, &ctx.buffer).await;
+                match disconnected(&ctx, recovery, &mut upgrades).await {
+                    Ok(resumed) => resumed,
+                    Err(closed) => break closed.unwrap_or(reason),
+                }
+            }
+        };
+
+        info!(offset = resume.offset, transport = resume.transport.name(), "session resumed");
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("transport", resume.transport.name());
+        *ctx.transport.lock().unwrap() = resume.transport.name();
+        transport = metrics::instrument(resume.transport, ctx.metrics.as_ref());
+        let open = recovery.as_ref().map(|recovery| recovery.open.clone());
+        let result = match open {
+            Some(open) => send(&mut transport, open, &ctx.buffer).await
+                .map_err(|_| SessionError::Transport(TransportError::Closed)),
+            None => Err(SessionError::NotRecoverable),
+        };
+        if result.is_ok() {
+            replay = packets.into();
+        }
+        let _ = resume.reply.send(result);
+    };
+
+    if let Some(recovery) = &recovery {
+        recovery.registry.unregister(&recovery.sid);
+    }
+    ctx.buffer.shutdown();
+    let _ = ctx.lifecycle.transition(SessionState::Closed, TransitionReason::Closed(reason));
+    if let Some(metrics) = &ctx.metrics {
+        metrics.session_closed(reason);
+    }
+    debug!(%reason, "session closed");
+    let _ = ctx.events.send(SessionEvent::Close(reason));
+    close(&mut transport, &ctx.buffer).await;
+}
+
+/// Runs the session over one transport until it closes, is lost or is replaced.
+async fn connected(
+    transport: &mut Box<dyn Transport>,
+    ctx: &Context,
+    recovery: &mut Option<Recoverable>,
+    upgrades: &mut UnboundedReceiver<Upgrade>,
+    replay: &mut VecDeque<EncodedPacket>,
+    limiter: &mut Option<RateLimiter>,
+    channel: &mut Option<SecureChannel>,
+) -> Stop {
+    let mut heartbeat = Heartbeat::new(ctx.role, ctx.interval, ctx.timeout);
+    let mut delayed: Option<(Packet, Instant)> = None;
+
+    loop {
+        if let Some(packet) = replay.pop_front() {
+            if let Some(packet) = seal(channel, packet) {
+                if let Err(reason) = send(transport, packet, &ctx.buffer).await {
+                    return lost_or_closed(reason);
+                }
+            }
+            continue;
+        }
+
+        tokio::select! {
+            outbound = ctx.buffer.pop() => match outbound {
+                Outbound::Packet { packet, drained } => {
+                    if let Some(recovery) = recovery.as_mut().filter(|_| packet._type() == &PacketType::Message) {
+                        recovery.log.push(packet.clone());
+                    }
+                    if let Some(packet) = seal(channel, packet) {
+                        if let Err(reason) = send(transport, packet, &ctx.buffer).await {
+                            return lost_or_closed(reason);
+                        }
+                    }
+                    if drained {
+                        let _ = ctx.events.send(SessionEvent::Drain);
+                    }
+                }
+                Outbound::Close(reason) => return match send(transport, Packet::new(PacketType::Close).into(), &ctx.buffer).await {
+                    Ok(()) | Err(CloseReason::TransportError) => Stop::Closed(reason),
+                    Err(aborted) => Stop::Closed(aborted),
+                },
+                Outbound::Abort(reason) => {
+                    // Best effort: a peer that is not reading must not hold up the abort.
+                    let _ = transport.send(Packet::new(PacketType::Close).into()).now_or_never();
+                    return Stop::Closed(reason);
+                }
+            },
+            _ = rt::sleep_until(delayed.as_ref().map_or(heartbeat.deadline, |(_, until)| *until)), if delayed.is_some() => {
+                let (packet, _) = delayed.take().unwrap();
+                match limiter.as_mut().map_or(Ok(()), |limiter| limiter.check(&packet)) {
+                    Ok(()) => if let Some(stop) = dispatch(packet, transport, ctx, recovery, &mut heartbeat, channel).await {
+                        return stop;
+                    },
+                    Err(wait) => delayed = Some((packet, rt::now() + wait)),
+                }
+            },
+            // Reading stops while a rate-limited packet is held back.
+            // Packets the current state ignores still count against the limits.
+            incoming = transport.next(), if delayed.is_none() => match incoming {
+                Some(Ok(packet)) => match limiter.as_mut().map_or(Ok(()), |limiter| limiter.check(&packet)) {
+                    Ok(()) if !ctx.lifecycle.state().can_receive(packet._type()) => {}
+                    Ok(()) => if let Some(stop) = dispatch(packet, transport, ctx, recovery, &mut heartbeat, channel).await {
+                        return stop;
+                    },
+                    Err(wait) => {
+                        debug!(?wait, "rate limit exceeded");
+                        match limiter.as_ref().map(RateLimiter::policy).unwrap_or_default() {
+                            RateLimitPolicy::Delay => delayed = Some((packet, rt::now() + wait)),
+                            RateLimitPolicy::Drop => {}
+                            RateLimitPolicy::Error => {
+                                if let Err(reason) = send(transport, Packet::error("rate limit exceeded").into(), &ctx.buffer).await {
+                                    return lost_or_closed(reason);
+                                }
+                            }
+                            RateLimitPolicy::Disconnect => {
+                                let _ = send(transport, Packet::new(PacketType::Close).into(), &ctx.buffer).await;
+                                return Stop::Closed(CloseReason::RateLimited);
+                            }
+                        }
+                    }
+                },
+                Some(Err(TransportError::Decoding(_))) => return Stop::Closed(CloseReason::ParseError),
+                Some(Err(_)) => return Stop::Lost(CloseReason::TransportError),
+                None => return Stop::Lost(CloseReason::TransportClose),
+            },
+            _ = rt::sleep_until(heartbeat.deadline) => match heartbeat.expire() {
+                Some(ping) => {
+                    trace!("sending ping");
+                    if let Err(reason) = send(transport, ping.into(), &ctx.buffer).await {
+                        return lost_or_closed(reason);
+                    }
+                    if let Some(limiter) = limiter.as_mut() {
+                        limiter.ping_sent();
+                    }
+                }
+                None => {
+                    debug!("heartbeat timed out");
+                    if let Some(metrics) = &ctx.metrics {
+                        metrics.heartbeat_timeout();
+                    }
+                    return Stop::Lost(CloseReason::PingTimeout);
+                }
+            },
+            Some(upgrade) = upgrades.recv() => {
+                if let Some(stop) = switch(transport, upgrade, ctx, recovery, &mut heartbeat, channel).await {
+                    return stop;
+                }
+            }
+            resume = next_resume(recovery) => {
+                if let Some((resume, packets)) = recovery.as_ref().and_then(|recovery| recovery.validate(resume)) {
+                    return Stop::Resumed(resume, packets);
+                }
+            }
+        }
+    }
+}
+
+/// Handles an admitted inbound packet. Returns `Some` if the connected phase should end.
+async fn dispatch(
+    packet: Packet,
+    transport: &mut Box<dyn Transport>,
+    ctx: &Context,
+    recovery: &mut Option<Recoverable>,
+    heartbeat: &mut Heartbeat,
+    channel: &mut Option<SecureChannel>,
+) -> Option<Stop> {
+    match packet._type() {
+        PacketType::Ping if ctx.role == Role::Client => {
+            trace!("received ping");
+            heartbeat.reset();
+            let mut pong = Packet::new(PacketType::Pong);
+            pong.with_data(RawData::Text(ctx.received.load(Ordering::Acquire).to_string())).ok();
+            if let Err(reason) = send(transport, pong.into(), &ctx.buffer).await {
+                return Some(lost_or_closed(reason));
+            }
+        }
+        PacketType::Pong if ctx.role == Role::Server => {
+            trace!("received pong");
+            heartbeat.reset();
+            let acknowledged = match packet.data() {
+                Some(RawData::Text(offset)) => offset.parse::<u64>().ok(),
+                _ => None,
+            };
+            if let (Some(recovery), Some(offset)) = (recovery.as_mut(), acknowledged) {
+                recovery.log.acknowledge(offset);
+            }
+        }
+        PacketType::Message => {
+            // Decryption comes first: messages are compressed before they are encrypted.
+            let packet = match channel {
+                Some(channel) => match channel.open(packet) {
+                    Ok(packet) => packet,
+                    Err(_) => {
+                        debug!("rejected message that failed decryption");
+                        return Some(Stop::Closed(CloseReason::ParseError));
+                    }
+                },
+                None => packet,
+            };
+            #[cfg(feature = "compression")]
+            let packet = match &ctx.compression {
+                Some(compression) => match compression.decompress(packet) {
+                    Ok(packet) => packet,
+                    Err(_) => {
+                        debug!("failed to decompress message");
+                        return Some(Stop::Closed(CloseReason::ParseError));
+                    }
+                },
+                None => packet,
+            };
+            // Signatures cover the data as sent, so they are checked last.
+            #[cfg(feature = "signing")]
+            let signature = match &ctx.signatures {
+                // A replayed or unverifiable message is dropped, not treated as a broken stream.
+                Some(signatures) => match signatures.verify(&packet) {
+                    Ok(signature) => signature,
+                    Err(error) => {
+                        debug!(%error, "dropped message that failed signature verification");
+                        if let Some(metrics) = &ctx.metrics {
+                            metrics.signature_rejected(&error);
+                        }
+                        return None;
+                    }
+                },
+                None => None,
+            };
+            #[cfg(not(feature = "signing"))]
+            let signature = None;
+            ctx.received.fetch_add(1, Ordering::AcqRel);
+            let data = packet.data()
+                .cloned()
+                .unwrap_or(RawData::Text(String::new()));
+            let event = match signature {
+                Some(signature) => SessionEvent::Signed(data, signature),
+                None => SessionEvent::Message(data),
+            };
+            let _ = ctx.events.send(event);
+        }
+        PacketType::Close => return Some(Stop::Closed(CloseReason::TransportClose)),
+        _ => {}
+    }
+    None
+}
+
+/// Waits out the recovery window without a transport.
+/// Returns the resume that ended the wait, or the reason the session closed instead (`None` if the window ran out).
+async fn disconnected(
+    ctx: &Context,
+    recovery: &mut Recoverable,
+    upgrades: &mut UnboundedReceiver<Upgrade>,
+) -> Result<(Resume, Vec<EncodedPacket>), Option<CloseReason>> {
+    let window = rt::sleep(recovery.registry.config().window());
+    tokio::pin!(window);
+
+    loop {
+        tokio::select! {
+            _ = &mut window => return Err(None),
+            resume = recovery.resumes.recv() => match resume {
+                Some(resume) => {
+                    if let Some(resumed) = recovery.validate(resume) {
+                        return Ok(resumed);
+                    }
+                }
+                None => return Err(None),
+            },
+            // There is no transport to move off; the client resumes instead.
+            Some(upgrade) = upgrades.recv() => abort_upgrade(ctx, upgrade),
+            // Messages sent while disconnected go straight to the replay log.
+            outbound = ctx.buffer.pop() => match outbound {
+                Outbound::Packet { packet, drained } => {
+                    if packet._type() == &PacketType::Message {
+                        recovery.log.push(packet);
+                    }
+                    if drained {
+                        let _ = ctx.events.send(SessionEvent::Drain);
+                    }
+                }
+                Outbound::Close(reason) | Outbound::Abort(reason) => return Err(Some(reason)),
+            },
+        }
+    }
+}
+
+/// Moves the session onto a probed transport.
+/// The client announces the move with `Upgrade`; the server releases the old transport with a `Noop`.
+/// Packets the peer wrote to the old transport before moving are still delivered: the client reads the old transport
+/// up to the server's `Noop`, and the server takes whatever has already arrived on it.
+/// Returns `Some` if the connected phase should end.
+async fn switch(
+    transport: &mut Box<dyn Transport>,
+    mut upgrade: Upgrade,
+    ctx: &Context,
+    recovery: &mut Option<Recoverable>,
+    heartbeat: &mut Heartbeat,
+    channel: &mut Option<SecureChannel>,
+) -> Option<Stop> {
+    match ctx.role {
+        Role::Client => {
+            if let Err(reason) = send(&mut upgrade.transport, Packet::new(PacketType::Upgrade).into(), &ctx.buffer).await {
+                abort_upgrade(ctx, upgrade);
+                return (reason != CloseReason::TransportError).then_some(Stop::Closed(reason));
+            }
+            let drained = rt::timeout(ctx.timeout, async {
+                while let Some(Ok(packet)) = transport.next().await {
+                    if packet._type() == &PacketType::Noop {
+                        break;
+                    }
+                    if let Some(stop) = dispatch(packet, transport, ctx, recovery, heartbeat, channel).await {
+                        return Some(stop);
+                    }
+                }
+                None
+            }).await;
+            if let Some(Some(stop)) = drained {
+                let _ = upgrade.reply.send(Err(SessionError::Closed));
+                return Some(stop);
+            }
+        }
+        Role::Server => {
+            let _ = send(transport, Packet::new(PacketType::Noop).into(), &ctx.buffer).await;
+            while let Some(Some(Ok(packet))) = transport.next().now_or_never() {
+                if let Some(stop) = dispatch(packet, transport, ctx, recovery, heartbeat, channel).await {
+                    let _ = upgrade.reply.send(Err(SessionError::Closed));
+                    return Some(stop);
+                }
+            }
+        }
+    }
+
+    close(transport, &ctx.buffer).await;
+    info!(from = transport.name(), to = upgrade.transport.name(), "transport upgraded");
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("transport", upgrade.transport.name());
+    if let Some(metrics) = &ctx.metrics {
+        metrics.upgraded(transport.name(), upgrade.transport.name());
+    }
+    *ctx.transport.lock().unwrap() = upgrade.transport.name();
+    *transport = metrics::instrument(upgrade.transport, ctx.metrics.as_ref());
+    let _ = ctx.lifecycle.transition(SessionState::Open, TransitionReason::UpgradeCompleted);
+    let _ = upgrade.reply.send(Ok(()));
+    None
+}
+
+/// Refuses an upgrade, leaving the session on its current transport.
+fn abort_upgrade(ctx: &Context, upgrade: Upgrade) {
+    debug!(to = upgrade.transport.name(), "upgrade aborted");
+    let _ = ctx.lifecycle.transition(SessionState::Open, TransitionReason::UpgradeAborted);
+    let _ = upgrade.reply.send(Err(SessionError::UpgradeFailed));
+}
+
+/// Waits for a resume request, or forever if the session is not recoverable.
+async fn next_resume(recovery: &mut Option<Recoverable>) -> Resume {
+    match recovery {
+        Some(recovery) => match recovery.resumes.recv().await {
+            Some(resume) => resume,
+            None => std::future::pending().await,
+        },
+        None => std::future::pending().await,
+    }
+}
+
+/// Maps a failed write: an abort closes the session, a transport failure only loses the transport.
+fn lost_or_closed(reason: CloseReason) -> Stop {
+    match reason {
+        CloseReason::TransportError => Stop::Lost(reason),
+        reason => Stop::Closed(reason),
+    }
+}
+
+/// Encrypts an outbound `Message` on an encrypted session. Returns `None` if it cannot be encrypted.
+fn seal(channel: &mut Option<SecureChannel>, packet: EncodedPacket) -> Option<EncodedPacket> {
+    match channel {
+        Some(channel) if packet._type() == &PacketType::Message => match channel.seal(packet.packet()) {
+            Ok(sealed) => Some(sealed.into()),
+            Err(_) => {
+                debug!("dropped message that could not be encrypted");
+                None
+            }
+        },
+        _ => Some(packet),
+    }
+}
+
+/// Closes a transport, giving up if the session is aborted first.
+async fn close(transport: &mut Box<dyn Transport>, buffer: &SendBuffer) {
+    tokio::select! {
+        _ = transport.close() => {}
+        _ = buffer.aborted() => {}
+    }
+}
+
+/// Writes a packet, giving up if the session is aborted first.
+async fn send(transport: &mut Box<dyn Transport>, packet: EncodedPacket, buffer: &SendBuffer) -> Result<(), CloseReason> {
+    tokio::select! {
+        sent = transport.send(packet) => sent.map_err(|_| CloseReason::TransportError),
+        reason = buffer.aborted() => Err(reason),
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt;
+
+#[cfg(feature = "encryption")]
+use crate::encryption::EncryptionError;
+use crate::protocol::{DecodingError, PacketError};
+use crate::session::SessionState;
+use crate::transport::TransportError;
+
+/// Error type for session setup and use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionError {
+    /// Handshake packet was missing or malformed.
+    Handshake(DecodingError),
+    /// Outbound packet is invalid, with underlying packet error.
+    Packet(PacketError),
+    /// Transport failed, with underlying transport error.
+    Transport(TransportError),
+    /// Send buffer is full.
+    BufferFull,
+    /// Operation is not allowed in the session's current state.
+    InvalidState(SessionState),
+    /// Session cannot be resumed: its id is unknown or packets after the offset are gone.
+    NotRecoverable,
+    /// Transport upgrade was refused or its probe failed; the session carries on over its current transport.
+    UpgradeFailed,
+    /// Key agreement failed, with underlying encryption error.
+    #[cfg(feature = "encryption")]
+    Encryption(EncryptionError),
+    /// Session is already closed.
+    Closed,
+}
+
+impl std::error::Error for SessionError {}
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Handshake(e) => write!(f, "Session handshake failed: {}", e),
+            SessionError::Packet(e) => write!(f, "Session packet is invalid: {}", e),
+            SessionError::Transport(e) => write!(f, "Session transport failed: {}", e),
+            SessionError::BufferFull => write!(f, "Session send buffer is full"),
+            SessionError::InvalidState(state) => write!(f, "Session operation is not allowed while {}", state),
+            SessionError::NotRecoverable => write!(f, "Session cannot be recovered"),
+            SessionError::UpgradeFailed => write!(f, "Session transport upgrade failed"),
+            #[cfg(feature = "encryption")]
+            SessionError::Encryption(e) => write!(f, "Session key agreement failed: {}", e),
+            SessionError::Closed => write!(f, "Session is closed"),
+        }
+    }
+}
+
+impl From<TransportError> for SessionError {
+    fn from(e: TransportError) -> Self {
+        SessionError::Transport(e)
+    }
+}
@@ -0,0 +1,184 @@
+use std::time::{Duration, Instant};
+
+use crate::protocol::{Packet, PacketType};
+use crate::rt;
+
+/// What a session does with an inbound packet that exceeds its rate limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitPolicy {
+    /// Stop reading from the transport until the packet fits within the limit.
+    #[default]
+    Delay,
+    /// Silently discard the packet.
+    Drop,
+    /// Discard the packet and answer with an `Error` packet.
+    Error,
+    /// Close the session with `CloseReason::RateLimited`.
+    Disconnect,
+}
+
+/// Per-session limits on inbound traffic, enforced with token buckets.
+/// Every inbound packet counts, heartbeats and `Close` included, except the `Pong` answering each `Ping` this side sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RateLimitConfig {
+    /// Packets per second.
+    packets_per_second: Option<u32>,
+    /// Packet data bytes per second.
+    bytes_per_second: Option<u64>,
+    /// `Message` packets per window.
+    messages_per_window: Option<(u32, Duration)>,
+    /// Behaviour when a limit is exceeded.
+    policy: RateLimitPolicy,
+}
+
+impl RateLimitConfig {
+    /// Returns the packets-per-second limit.
+    pub fn packets_per_second(&self) -> Option<u32> {
+        self.packets_per_second
+    }
+
+    /// Limits packets per second.
+    pub fn with_packets_per_second(mut self, limit: u32) -> Self {
+        self.packets_per_second = Some(limit);
+        self
+    }
+
+    /// Returns the bytes-per-second limit.
+    pub fn bytes_per_second(&self) -> Option<u64> {
+        self.bytes_per_second
+    }
+
+    /// Limits packet data bytes per second.
+    pub fn with_bytes_per_second(mut self, limit: u64) -> Self {
+        self.bytes_per_second = Some(limit);
+        self
+    }
+
+    /// Returns the `Message` limit and its window.
+    pub fn messages_per_window(&self) -> Option<(u32, Duration)> {
+        self.messages_per_window
+    }
+
+    /// Limits `Message` packets per window.
+    pub fn with_messages_per_window(mut self, limit: u32, window: Duration) -> Self {
+        self.messages_per_window = Some((limit, window));
+        self
+    }
+
+    /// Returns the policy applied when a limit is exceeded.
+    pub fn policy(&self) -> RateLimitPolicy {
+        self.policy
+    }
+
+    /// Sets the policy applied when a limit is exceeded.
+    pub fn with_policy(mut self, policy: RateLimitPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+/// A bucket holding up to `capacity` tokens, refilled at `capacity` per `period`.
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    /// Tokens added per second.
+    rate: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: f64, period: Duration, now: Instant) -> Self {
+        Self {
+            capacity,
+            tokens: capacity,
+            rate: capacity / period.as_secs_f64().max(f64::EPSILON),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Returns how long until `amount` tokens are available. Amounts above capacity only need a full bucket.
+    fn wait(&self, amount: f64) -> Duration {
+        let missing = amount.min(self.capacity) - self.tokens;
+        match missing > 1e-9 {
+            true => Duration::from_secs_f64(missing / self.rate),
+            false => Duration::ZERO,
+        }
+    }
+
+    fn take(&mut self, amount: f64) {
+        self.tokens = (self.tokens - amount.min(self.capacity)).max(0.0);
+    }
+}
+
+/// Enforces a `RateLimitConfig` on one session's inbound packets.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    policy: RateLimitPolicy,
+    packets: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+    messages: Option<TokenBucket>,
+    /// Set while a sent `Ping` is unanswered; its `Pong` is admitted without counting.
+    pong_owed: bool,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(config: &RateLimitConfig) -> Self {
+        let now = rt::now();
+        let second = Duration::from_secs(1);
+        Self {
+            policy: config.policy,
+            packets: config.packets_per_second.map(|limit| TokenBucket::new(limit as f64, second, now)),
+            bytes: config.bytes_per_second.map(|limit| TokenBucket::new(limit as f64, second, now)),
+            messages: config.messages_per_window.map(|(limit, window)| TokenBucket::new(limit as f64, window, now)),
+            pong_owed: false,
+        }
+    }
+
+    /// Returns the policy for packets over the limit.
+    pub(crate) fn policy(&self) -> RateLimitPolicy {
+        self.policy
+    }
+
+    /// Records a `Ping` sent to the peer, so that one `Pong` in reply is admitted for free.
+    pub(crate) fn ping_sent(&mut self) {
+        self.pong_owed = true;
+    }
+
+    /// Admits a packet, or returns how long until it would fit. Nothing is taken from the buckets unless it fits.
+    pub(crate) fn check(&mut self, packet: &Packet) -> Result<(), Duration> {
+        if packet._type() == &PacketType::Pong && std::mem::take(&mut self.pong_owed) {
+            return Ok(());
+        }
+        let now = rt::now();
+        let bytes = packet.data().map_or(0, |data| data.len()) as f64;
+        let message = packet._type() == &PacketType::Message;
+
+        let mut wait = Duration::ZERO;
+        for (bucket, amount) in self.buckets(bytes, message) {
+            bucket.refill(now);
+            wait = wait.max(bucket.wait(amount));
+        }
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+        for (bucket, amount) in self.buckets(bytes, message) {
+            bucket.take(amount);
+        }
+        Ok(())
+    }
+
+    fn buckets(&mut self, bytes: f64, message: bool) -> impl Iterator<Item = (&mut TokenBucket, f64)> {
+        let messages = self.messages.as_mut().filter(|_| message);
+        self.packets.as_mut().map(|bucket| (bucket, 1.0))
+            .into_iter()
+            .chain(self.bytes.as_mut().map(|bucket| (bucket, bytes)))
+            .chain(messages.map(|bucket| (bucket, 1.0)))
+    }
+}
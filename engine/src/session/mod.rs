@@ -0,0 +1,510 @@
+mod buffer;
+mod config;
+mod driver;
+mod error;
+mod limit;
+mod recovery;
+mod state;
+mod upgrade;
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use base64::{Engine as _, engine::general_purpose};
+use futures::{SinkExt, StreamExt};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+
+#[cfg(feature = "compression")]
+use crate::compression::CompressionConfig;
+#[cfg(feature = "encryption")]
+use crate::encryption::{EncryptionError, KeyExchange};
+use crate::metrics;
+#[cfg(feature = "compression")]
+use crate::protocol::Compression;
+use crate::protocol::{EncodedPacket, Handshake, Packet, PacketError, PacketType, RawData, Signature, MAX_PACKET_SIZE};
+use crate::rt;
+use crate::transport::Transport;
+use buffer::SendBuffer;
+use driver::{Context, Recoverable, SecureChannel};
+use recovery::ReplayLog;
+use state::Lifecycle;
+use upgrade::Upgrade;
+
+pub(crate) use driver::Role;
+
+pub use buffer::{OverflowPolicy, SendBufferConfig};
+pub use config::SessionConfig;
+pub use error::SessionError;
+pub use limit::{RateLimitConfig, RateLimitPolicy};
+pub use recovery::{Recovery, RecoveryConfig};
+pub use state::{LifecycleEvent, SessionState, TransitionReason};
+
+/// Reason a session was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseReason {
+    /// The peer closed the session or the transport ended.
+    TransportClose,
+    /// The transport failed to read or write.
+    TransportError,
+    /// The peer missed its heartbeat.
+    PingTimeout,
+    /// The session was closed locally.
+    ForcedClose,
+    /// The peer sent a frame that could not be decoded.
+    ParseError,
+    /// The send buffer overflowed under `OverflowPolicy::Disconnect`.
+    BufferOverflow,
+    /// The server shut down.
+    ServerShutdown,
+    /// The peer exceeded its rate limit under `RateLimitPolicy::Disconnect`.
+    RateLimited,
+}
+
+impl fmt::Display for CloseReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CloseReason::TransportClose => write!(f, "transport close"),
+            CloseReason::TransportError => write!(f, "transport error"),
+            CloseReason::PingTimeout => write!(f, "ping timeout"),
+            CloseReason::ForcedClose => write!(f, "forced close"),
+            CloseReason::ParseError => write!(f, "parse error"),
+            CloseReason::BufferOverflow => write!(f, "buffer overflow"),
+            CloseReason::ServerShutdown => write!(f, "server shutting down"),
+            CloseReason::RateLimited => write!(f, "rate limit exceeded"),
+        }
+    }
+}
+
+/// Events delivered to a session's owner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionEvent {
+    /// A `Message` packet was received.
+    Message(RawData),
+    /// A signed `Message` packet was received and its signature verified.
+    Signed(RawData, Signature),
+    /// The send buffer drained to its low water mark after filling up.
+    Drain,
+    /// The session closed. No further events follow.
+    Close(CloseReason),
+}
+
+/// An open engine session over any `Transport`.
+/// Heartbeats run on a background task for as long as the session is open.
+#[derive(Debug)]
+pub struct Session {
+    /// The handshake exchanged when the session opened.
+    handshake: Handshake,
+    /// Name of the underlying transport, updated by the driver when the session moves.
+    transport: Arc<Mutex<&'static str>>,
+    /// Outbound packets for the driver task.
+    buffer: Arc<SendBuffer>,
+    /// Inbound events from the driver task.
+    events: UnboundedReceiver<SessionEvent>,
+    /// Lifecycle state shared with the driver task.
+    lifecycle: Arc<Lifecycle>,
+    /// State transitions, oldest first.
+    transitions: UnboundedReceiver<LifecycleEvent>,
+    /// `Message` packets received so far.
+    received: Arc<AtomicU64>,
+    /// Largest packet data that can be sent; less than `MAX_PACKET_SIZE` on encrypted sessions.
+    max_data: usize,
+    /// Which side of the session this is.
+    role: Role,
+    /// Probed transports for the driver to move onto.
+    upgrades: UnboundedSender<Upgrade>,
+    /// Settings and algorithm outbound messages are compressed with, if any.
+    #[cfg(feature = "compression")]
+    compression: Option<(CompressionConfig, Compression)>,
+    /// Span the driver runs in; events about the session raised outside the driver are recorded in it too.
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl Session {
+    /// Opens a server-side session: sends the `Open` handshake and starts pinging the peer.
+    pub async fn accept<T: Transport>(transport: T, config: &SessionConfig) -> Result<Self, SessionError> {
+        Self::accept_with(transport, config, None).await
+    }
+
+    /// Opens an encrypted server-side session with the client's base64url X25519 public key.
+    /// The server's own key is sent in the handshake.
+    #[cfg(feature = "encryption")]
+    pub async fn accept_encrypted<T: Transport>(transport: T, config: &SessionConfig, client_key: &str) -> Result<Self, SessionError> {
+        let encryption = config.encryption()
+            .cloned()
+            .unwrap_or_default()
+            .with_peer_key(client_key.to_string());
+        Self::accept_with(transport, &config.clone().with_encryption(encryption), None).await
+    }
+
+    /// Opens a server-side session, registered with `recovery` if given so it can be resumed after losing its transport.
+    pub(crate) async fn accept_with<T: Transport>(transport: T, config: &SessionConfig, recovery: Option<&Recovery>) -> Result<Self, SessionError> {
+        let mut transport = metrics::instrument(Box::new(transport), config.metrics());
+        let handshake = config.handshake(generate_sid());
+        let handshake = match recovery {
+            Some(_) => handshake.with_pid(generate_sid()),
+            None => handshake,
+        };
+        #[cfg(feature = "encryption")]
+        let (handshake, channel) = agree_server_keys(handshake, config)?;
+        #[cfg(not(feature = "encryption"))]
+        let channel = None;
+        transport.send(handshake.to_packet().into()).await?;
+        debug!(sid = handshake.sid(), transport = transport.name(), "sent handshake");
+
+        Self::spawn(transport, handshake, Role::Server, config, recovery, channel)
+    }
+
+    /// Opens a client-side session: waits for the `Open` handshake and answers the server's pings.
+    pub async fn connect<T: Transport>(transport: T) -> Result<Self, SessionError> {
+        Self::connect_with(transport, &SessionConfig::default()).await
+    }
+
+    /// Opens a client-side session with local settings; the heartbeat still follows the server's handshake.
+    pub async fn connect_with<T: Transport>(mut transport: T, config: &SessionConfig) -> Result<Self, SessionError> {
+        let handshake = receive_handshake(&mut transport).await?;
+        Self::spawn(Box::new(transport), handshake, Role::Client, config, None, None)
+    }
+
+    /// Opens an encrypted client-side session with the key pair whose public key was sent with the handshake request.
+    /// Fails if the server does not answer with a key of its own.
+    #[cfg(feature = "encryption")]
+    pub async fn connect_encrypted<T: Transport>(mut transport: T, config: &SessionConfig, exchange: KeyExchange) -> Result<Self, SessionError> {
+        let handshake = receive_handshake(&mut transport).await?;
+        let server_key = handshake.key()
+            .ok_or(SessionError::Encryption(EncryptionError::MissingKey))?;
+        let channel = exchange.agree(server_key, Role::Client, &config.encryption().cloned().unwrap_or_default())
+            .map_err(SessionError::Encryption)?;
+        Self::spawn(Box::new(transport), handshake, Role::Client, config, None, Some(channel))
+    }
+
+    /// Opens the session once the handshake is exchanged and starts its driver.
+    fn spawn(
+        transport: Box<dyn Transport>,
+        handshake: Handshake,
+        role: Role,
+        config: &SessionConfig,
+        recovery: Option<&Recovery>,
+        channel: Option<SecureChannel>,
+    ) -> Result<Self, SessionError> {
+        let (lifecycle, transitions) = Lifecycle::channel();
+        lifecycle.transition(SessionState::Open, TransitionReason::Handshake)?;
+        let max_data = match channel {
+            Some(_) => MAX_PACKET_SIZE - SecureChannel::OVERHEAD,
+            None => MAX_PACKET_SIZE,
+        };
+        let buffer = Arc::new(SendBuffer::new(config.send_buffer()));
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+        let received = Arc::new(AtomicU64::new(0));
+        let name = transport.name();
+        let transport_name = Arc::new(Mutex::new(name));
+        let (upgrades_tx, upgrades_rx) = mpsc::unbounded_channel();
+
+        let recovery = recovery.map(|registry| Recoverable {
+            registry: registry.clone(),
+            sid: handshake.sid().to_string(),
+            open: handshake.to_packet().into(),
+            resumes: registry.register(handshake.sid(), handshake.pid().unwrap_or_default()),
+            log: ReplayLog::new(registry.config().max_packets()),
+        });
+        #[cfg(feature = "compression")]
+        let compression = config.compression().and_then(|compression| {
+            let algorithm = match role {
+                // Without a client offer there is nothing to say the client can decompress the server's choice.
+                Role::Server => config.agreed_compression(),
+                Role::Client => handshake.compression().iter()
+                    .copied()
+                    .find(|&algorithm| compression.accepts(algorithm, handshake.dictionary())),
+            };
+            Some((compression.clone(), algorithm?))
+        });
+        let ctx = Context {
+            role,
+            interval: Duration::from_millis(handshake.ping_interval()),
+            timeout: Duration::from_millis(handshake.ping_timeout()),
+            buffer: buffer.clone(),
+            lifecycle: lifecycle.clone(),
+            events: events_tx,
+            received: received.clone(),
+            transport: transport_name.clone(),
+            rate_limit: config.rate_limit(),
+            metrics: config.metrics().cloned(),
+            #[cfg(feature = "compression")]
+            compression: config.compression().cloned(),
+            #[cfg(feature = "signing")]
+            signatures: config.signatures().cloned(),
+        };
+        if let Some(metrics) = &ctx.metrics {
+            metrics.session_opened(name);
+        }
+        let driver = driver::run(transport, ctx, recovery, upgrades_rx, channel);
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!("session", sid = handshake.sid(), transport = name);
+        #[cfg(feature = "tracing")]
+        let driver = tracing::Instrument::instrument(driver, span.clone());
+        rt::spawn(driver);
+
+        Ok(Self {
+            handshake,
+            transport: transport_name,
+            buffer,
+            events: events_rx,
+            lifecycle,
+            transitions,
+            received,
+            max_data,
+            role,
+            upgrades: upgrades_tx,
+            #[cfg(feature = "compression")]
+            compression,
+            #[cfg(feature = "tracing")]
+            span,
+        })
+    }
+
+    /// Returns the session id.
+    pub fn sid(&self) -> &str {
+        self.handshake.sid()
+    }
+
+    /// Returns the handshake exchanged when the session opened.
+    pub fn handshake(&self) -> &Handshake {
+        &self.handshake
+    }
+
+    /// Returns the name of the underlying transport.
+    pub fn transport(&self) -> &'static str {
+        *self.transport.lock().unwrap()
+    }
+
+    /// Returns the number of `Message` packets received, which a client presents as its offset when resuming.
+    pub fn offset(&self) -> u64 {
+        self.received.load(Ordering::Acquire)
+    }
+
+    /// Returns the current lifecycle state.
+    pub fn state(&self) -> SessionState {
+        self.lifecycle.state()
+    }
+
+    /// Queues a `Message` packet carrying the given data, waiting while the send buffer is full.
+    pub async fn send(&self, data: RawData) -> Result<(), SessionError> {
+        self.send_packet(message(data)?).await
+    }
+
+    /// Queues a `Message` packet carrying the given data, applying the overflow policy if the send buffer is full.
+    pub fn try_send(&self, data: RawData) -> Result<(), SessionError> {
+        self.try_send_packet(message(data)?)
+    }
+
+    /// Queues an arbitrary packet, waiting while the send buffer is full.
+    pub async fn send_packet(&self, packet: Packet) -> Result<(), SessionError> {
+        self.send_encoded(self.compress(packet).into()).await
+    }
+
+    /// Queues an arbitrary packet, applying the overflow policy if the send buffer is full.
+    pub fn try_send_packet(&self, packet: Packet) -> Result<(), SessionError> {
+        self.try_send_encoded(self.compress(packet).into())
+    }
+
+    /// Queues a pre-encoded packet, waiting while the send buffer is full.
+    /// The packet is sent as encoded, without compression.
+    /// Clones of one `EncodedPacket` sent to many sessions share a single encoding.
+    pub async fn send_encoded(&self, packet: EncodedPacket) -> Result<(), SessionError> {
+        check_send(&self.lifecycle, &packet, self.max_data)?;
+        self.buffer.push(packet).await
+    }
+
+    /// Queues a pre-encoded packet, applying the overflow policy if the send buffer is full.
+    pub fn try_send_encoded(&self, packet: EncodedPacket) -> Result<(), SessionError> {
+        check_send(&self.lifecycle, &packet, self.max_data)?;
+        self.buffer.try_push(packet)
+    }
+
+    /// Compresses a `Message` packet with the session's algorithm, if any.
+    fn compress(&self, packet: Packet) -> Packet {
+        #[cfg(feature = "compression")]
+        if let Some((compression, algorithm)) = &self.compression {
+            if packet._type() == &PacketType::Message {
+                return compression.compress(*algorithm, packet);
+            }
+        }
+        packet
+    }
+
+    /// Returns the number of packets waiting in the send buffer.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Waits for the next session event. Returns `None` once the close event has been delivered.
+    pub async fn recv(&mut self) -> Option<SessionEvent> {
+        self.events.recv().await
+    }
+
+    /// Waits for the next lifecycle transition. Returns `None` once the `Closed` transition has been delivered.
+    pub async fn recv_lifecycle(&mut self) -> Option<LifecycleEvent> {
+        self.transitions.recv().await
+    }
+
+    /// Moves the session onto `transport`, which must be one of the transports listed in `Handshake.upgrades`.
+    /// A client calls this with a transport it has just opened to the server, and the server with the transport it
+    /// accepted; both sides probe it before the session moves. On failure the session stays on its current transport.
+    pub async fn upgrade<T: Transport>(&self, transport: T) -> Result<(), SessionError> {
+        self.handle().upgrade(transport).await
+    }
+
+    /// Flushes the send buffer, then sends a `Close` packet and shuts the session down.
+    pub fn close(&self) {
+        self.handle().close(CloseReason::ForcedClose);
+    }
+
+    /// Returns a handle that can close the session from elsewhere.
+    pub(crate) fn handle(&self) -> SessionHandle {
+        SessionHandle {
+            buffer: self.buffer.clone(),
+            lifecycle: self.lifecycle.clone(),
+            max_data: self.max_data,
+            role: self.role,
+            allowed_upgrades: self.handshake.upgrades().into(),
+            probe_timeout: Duration::from_millis(self.handshake.ping_timeout()),
+            upgrades: self.upgrades.clone(),
+            #[cfg(feature = "tracing")]
+            span: self.span.clone(),
+        }
+    }
+}
+
+/// Closes or writes to a session on behalf of whoever owns the `Session`, such as a server shutting down or broadcasting.
+#[derive(Debug, Clone)]
+pub(crate) struct SessionHandle {
+    buffer: Arc<SendBuffer>,
+    lifecycle: Arc<Lifecycle>,
+    max_data: usize,
+    role: Role,
+    /// Transports the handshake allows upgrading to.
+    allowed_upgrades: Arc<[String]>,
+    /// Time the peer has to finish an upgrade probe.
+    probe_timeout: Duration,
+    upgrades: UnboundedSender<Upgrade>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl SessionHandle {
+    /// Flushes the send buffer, then sends a `Close` packet and shuts the session down.
+    pub(crate) fn close(&self, reason: CloseReason) {
+        debug!(parent: &self.span, %reason, "close requested");
+        let _ = self.lifecycle.transition(SessionState::Closing, TransitionReason::CloseRequested);
+        self.buffer.close(reason);
+    }
+
+    /// Shuts the session down at once, abandoning queued packets.
+    pub(crate) fn abort(&self, reason: CloseReason) {
+        debug!(parent: &self.span, %reason, "abort requested");
+        let _ = self.lifecycle.transition(SessionState::Closing, TransitionReason::CloseRequested);
+        self.buffer.abort(reason);
+    }
+
+    /// Queues a pre-encoded packet, applying the overflow policy if the send buffer is full.
+    pub(crate) fn try_send_encoded(&self, packet: EncodedPacket) -> Result<(), SessionError> {
+        check_send(&self.lifecycle, &packet, self.max_data)?;
+        self.buffer.try_push(packet)
+    }
+
+    /// Probes `transport`, then has the driver move the session onto it.
+    pub(crate) async fn upgrade<T: Transport>(&self, transport: T) -> Result<(), SessionError> {
+        if !self.allowed_upgrades.iter().any(|name| name == transport.name()) {
+            debug!(parent: &self.span, to = transport.name(), "refused upgrade to a transport the handshake does not list");
+            return Err(SessionError::UpgradeFailed);
+        }
+        self.lifecycle.transition(SessionState::Upgrading, TransitionReason::UpgradeStarted)?;
+        let mut transport: Box<dyn Transport> = Box::new(transport);
+        if let Err(e) = upgrade::probe(&mut transport, self.role, self.probe_timeout).await {
+            debug!(parent: &self.span, to = transport.name(), error = %e, "upgrade probe failed");
+            let _ = self.lifecycle.transition(SessionState::Open, TransitionReason::UpgradeAborted);
+            return Err(e);
+        }
+        let (reply, result) = oneshot::channel();
+        self.upgrades.send(Upgrade { transport, reply })
+            .map_err(|_| SessionError::Closed)?;
+        result.await.unwrap_or(Err(SessionError::Closed))
+    }
+
+    /// Returns whether the session has closed.
+    pub(crate) fn is_closed(&self) -> bool {
+        self.lifecycle.state() == SessionState::Closed
+    }
+
+    /// Waits until the session is closed.
+    pub(crate) async fn closed(&self) {
+        self.lifecycle.closed().await
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Waits for the `Open` packet and reads the handshake from it.
+async fn receive_handshake<T: Transport>(transport: &mut T) -> Result<Handshake, SessionError> {
+    let packet = match transport.next().await {
+        Some(packet) => packet?,
+        None => return Err(SessionError::Closed),
+    };
+    let handshake = Handshake::from_packet(&packet)
+        .map_err(SessionError::Handshake)?;
+    debug!(sid = handshake.sid(), transport = transport.name(), "received handshake");
+    Ok(handshake)
+}
+
+/// Agrees on keys with a client that sent its public key, adding the server's key to the handshake.
+/// Refuses clients without a key when encryption is required.
+#[cfg(feature = "encryption")]
+fn agree_server_keys(handshake: Handshake, config: &SessionConfig) -> Result<(Handshake, Option<SecureChannel>), SessionError> {
+    let Some(encryption) = config.encryption() else {
+        return Ok((handshake, None));
+    };
+    let Some(client_key) = encryption.peer_key() else {
+        return match encryption.required() {
+            true => Err(SessionError::Encryption(EncryptionError::MissingKey)),
+            false => Ok((handshake, None)),
+        };
+    };
+    let exchange = KeyExchange::new()
+        .map_err(SessionError::Encryption)?;
+    let server_key = exchange.public_key();
+    let channel = exchange.agree(client_key, Role::Server, encryption)
+        .map_err(SessionError::Encryption)?;
+    Ok((handshake.with_key(server_key), Some(channel)))
+}
+
+/// Rejects packets the current state does not allow.
+fn check_send(lifecycle: &Lifecycle, packet: &EncodedPacket, max_data: usize) -> Result<(), SessionError> {
+    match lifecycle.state() {
+        SessionState::Closing | SessionState::Closed => Err(SessionError::Closed),
+        state if !state.can_send(packet._type()) => Err(SessionError::InvalidState(state)),
+        _ if packet.packet().data().map_or(0, RawData::len) > max_data => Err(SessionError::Packet(PacketError::DataTooLarge)),
+        _ => Ok(()),
+    }
+}
+
+fn message(data: RawData) -> Result<Packet, SessionError> {
+    let mut packet = Packet::new(PacketType::Message);
+    packet.with_data(data)
+        .map_err(SessionError::Packet)?;
+    Ok(packet)
+}
+
+/// Generates a random, URL-safe, 20 character session id.
+pub(crate) fn generate_sid() -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(rand::random::<[u8; 15]>())
+}
@@ -0,0 +1,187 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::protocol::EncodedPacket;
+use crate::session::{Session, SessionConfig, SessionError};
+use crate::transport::Transport;
+
+/// Connection state recovery settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecoveryConfig {
+    /// How long a disconnected session waits for its client to resume.
+    window: Duration,
+    /// Unacknowledged `Message` packets kept for replay.
+    max_packets: usize,
+}
+
+impl Default for RecoveryConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(120),
+            max_packets: 1024,
+        }
+    }
+}
+
+impl RecoveryConfig {
+    /// Returns the recovery window.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Sets the recovery window.
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Returns the maximum number of packets kept for replay.
+    pub fn max_packets(&self) -> usize {
+        self.max_packets
+    }
+
+    /// Sets the maximum number of packets kept for replay.
+    pub fn with_max_packets(mut self, max_packets: usize) -> Self {
+        self.max_packets = max_packets;
+        self
+    }
+}
+
+/// A new transport for a session, and the offset of the last `Message` its client received.
+pub(crate) struct Resume {
+    pub(crate) transport: Box<dyn Transport>,
+    pub(crate) offset: u64,
+    pub(crate) reply: oneshot::Sender<Result<(), SessionError>>,
+}
+
+/// A recoverable session's private id, and where to send its resumes.
+#[derive(Debug)]
+struct Parked {
+    pid: String,
+    resumes: mpsc::UnboundedSender<Resume>,
+}
+
+/// Server-side registry of recoverable sessions.
+/// A session accepted through it survives transport loss for the recovery window and can be resumed
+/// by its session id together with the private id from its handshake.
+#[derive(Debug, Clone)]
+pub struct Recovery {
+    config: RecoveryConfig,
+    sessions: Arc<Mutex<HashMap<String, Parked>>>,
+}
+
+impl Recovery {
+    /// Creates an empty registry.
+    pub fn new(config: RecoveryConfig) -> Self {
+        Self {
+            config,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the recovery settings.
+    pub fn config(&self) -> &RecoveryConfig {
+        &self.config
+    }
+
+    /// Returns the number of sessions that can still be resumed.
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    /// Returns whether no sessions can be resumed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Opens a recoverable server-side session.
+    pub async fn accept<T: Transport>(&self, transport: T, config: &SessionConfig) -> Result<Session, SessionError> {
+        Session::accept_with(transport, config, Some(self)).await
+    }
+
+    /// Moves a session onto a new transport, if `pid` is the private id from its handshake.
+    /// The session resends its `Open` handshake, then replays every `Message` after `offset` before carrying on.
+    pub async fn resume<T: Transport>(&self, transport: T, sid: &str, pid: &str, offset: u64) -> Result<(), SessionError> {
+        let resumes = self.sessions.lock().unwrap()
+            .get(sid)
+            .filter(|parked| parked.pid == pid)
+            .map(|parked| parked.resumes.clone())
+            .ok_or(SessionError::NotRecoverable)?;
+        let (reply, result) = oneshot::channel();
+        let resume = Resume {
+            transport: Box::new(transport),
+            offset,
+            reply,
+        };
+        resumes.send(resume).map_err(|_| SessionError::NotRecoverable)?;
+        result.await.unwrap_or(Err(SessionError::NotRecoverable))
+    }
+
+    pub(crate) fn register(&self, sid: &str, pid: &str) -> mpsc::UnboundedReceiver<Resume> {
+        let (resumes, resumes_rx) = mpsc::unbounded_channel();
+        let parked = Parked { pid: pid.to_string(), resumes };
+        self.sessions.lock().unwrap().insert(sid.to_string(), parked);
+        resumes_rx
+    }
+
+    pub(crate) fn unregister(&self, sid: &str) {
+        self.sessions.lock().unwrap().remove(sid);
+    }
+}
+
+/// Sent `Message` packets the client has not acknowledged, by offset.
+#[derive(Debug)]
+pub(crate) struct ReplayLog {
+    packets: VecDeque<(u64, EncodedPacket)>,
+    /// Offset given to the next packet; offsets start at 1.
+    next: u64,
+    max_packets: usize,
+}
+
+impl ReplayLog {
+    pub(crate) fn new(max_packets: usize) -> Self {
+        Self {
+            packets: VecDeque::new(),
+            next: 1,
+            max_packets,
+        }
+    }
+
+    /// Records a packet, dropping the oldest if the log is full.
+    pub(crate) fn push(&mut self, packet: EncodedPacket) {
+        if self.max_packets == 0 {
+            self.next += 1;
+            return;
+        }
+        if self.packets.len() == self.max_packets {
+            self.packets.pop_front();
+        }
+        self.packets.push_back((self.next, packet));
+        self.next += 1;
+    }
+
+    /// Forgets packets up to and including `offset`.
+    pub(crate) fn acknowledge(&mut self, offset: u64) {
+        while self.packets.front().is_some_and(|(n, _)| *n <= offset) {
+            self.packets.pop_front();
+        }
+    }
+
+    /// Returns the packets after `offset`, or `None` if some of them were already dropped.
+    pub(crate) fn replay(&self, offset: u64) -> Option<Vec<EncodedPacket>> {
+        if offset >= self.next {
+            return None;
+        }
+        let oldest = self.packets.front().map_or(self.next, |(n, _)| *n);
+        if offset + 1 < oldest {
+            return None;
+        }
+        Some(self.packets.iter()
+            .filter(|(n, _)| *n > offset)
+            .map(|(_, packet)| packet.clone())
+            .collect())
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::watch;
+
+use crate::protocol::PacketType;
+use crate::session::{CloseReason, SessionError};
+
+/// Lifecycle state of a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    /// Handshake not yet completed.
+    Opening,
+    /// Handshake completed; packets flow freely.
+    Open,
+    /// Moving to another transport.
+    Upgrading,
+    /// Close requested; queued packets are being flushed.
+    Closing,
+    /// Session is closed.
+    Closed,
+}
+
+impl fmt::Display for SessionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionState::Opening => write!(f, "opening"),
+            SessionState::Open => write!(f, "open"),
+            SessionState::Upgrading => write!(f, "upgrading"),
+            SessionState::Closing => write!(f, "closing"),
+            SessionState::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+impl SessionState {
+    /// Returns whether the state machine allows moving to `to`.
+    pub fn can_transition(&self, to: SessionState) -> bool {
+        use SessionState::*;
+        matches!(
+            (self, to),
+            (Opening, Open) | (Opening, Closed)
+                | (Open, Upgrading) | (Open, Closing) | (Open, Closed)
+                | (Upgrading, Open) | (Upgrading, Closing) | (Upgrading, Closed)
+                | (Closing, Closed)
+        )
+    }
+
+    /// Returns whether a packet of the given type may be sent in this state.
+    pub fn can_send(&self, packet_type: &PacketType) -> bool {
+        match self {
+            SessionState::Opening => packet_type == &PacketType::Open,
+            SessionState::Open | SessionState::Upgrading => packet_type != &PacketType::Open,
+            SessionState::Closing => packet_type == &PacketType::Close,
+            SessionState::Closed => false,
+        }
+    }
+
+    /// Returns whether a packet of the given type may be received in this state.
+    pub fn can_receive(&self, packet_type: &PacketType) -> bool {
+        match self {
+            SessionState::Opening => packet_type == &PacketType::Open,
+            SessionState::Open | SessionState::Upgrading | SessionState::Closing => packet_type != &PacketType::Open,
+            SessionState::Closed => false,
+        }
+    }
+}
+
+/// Why a session changed state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionReason {
+    /// The handshake completed.
+    Handshake,
+    /// A transport upgrade started.
+    UpgradeStarted,
+    /// A transport upgrade completed.
+    UpgradeCompleted,
+    /// A transport upgrade was abandoned.
+    UpgradeAborted,
+    /// The owner asked to close the session.
+    CloseRequested,
+    /// The session closed.
+    Closed(CloseReason),
+}
+
+/// A session state transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LifecycleEvent {
+    /// State before the transition.
+    from: SessionState,
+    /// State after the transition.
+    to: SessionState,
+    /// Why the transition happened.
+    reason: TransitionReason,
+}
+
+impl LifecycleEvent {
+    /// Creates a lifecycle event.
+    pub fn new(from: SessionState, to: SessionState, reason: TransitionReason) -> Self {
+        Self { from, to, reason }
+    }
+
+    /// Returns the state before the transition.
+    pub fn from(&self) -> SessionState {
+        self.from
+    }
+
+    /// Returns the state after the transition.
+    pub fn to(&self) -> SessionState {
+        self.to
+    }
+
+    /// Returns why the transition happened.
+    pub fn reason(&self) -> TransitionReason {
+        self.reason
+    }
+}
+
+/// Session state shared by a `Session` handle and its driver.
+#[derive(Debug)]
+pub(crate) struct Lifecycle {
+    state: watch::Sender<SessionState>,
+    /// Dropped on the `Closed` transition so the receiver ends.
+    events: Mutex<Option<UnboundedSender<LifecycleEvent>>>,
+}
+
+impl Lifecycle {
+    /// Creates a lifecycle in the `Opening` state and the receiver for its transitions.
+    pub(crate) fn channel() -> (Arc<Self>, UnboundedReceiver<LifecycleEvent>) {
+        let (events, transitions) = mpsc::unbounded_channel();
+        let lifecycle = Self {
+            state: watch::Sender::new(SessionState::Opening),
+            events: Mutex::new(Some(events)),
+        };
+        (Arc::new(lifecycle), transitions)
+    }
+
+    /// Returns the current state.
+    pub(crate) fn state(&self) -> SessionState {
+        *self.state.borrow()
+    }
+
+    /// Moves to `to`, raising a lifecycle event, or rejects an illegal transition.
+    pub(crate) fn transition(&self, to: SessionState, reason: TransitionReason) -> Result<LifecycleEvent, SessionError> {
+        let mut result = Err(SessionError::Closed);
+        self.state.send_if_modified(|state| {
+            if !state.can_transition(to) {
+                result = Err(SessionError::InvalidState(*state));
+                return false;
+            }
+            let event = LifecycleEvent::new(*state, to, reason);
+            trace!(from = %event.from(), to = %event.to(), reason = ?event.reason(), "session state changed");
+            *state = to;
+
+            let mut events = self.events.lock().unwrap();
+            if let Some(events) = events.as_ref() {
+                let _ = events.send(event);
+            }
+            if to == SessionState::Closed {
+                *events = None;
+            }
+            result = Ok(event);
+            true
+        });
+        result
+    }
+
+    /// Waits until the session reaches `Closed`.
+    pub(crate) async fn closed(&self) {
+        let mut state = self.state.subscribe();
+        let _ = state.wait_for(|state| *state == SessionState::Closed).await;
+    }
+}
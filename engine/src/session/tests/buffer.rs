@@ -0,0 +1,205 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+
+use crate::protocol::{EncodedPacket, Packet, PacketType, RawData};
+use crate::session::buffer::{Outbound, SendBuffer};
+use crate::session::{
+    CloseReason, OverflowPolicy, SendBufferConfig, Session, SessionConfig, SessionError, SessionEvent,
+};
+use crate::transport::memory::MemoryTransport;
+
+fn numbered(n: usize) -> EncodedPacket {
+    let mut packet = Packet::new(PacketType::Message);
+    packet.with_data(RawData::Text(n.to_string())).unwrap();
+    packet.into()
+}
+
+fn sized(len: usize) -> EncodedPacket {
+    let mut packet = Packet::new(PacketType::Message);
+    packet.with_data(RawData::Binary(vec![len as u8; len])).unwrap();
+    packet.into()
+}
+
+fn buffer(high: usize, low: usize, policy: OverflowPolicy) -> SendBuffer {
+    SendBuffer::new(SendBufferConfig::new(high, low, policy))
+}
+
+async fn pop_packet(buffer: &SendBuffer) -> (EncodedPacket, bool) {
+    match buffer.pop().await {
+        Outbound::Packet { packet, drained } => (packet, drained),
+        other => panic!("Expected packet, got {:?}", other),
+    }
+}
+
+#[test]
+fn config_caps_low_water_mark() {
+    let config = SendBufferConfig::new(4, 10, OverflowPolicy::DropOldest);
+    assert_eq!(config.high_water_mark(), 4);
+    assert_eq!(config.low_water_mark(), 4);
+    assert_eq!(config.policy(), OverflowPolicy::DropOldest);
+    assert_eq!(SendBufferConfig::default().policy(), OverflowPolicy::Error);
+}
+
+#[test]
+fn config_caps_low_water_bytes() {
+    let config = SendBufferConfig::default().with_byte_water_marks(100, 500);
+    assert_eq!(config.high_water_bytes(), 100);
+    assert_eq!(config.low_water_bytes(), 100);
+    assert_eq!(config.high_water_mark(), SendBufferConfig::default().high_water_mark());
+}
+
+#[tokio::test]
+async fn byte_water_marks_bound_large_payloads() {
+    let config = SendBufferConfig::new(100, 50, OverflowPolicy::Error).with_byte_water_marks(1000, 200);
+    let buffer = SendBuffer::new(config);
+    buffer.try_push(sized(600)).unwrap();
+    buffer.try_push(sized(300)).unwrap();
+    buffer.try_push(sized(150)).unwrap();
+    assert_eq!(buffer.try_push(sized(1)), Err(SessionError::BufferFull));
+
+    // Two packets are well under the packet low water mark, but 450 bytes are still queued.
+    assert!(!pop_packet(&buffer).await.1);
+    let (_, drained) = pop_packet(&buffer).await;
+    assert!(drained);
+    buffer.try_push(sized(1)).unwrap();
+}
+
+#[tokio::test]
+async fn drop_oldest_evicts_until_bytes_fit() {
+    let config = SendBufferConfig::new(100, 0, OverflowPolicy::DropOldest).with_byte_water_marks(100, 0);
+    let buffer = SendBuffer::new(config);
+    for len in [40, 30, 30] {
+        buffer.try_push(sized(len)).unwrap();
+    }
+    buffer.try_push(sized(60)).unwrap();
+    assert_eq!(pop_packet(&buffer).await.0, sized(30));
+    assert_eq!(pop_packet(&buffer).await.0, sized(60));
+    assert_eq!(buffer.len(), 0);
+}
+
+#[test]
+fn try_push_errors_when_full() {
+    let buffer = buffer(2, 0, OverflowPolicy::Error);
+    buffer.try_push(numbered(1)).unwrap();
+    buffer.try_push(numbered(2)).unwrap();
+    assert_eq!(buffer.try_push(numbered(3)), Err(SessionError::BufferFull));
+    assert_eq!(buffer.len(), 2);
+}
+
+#[tokio::test]
+async fn drop_oldest_evicts_head() {
+    let buffer = buffer(2, 0, OverflowPolicy::DropOldest);
+    for n in 1..=3 {
+        buffer.try_push(numbered(n)).unwrap();
+    }
+    assert_eq!(pop_packet(&buffer).await.0, numbered(2));
+    assert_eq!(pop_packet(&buffer).await.0, numbered(3));
+}
+
+#[tokio::test]
+async fn drop_newest_keeps_queue() {
+    let buffer = buffer(2, 0, OverflowPolicy::DropNewest);
+    for n in 1..=3 {
+        buffer.try_push(numbered(n)).unwrap();
+    }
+    assert_eq!(buffer.len(), 2);
+    assert_eq!(pop_packet(&buffer).await.0, numbered(1));
+    assert_eq!(pop_packet(&buffer).await.0, numbered(2));
+}
+
+#[tokio::test]
+async fn disconnect_policy_signals_overflow() {
+    let buffer = buffer(1, 0, OverflowPolicy::Disconnect);
+    buffer.try_push(numbered(1)).unwrap();
+    assert_eq!(buffer.try_push(numbered(2)), Err(SessionError::BufferFull));
+    assert!(matches!(buffer.pop().await, Outbound::Abort(CloseReason::BufferOverflow)));
+}
+
+#[tokio::test]
+async fn drained_at_low_water_mark() {
+    let buffer = buffer(3, 1, OverflowPolicy::Error);
+    for n in 1..=3 {
+        buffer.try_push(numbered(n)).unwrap();
+    }
+    assert_eq!(buffer.try_push(numbered(4)), Err(SessionError::BufferFull));
+    assert!(!pop_packet(&buffer).await.1);
+    // Still full until the low water mark is reached.
+    assert_eq!(buffer.try_push(numbered(4)), Err(SessionError::BufferFull));
+    assert!(pop_packet(&buffer).await.1);
+    buffer.try_push(numbered(4)).unwrap();
+}
+
+#[tokio::test]
+async fn push_waits_for_drain() {
+    let buffer = std::sync::Arc::new(buffer(1, 0, OverflowPolicy::Error));
+    buffer.push(numbered(1)).await.unwrap();
+
+    let waiting = tokio::spawn({
+        let buffer = buffer.clone();
+        async move { buffer.push(numbered(2)).await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(!waiting.is_finished());
+
+    assert_eq!(pop_packet(&buffer).await, (numbered(1), true));
+    waiting.await.unwrap().unwrap();
+    assert_eq!(pop_packet(&buffer).await.0, numbered(2));
+}
+
+#[tokio::test]
+async fn close_flushes_before_closing() {
+    let buffer = buffer(4, 0, OverflowPolicy::Error);
+    buffer.try_push(numbered(1)).unwrap();
+    buffer.close(CloseReason::ForcedClose);
+    assert_eq!(buffer.try_push(numbered(2)), Err(SessionError::Closed));
+    assert_eq!(pop_packet(&buffer).await.0, numbered(1));
+    assert!(matches!(buffer.pop().await, Outbound::Close(CloseReason::ForcedClose)));
+}
+
+#[tokio::test]
+async fn abort_skips_queued_packets() {
+    let buffer = buffer(4, 0, OverflowPolicy::Error);
+    buffer.try_push(numbered(1)).unwrap();
+    buffer.close(CloseReason::ServerShutdown);
+    buffer.abort(CloseReason::ServerShutdown);
+    assert!(matches!(buffer.pop().await, Outbound::Abort(CloseReason::ServerShutdown)));
+    assert_eq!(buffer.aborted().await, CloseReason::ServerShutdown);
+}
+
+#[tokio::test]
+async fn slow_consumer_fills_buffer_then_drains() {
+    let (server, mut client) = MemoryTransport::pair_with(1, true);
+    let config = SessionConfig::default()
+        .with_send_buffer(SendBufferConfig::new(4, 1, OverflowPolicy::Error));
+    let mut server = Session::accept(server, &config).await.unwrap();
+
+    let mut sent = 0;
+    while server.try_send(RawData::Text(sent.to_string())).is_ok() {
+        sent += 1;
+        assert!(sent < 100, "send buffer never filled");
+    }
+    assert_eq!(server.try_send(RawData::Text("x".into())), Err(SessionError::BufferFull));
+
+    tokio::spawn(async move { while client.next().await.is_some() {} });
+    assert_eq!(server.recv().await, Some(SessionEvent::Drain));
+    server.send(RawData::Text("after drain".into())).await.unwrap();
+}
+
+#[tokio::test]
+async fn slow_consumer_disconnected() {
+    let (server, _client) = MemoryTransport::pair_with(1, true);
+    let config = SessionConfig::default()
+        .with_send_buffer(SendBufferConfig::new(2, 0, OverflowPolicy::Disconnect));
+    let mut server = Session::accept(server, &config).await.unwrap();
+
+    // Let the driver block writing to the client, which never reads.
+    let mut sent = 0;
+    while server.try_send(RawData::Text(sent.to_string())).is_ok() {
+        sent += 1;
+        assert!(sent < 100, "send buffer never filled");
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert_eq!(server.recv().await, Some(SessionEvent::Close(CloseReason::BufferOverflow)));
+    assert_eq!(server.try_send(RawData::Text("x".into())), Err(SessionError::Closed));
+}
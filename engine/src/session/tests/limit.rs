@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+
+use crate::protocol::{Packet, PacketType, RawData};
+use crate::protocol::tests::text_message;
+use crate::session::limit::RateLimiter;
+use crate::session::{CloseReason, RateLimitConfig, RateLimitPolicy, Session, SessionConfig, SessionEvent};
+use crate::transport::memory::MemoryTransport;
+
+/// Accepts a rate-limited session over a fresh memory transport, returning the raw client side.
+async fn open(rate_limit: RateLimitConfig) -> (Session, MemoryTransport) {
+    let (server, mut client) = MemoryTransport::pair();
+    let config = SessionConfig::default().with_rate_limit(rate_limit);
+    let session = Session::accept(server, &config).await.unwrap();
+    assert_eq!(client.next().await.unwrap().unwrap()._type(), &PacketType::Open);
+    (session, client)
+}
+
+// Exact waits need the paused tokio clock.
+#[cfg(feature = "tokio")]
+#[tokio::test(start_paused = true)]
+async fn limiter_refills_over_time() {
+    let mut limiter = RateLimiter::new(&RateLimitConfig::default().with_packets_per_second(2));
+    assert!(limiter.check(&text_message("a")).is_ok());
+    assert!(limiter.check(&text_message("b")).is_ok());
+    assert_eq!(limiter.check(&text_message("c")), Err(Duration::from_millis(500)));
+
+    tokio::time::advance(Duration::from_millis(500)).await;
+    assert!(limiter.check(&text_message("c")).is_ok());
+}
+
+#[tokio::test(start_paused = true)]
+async fn limiter_exempts_one_pong_per_ping() {
+    let mut limiter = RateLimiter::new(&RateLimitConfig::default().with_packets_per_second(1));
+    assert!(limiter.check(&text_message("a")).is_ok());
+    for _type in [PacketType::Ping, PacketType::Pong, PacketType::Close, PacketType::Noop] {
+        assert!(limiter.check(&Packet::new(_type)).is_err());
+    }
+
+    limiter.ping_sent();
+    assert!(limiter.check(&Packet::new(PacketType::Pong)).is_ok());
+    assert!(limiter.check(&Packet::new(PacketType::Pong)).is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn limiter_takes_nothing_from_a_rejected_packet() {
+    let config = RateLimitConfig::default()
+        .with_packets_per_second(10)
+        .with_bytes_per_second(4);
+    let mut limiter = RateLimiter::new(&config);
+    assert!(limiter.check(&text_message("abc")).is_ok());
+    assert!(limiter.check(&text_message("abc")).is_err());
+    // The rejected packet did not use up a packet token, so a small one still fits.
+    assert!(limiter.check(&text_message("d")).is_ok());
+}
+
+// Exact waits need the paused tokio clock.
+#[cfg(feature = "tokio")]
+#[tokio::test(start_paused = true)]
+async fn limiter_counts_messages_per_window() {
+    let config = RateLimitConfig::default().with_messages_per_window(1, Duration::from_secs(60));
+    let mut limiter = RateLimiter::new(&config);
+    assert!(limiter.check(&text_message("a")).is_ok());
+    assert!(limiter.check(&Packet::new(PacketType::Noop)).is_ok());
+    assert_eq!(limiter.check(&text_message("b")), Err(Duration::from_secs(60)));
+}
+
+#[tokio::test]
+async fn delay_policy_holds_packets_back() {
+    let config = RateLimitConfig::default().with_messages_per_window(1, Duration::from_millis(100));
+    let (mut session, mut client) = open(config).await;
+
+    let started = tokio::time::Instant::now();
+    client.send(text_message("one").into()).await.unwrap();
+    client.send(text_message("two").into()).await.unwrap();
+    assert_eq!(session.recv().await, Some(SessionEvent::Message(RawData::Text("one".into()))));
+    assert_eq!(session.recv().await, Some(SessionEvent::Message(RawData::Text("two".into()))));
+    assert!(started.elapsed() >= Duration::from_millis(90));
+}
+
+#[tokio::test]
+async fn drop_policy_discards_packets() {
+    let config = RateLimitConfig::default()
+        .with_messages_per_window(1, Duration::from_secs(60))
+        .with_policy(RateLimitPolicy::Drop);
+    let (mut session, mut client) = open(config).await;
+
+    client.send(text_message("one").into()).await.unwrap();
+    client.send(text_message("two").into()).await.unwrap();
+    assert_eq!(session.recv().await, Some(SessionEvent::Message(RawData::Text("one".into()))));
+    assert!(tokio::time::timeout(Duration::from_millis(50), session.recv()).await.is_err());
+    assert_eq!(session.offset(), 1);
+}
+
+#[tokio::test]
+async fn error_policy_answers_with_error_packet() {
+    let config = RateLimitConfig::default()
+        .with_messages_per_window(1, Duration::from_secs(60))
+        .with_policy(RateLimitPolicy::Error);
+    let (mut session, mut client) = open(config).await;
+
+    client.send(text_message("one").into()).await.unwrap();
+    client.send(text_message("two").into()).await.unwrap();
+    assert_eq!(session.recv().await, Some(SessionEvent::Message(RawData::Text("one".into()))));
+    assert_eq!(client.next().await.unwrap().unwrap(), Packet::error("rate limit exceeded"));
+}
+
+#[tokio::test]
+async fn disconnect_policy_closes_session() {
+    let config = RateLimitConfig::default()
+        .with_messages_per_window(1, Duration::from_secs(60))
+        .with_policy(RateLimitPolicy::Disconnect);
+    let (mut session, mut client) = open(config).await;
+
+    client.send(text_message("one").into()).await.unwrap();
+    client.send(text_message("two").into()).await.unwrap();
+    assert_eq!(session.recv().await, Some(SessionEvent::Message(RawData::Text("one".into()))));
+    assert_eq!(session.recv().await, Some(SessionEvent::Close(CloseReason::RateLimited)));
+    assert_eq!(client.next().await.unwrap().unwrap()._type(), &PacketType::Close);
+}
+
+#[tokio::test]
+async fn heartbeat_floods_are_limited() {
+    let config = RateLimitConfig::default()
+        .with_packets_per_second(5)
+        .with_policy(RateLimitPolicy::Disconnect);
+    let (mut session, mut client) = open(config).await;
+    for _ in 0..10 {
+        client.send(Packet::new(PacketType::Pong).into()).await.unwrap();
+    }
+    assert_eq!(session.recv().await, Some(SessionEvent::Close(CloseReason::RateLimited)));
+}
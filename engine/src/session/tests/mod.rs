@@ -0,0 +1,400 @@
+#[cfg(test)]
+mod buffer;
+#[cfg(test)]
+mod limit;
+#[cfg(test)]
+mod recovery;
+#[cfg(test)]
+mod state;
+
+use std::time::Duration;
+
+use futures::{FutureExt, SinkExt, StreamExt};
+
+use crate::protocol::{EncodedPacket, Handshake, Packet, PacketType, RawData};
+use crate::session::{generate_sid, CloseReason, Session, SessionConfig, SessionEvent};
+use crate::transport::memory::MemoryTransport;
+
+fn fast_config() -> SessionConfig {
+    SessionConfig::default()
+        .with_ping_interval(Duration::from_millis(50))
+        .with_ping_timeout(Duration::from_millis(50))
+}
+
+#[test]
+fn default_config_matches_engine_io() {
+    let config = SessionConfig::default();
+    assert_eq!(config.ping_interval(), Duration::from_millis(25_000));
+    assert_eq!(config.ping_timeout(), Duration::from_millis(20_000));
+    assert_eq!(config.max_payload(), 1_000_000);
+    assert!(config.upgrades().is_empty());
+}
+
+#[test]
+fn config_builds_handshake() {
+    let config = fast_config().with_upgrades(vec!["websocket".into()]);
+    let handshake = config.handshake("sid".into());
+    assert_eq!(handshake, Handshake::new("sid".into(), vec!["websocket".into()], 50, 50, 1_000_000));
+}
+
+#[test]
+fn generated_sids_are_unique_and_url_safe() {
+    let a = generate_sid();
+    let b = generate_sid();
+    assert_eq!(a.len(), 20);
+    assert_ne!(a, b);
+    assert!(a.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+}
+
+#[tokio::test]
+async fn heartbeat_keeps_session_open() {
+    let (server, client) = MemoryTransport::pair();
+    let config = fast_config();
+    let (server, client) = tokio::join!(
+        Session::accept(server, &config),
+        Session::connect(client),
+    );
+    let (server, mut client) = (server.unwrap(), client.unwrap());
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    server.send(RawData::Text("still here".into())).await.unwrap();
+    assert_eq!(client.recv().await, Some(SessionEvent::Message(RawData::Text("still here".into()))));
+}
+
+#[tokio::test]
+async fn server_times_out_silent_client() {
+    let (server, mut client) = MemoryTransport::pair();
+    let mut server = Session::accept(server, &fast_config()).await.unwrap();
+
+    // Read packets without ever answering a ping.
+    tokio::spawn(async move { while client.next().await.is_some() {} });
+
+    assert_eq!(server.recv().await, Some(SessionEvent::Close(CloseReason::PingTimeout)));
+    assert_eq!(server.recv().await, None);
+}
+
+#[tokio::test]
+async fn client_times_out_silent_server() {
+    let (mut server, client) = MemoryTransport::pair();
+    server.send(fast_config().handshake(generate_sid()).to_packet().into()).await.unwrap();
+
+    let mut client = Session::connect(client).await.unwrap();
+    assert_eq!(client.recv().await, Some(SessionEvent::Close(CloseReason::PingTimeout)));
+}
+
+#[tokio::test]
+async fn connect_rejects_non_open_packet() {
+    let (mut server, client) = MemoryTransport::pair();
+    server.send(Packet::new(PacketType::Noop).into()).await.unwrap();
+
+    assert!(Session::connect(client).await.is_err());
+}
+
+#[tokio::test]
+async fn close_notifies_peer() {
+    let (server, client) = MemoryTransport::pair();
+    let config = SessionConfig::default();
+    let (server, client) = tokio::join!(
+        Session::accept(server, &config),
+        Session::connect(client),
+    );
+    let (mut server, mut client) = (server.unwrap(), client.unwrap());
+
+    client.close();
+    assert_eq!(client.recv().await, Some(SessionEvent::Close(CloseReason::ForcedClose)));
+    assert_eq!(server.recv().await, Some(SessionEvent::Close(CloseReason::TransportClose)));
+    assert!(server.send(RawData::Text("gone".into())).await.is_err());
+}
+
+#[tokio::test]
+async fn encoded_packet_reaches_every_session() {
+    let config = SessionConfig::default();
+    let mut peers = Vec::new();
+    let mut servers = Vec::new();
+    for supports_binary in [true, false] {
+        let (server, client) = MemoryTransport::pair_with(16, supports_binary);
+        let (server, client) = tokio::join!(
+            Session::accept(server, &config),
+            Session::connect(client),
+        );
+        servers.push(server.unwrap());
+        peers.push(client.unwrap());
+    }
+
+    let mut packet = Packet::new(PacketType::Message);
+    packet.with_data(RawData::Binary(vec![1, 2, 3])).unwrap();
+    let broadcast = EncodedPacket::new(packet);
+    for server in &servers {
+        server.send_encoded(broadcast.clone()).await.unwrap();
+    }
+    for peer in &mut peers {
+        assert_eq!(peer.recv().await, Some(SessionEvent::Message(RawData::Binary(vec![1, 2, 3]))));
+    }
+}
+
+#[cfg(feature = "compression")]
+#[tokio::test]
+async fn messages_are_compressed_with_the_negotiated_algorithm() {
+    use crate::compression::CompressionConfig;
+    use crate::protocol::{Compression, PacketOptions};
+
+    let text = "compress me, ".repeat(20);
+    let server_config = SessionConfig::default()
+        .with_compression(CompressionConfig::default().with_algorithms(vec![Compression::Brotli, Compression::Gzip]));
+    let negotiated = server_config.clone().negotiate_compression(&[Compression::Gzip], None);
+    let client_config = SessionConfig::default()
+        .with_compression(CompressionConfig::default().with_algorithms(vec![Compression::Gzip]));
+    let (server, client) = MemoryTransport::pair();
+    let (server, client) = tokio::join!(
+        Session::accept(server, &negotiated),
+        Session::connect_with(client, &client_config),
+    );
+    let (mut server, mut client) = (server.unwrap(), client.unwrap());
+    assert_eq!(client.handshake().compression(), &[Compression::Gzip]);
+
+    server.send(RawData::Text(text.clone())).await.unwrap();
+    assert_eq!(client.recv().await, Some(SessionEvent::Message(RawData::Text(text.clone()))));
+    client.send(RawData::Text(text.clone())).await.unwrap();
+    assert_eq!(server.recv().await, Some(SessionEvent::Message(RawData::Text(text.clone()))));
+
+    // A raw peer sees the agreed algorithm on the wire.
+    let (server, mut peer) = MemoryTransport::pair();
+    let server = Session::accept(server, &negotiated).await.unwrap();
+    let _open = peer.next().await;
+    server.send(RawData::Text(text.clone())).await.unwrap();
+    let packet = peer.next().await.unwrap().unwrap();
+    assert_eq!(packet.options().and_then(PacketOptions::compression), Some(Compression::Gzip));
+
+    // Nothing was agreed with a peer that made no offer, so the server does not compress.
+    let (server, mut peer) = MemoryTransport::pair();
+    let server = Session::accept(server, &server_config).await.unwrap();
+    let _open = peer.next().await;
+    server.send(RawData::Text(text.clone())).await.unwrap();
+    let packet = peer.next().await.unwrap().unwrap();
+    assert_eq!(packet.data(), Some(&RawData::Text(text)));
+}
+
+#[tokio::test]
+async fn upgrades_need_an_advertised_transport_and_a_probe() {
+    use crate::session::{SessionError, SessionState, TransitionReason};
+
+    let config = fast_config();
+    let (server, client) = MemoryTransport::pair();
+    let (server, client) = tokio::join!(Session::accept(server, &config), Session::connect(client));
+    let client = client.unwrap();
+    assert_eq!(client.upgrade(MemoryTransport::pair().0).await, Err(SessionError::UpgradeFailed));
+    assert_eq!(client.state(), SessionState::Open);
+    drop(server);
+
+    let config = config.with_upgrades(vec!["memory".into()]);
+    let (server, client) = MemoryTransport::pair();
+    let (server, client) = tokio::join!(Session::accept(server, &config), Session::connect(client));
+    let (mut server, mut client) = (server.unwrap(), client.unwrap());
+    let (transport, mut peer) = MemoryTransport::pair();
+    let mut packet = Packet::new(PacketType::Message);
+    packet.with_data(RawData::Text("not a probe".into())).unwrap();
+    peer.send(packet.into()).await.unwrap();
+    assert_eq!(server.upgrade(transport).await, Err(SessionError::UpgradeFailed));
+
+    let reasons: Vec<_> = std::iter::from_fn(|| server.recv_lifecycle().now_or_never().flatten())
+        .map(|event| event.reason())
+        .collect();
+    assert_eq!(reasons, [TransitionReason::Handshake, TransitionReason::UpgradeStarted, TransitionReason::UpgradeAborted]);
+    server.send(RawData::Text("still here".into())).await.unwrap();
+    assert_eq!(client.recv().await, Some(SessionEvent::Message(RawData::Text("still here".into()))));
+}
+
+#[cfg(feature = "encryption")]
+#[tokio::test]
+async fn encrypted_sessions_agree_keys_in_the_handshake() {
+    use crate::encryption::{EncryptionConfig, KeyExchange};
+
+    let config = SessionConfig::default().with_encryption(EncryptionConfig::default().with_rotate_after(2));
+    let exchange = KeyExchange::new().unwrap();
+    let client_key = exchange.public_key();
+    let (server, client) = MemoryTransport::pair();
+    let (server, client) = tokio::join!(
+        Session::accept_encrypted(server, &config, &client_key),
+        Session::connect_encrypted(client, &config, exchange),
+    );
+    let (mut server, mut client) = (server.unwrap(), client.unwrap());
+    assert!(client.handshake().key().is_some());
+
+    // Enough messages to rotate keys in both directions.
+    for n in 0..5 {
+        server.send(RawData::Text(format!("down {n}"))).await.unwrap();
+        client.send(RawData::Binary(vec![n])).await.unwrap();
+    }
+    for n in 0..5 {
+        assert_eq!(client.recv().await, Some(SessionEvent::Message(RawData::Text(format!("down {n}")))));
+        assert_eq!(server.recv().await, Some(SessionEvent::Message(RawData::Binary(vec![n]))));
+    }
+}
+
+#[cfg(feature = "encryption")]
+#[tokio::test]
+async fn encrypted_session_closes_on_replayed_message() {
+    use crate::encryption::KeyExchange;
+
+    let exchange = KeyExchange::new().unwrap();
+    let (server, mut peer) = MemoryTransport::pair();
+    let mut server = Session::accept_encrypted(server, &SessionConfig::default(), &exchange.public_key()).await.unwrap();
+    let open = peer.next().await.unwrap().unwrap();
+    let server_key = Handshake::from_packet(&open).unwrap().key().unwrap().to_string();
+    let mut channel = exchange.agree(&server_key, crate::session::Role::Client, &Default::default()).unwrap();
+
+    let mut packet = Packet::new(PacketType::Message);
+    packet.with_data(RawData::Text("once".into())).unwrap();
+    let sealed = channel.seal(&packet).unwrap();
+    peer.send(sealed.clone().into()).await.unwrap();
+    assert_eq!(server.recv().await, Some(SessionEvent::Message(RawData::Text("once".into()))));
+    peer.send(sealed.into()).await.unwrap();
+    assert_eq!(server.recv().await, Some(SessionEvent::Close(CloseReason::ParseError)));
+}
+
+#[cfg(feature = "signing")]
+#[tokio::test]
+async fn signed_messages_are_verified_across_a_relay() {
+    use crate::protocol::PacketOptions;
+    use crate::signing::{SignatureConfig, SigningKey};
+
+    let key = SigningKey::from_pkcs8(7, &SigningKey::generate_pkcs8().unwrap()).unwrap();
+    let public = key.public_key();
+    let config = SessionConfig::default()
+        .with_signatures(SignatureConfig::new(move |key_id| (key_id == 7).then_some(public)));
+    let (relay_in, origin) = MemoryTransport::pair();
+    let (relay_in, origin) = tokio::join!(Session::accept(relay_in, &config), Session::connect_with(origin, &config));
+    // The destination keeps its own record of accepted counters, as a separate process would.
+    let destination_config = SessionConfig::default()
+        .with_signatures(SignatureConfig::new(move |key_id| (key_id == 7).then_some(public)));
+    let (relay_out, destination) = MemoryTransport::pair();
+    let (relay_out, destination) = tokio::join!(Session::accept(relay_out, &config), Session::connect_with(destination, &destination_config));
+    let (mut relay_in, origin, relay_out, mut destination) = (relay_in.unwrap(), origin.unwrap(), relay_out.unwrap(), destination.unwrap());
+
+    let mut packet = Packet::new(PacketType::Message);
+    packet.with_data(RawData::Text("from origin".into())).unwrap();
+    origin.send_packet(key.sign(packet).unwrap()).await.unwrap();
+    let (data, signature) = match relay_in.recv().await {
+        Some(SessionEvent::Signed(data, signature)) => (data, signature),
+        event => panic!("expected a signed message, got {event:?}"),
+    };
+    assert_eq!(signature.key_id(), 7);
+
+    // The relay forwards the data with the origin's signature, which the destination verifies on its own.
+    let forward = |data: RawData| {
+        let mut packet = Packet::new(PacketType::Message);
+        packet.with_data(data).unwrap();
+        packet.with_options(PacketOptions::default().with_signature(signature));
+        packet
+    };
+    relay_out.send_packet(forward(data.clone())).await.unwrap();
+    assert_eq!(destination.recv().await, Some(SessionEvent::Signed(data, signature)));
+
+    // A relay that alters the data cannot make the signature fit: the message is dropped and the session goes on.
+    relay_out.send_packet(forward(RawData::Text("from relay".into()))).await.unwrap();
+    relay_out.send(RawData::Text("unsigned".into())).await.unwrap();
+    assert_eq!(destination.recv().await, Some(SessionEvent::Message(RawData::Text("unsigned".into()))));
+}
+
+#[cfg(feature = "signing")]
+#[tokio::test]
+async fn sessions_sharing_a_config_accept_interleaved_counters() {
+    use crate::signing::{SignatureConfig, SigningKey};
+
+    let key = SigningKey::from_pkcs8(3, &SigningKey::generate_pkcs8().unwrap()).unwrap();
+    let public = key.public_key();
+    let config = SessionConfig::default()
+        .with_signatures(SignatureConfig::new(move |key_id| (key_id == 3).then_some(public)));
+    let mut sessions = Vec::new();
+    for _ in 0..2 {
+        let (server, client) = MemoryTransport::pair();
+        let (server, client) = tokio::join!(Session::accept(server, &config), Session::connect(client));
+        sessions.push((server.unwrap(), client.unwrap()));
+    }
+    let signed: Vec<_> = (0..5).map(|n| {
+        let mut packet = Packet::new(PacketType::Message);
+        packet.with_data(RawData::Text(n.to_string())).unwrap();
+        key.sign(packet).unwrap()
+    }).collect();
+
+    // Counters 1 and 3 travel over the first session, 0 and 2 over the second, each arriving after a higher one.
+    for (n, session) in [(1, 0), (0, 1), (3, 0), (2, 1)] {
+        let (server, client) = &mut sessions[session];
+        client.send_packet(signed[n].clone()).await.unwrap();
+        match server.recv().await {
+            Some(SessionEvent::Signed(data, signature)) => {
+                assert_eq!(data, RawData::Text(n.to_string()));
+                assert_eq!(signature.counter(), n as u64);
+            }
+            event => panic!("expected signed message {n}, got {event:?}"),
+        }
+    }
+
+    // A message replayed into the other session is dropped, and that session stays open.
+    let (server, client) = &mut sessions[1];
+    client.send_packet(signed[1].clone()).await.unwrap();
+    client.send_packet(signed[4].clone()).await.unwrap();
+    assert!(matches!(server.recv().await, Some(SessionEvent::Signed(data, _)) if data == RawData::Text("4".into())));
+}
+
+#[cfg(feature = "signing")]
+#[tokio::test]
+async fn required_signatures_drop_unsigned_messages() {
+    use crate::signing::{SignatureConfig, SigningKey};
+
+    let key = SigningKey::from_pkcs8(1, &SigningKey::generate_pkcs8().unwrap()).unwrap();
+    let mut signed = Packet::new(PacketType::Message);
+    signed.with_data(RawData::Text("signed".into())).unwrap();
+    let signed = key.sign(signed).unwrap();
+
+    // Without a verifier, signatures are neither checked nor surfaced.
+    let config = SessionConfig::default();
+    let (server, client) = MemoryTransport::pair();
+    let (server, client) = tokio::join!(Session::accept(server, &config), Session::connect(client));
+    let (mut server, client) = (server.unwrap(), client.unwrap());
+    client.send_packet(signed.clone()).await.unwrap();
+    assert_eq!(server.recv().await, Some(SessionEvent::Message(RawData::Text("signed".into()))));
+
+    let public = key.public_key();
+    let required = SessionConfig::default()
+        .with_signatures(SignatureConfig::new(move |key_id| (key_id == 1).then_some(public)).with_required(true));
+    let (server, client) = MemoryTransport::pair();
+    let (server, client) = tokio::join!(Session::accept(server, &required), Session::connect(client));
+    let (mut server, client) = (server.unwrap(), client.unwrap());
+    client.send(RawData::Text("unsigned".into())).await.unwrap();
+    client.send_packet(signed).await.unwrap();
+    assert!(matches!(server.recv().await, Some(SessionEvent::Signed(data, _)) if data == RawData::Text("signed".into())));
+}
+
+#[cfg(feature = "encryption")]
+#[tokio::test]
+async fn encryption_failures_refuse_the_session() {
+    use crate::encryption::{EncryptionConfig, EncryptionError, KeyExchange};
+    use crate::session::SessionError;
+
+    // The server requires encryption but the client sent no key.
+    let required = SessionConfig::default().with_encryption(EncryptionConfig::default().with_required(true));
+    let (server, _client) = MemoryTransport::pair();
+    let refused = Session::accept(server, &required).await;
+    assert_eq!(refused.err(), Some(SessionError::Encryption(EncryptionError::MissingKey)));
+
+    // The client asked for encryption but the server answered in the clear.
+    let config = SessionConfig::default();
+    let (server, client) = MemoryTransport::pair();
+    let (_server, client) = tokio::join!(
+        Session::accept(server, &config),
+        Session::connect_encrypted(client, &config, KeyExchange::new().unwrap()),
+    );
+    assert_eq!(client.err(), Some(SessionError::Encryption(EncryptionError::MissingKey)));
+
+    // Sealed messages must fit in a packet.
+    let exchange = KeyExchange::new().unwrap();
+    let (server, client) = MemoryTransport::pair();
+    let client_key = exchange.public_key();
+    let (server, _client) = tokio::join!(
+        Session::accept_encrypted(server, &config, &client_key),
+        Session::connect_encrypted(client, &config, exchange),
+    );
+    let full = RawData::Binary(vec![0; crate::protocol::MAX_PACKET_SIZE]);
+    assert_eq!(server.unwrap().try_send(full), Err(SessionError::Packet(crate::protocol::PacketError::DataTooLarge)));
+}
@@ -0,0 +1,163 @@
+use std::time::{Duration, Instant};
+
+use futures::{SinkExt, StreamExt};
+
+use crate::protocol::{EncodedPacket, Handshake, Packet, PacketType, RawData};
+use crate::protocol::tests::text_message;
+use crate::session::recovery::ReplayLog;
+use crate::session::{
+    CloseReason, Recovery, RecoveryConfig, Session, SessionConfig, SessionError, SessionEvent, SessionState,
+};
+use crate::transport::memory::MemoryTransport;
+
+fn replayed(log: &ReplayLog, offset: u64) -> Option<Vec<Packet>> {
+    log.replay(offset).map(|packets| packets.iter().map(EncodedPacket::packet).cloned().collect())
+}
+
+async fn next_packet(transport: &mut MemoryTransport) -> Packet {
+    transport.next().await.unwrap().unwrap()
+}
+
+/// Returns the private id a client presents to resume `session`.
+fn pid(session: &Session) -> &str {
+    session.handshake().pid().unwrap()
+}
+
+/// Accepts a recoverable session over a fresh memory transport, returning the raw client side.
+async fn open(recovery: &Recovery, config: &SessionConfig) -> (Session, MemoryTransport) {
+    let (server, mut client) = MemoryTransport::pair();
+    let session = recovery.accept(server, config).await.unwrap();
+    assert_eq!(next_packet(&mut client).await._type(), &PacketType::Open);
+    (session, client)
+}
+
+#[test]
+fn replay_log_tracks_offsets() {
+    let mut log = ReplayLog::new(8);
+    for n in 1..=3 {
+        log.push(text_message(&n.to_string()).into());
+    }
+    assert_eq!(replayed(&log, 0), Some(vec![text_message("1"), text_message("2"), text_message("3")]));
+    assert_eq!(replayed(&log, 2), Some(vec![text_message("3")]));
+    assert_eq!(replayed(&log, 3), Some(vec![]));
+    assert_eq!(replayed(&log, 4), None);
+
+    log.acknowledge(2);
+    assert_eq!(replayed(&log, 2), Some(vec![text_message("3")]));
+    assert_eq!(replayed(&log, 1), None);
+}
+
+#[test]
+fn replay_log_drops_oldest_when_full() {
+    let mut log = ReplayLog::new(2);
+    for n in 1..=3 {
+        log.push(text_message(&n.to_string()).into());
+    }
+    assert_eq!(replayed(&log, 0), None);
+    assert_eq!(replayed(&log, 1), Some(vec![text_message("2"), text_message("3")]));
+}
+
+#[tokio::test]
+async fn resume_replays_unreceived_messages() {
+    let recovery = Recovery::new(RecoveryConfig::default());
+    let (mut session, mut client) = open(&recovery, &SessionConfig::default()).await;
+
+    session.send(RawData::Text("one".into())).await.unwrap();
+    session.send(RawData::Text("two".into())).await.unwrap();
+    assert_eq!(next_packet(&mut client).await, text_message("one"));
+    drop(client);
+
+    // Sent while the client is away.
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    session.send(RawData::Text("three".into())).await.unwrap();
+
+    let (server, mut client) = MemoryTransport::pair();
+    recovery.resume(server, session.sid(), pid(&session), 1).await.unwrap();
+    let handshake = Handshake::from_packet(&next_packet(&mut client).await).unwrap();
+    assert_eq!(handshake.sid(), session.sid());
+    assert_eq!(next_packet(&mut client).await, text_message("two"));
+    assert_eq!(next_packet(&mut client).await, text_message("three"));
+
+    client.send(text_message("back").into()).await.unwrap();
+    assert_eq!(session.recv().await, Some(SessionEvent::Message(RawData::Text("back".into()))));
+    assert_eq!(session.state(), SessionState::Open);
+}
+
+#[tokio::test]
+async fn resume_takes_over_live_transport() {
+    let recovery = Recovery::new(RecoveryConfig::default());
+    let (session, mut old) = open(&recovery, &SessionConfig::default()).await;
+
+    let (server, mut client) = MemoryTransport::pair();
+    recovery.resume(server, session.sid(), pid(&session), 0).await.unwrap();
+    assert_eq!(next_packet(&mut client).await._type(), &PacketType::Open);
+    assert!(old.next().await.is_none());
+
+    session.send(RawData::Text("moved".into())).await.unwrap();
+    assert_eq!(next_packet(&mut client).await, text_message("moved"));
+}
+
+#[tokio::test]
+async fn close_fires_after_window_expires() {
+    let recovery = Recovery::new(RecoveryConfig::default().with_window(Duration::from_millis(100)));
+    let (mut session, client) = open(&recovery, &SessionConfig::default()).await;
+    assert_eq!(recovery.len(), 1);
+
+    let started = Instant::now();
+    drop(client);
+    assert_eq!(session.recv().await, Some(SessionEvent::Close(CloseReason::TransportClose)));
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    assert!(recovery.is_empty());
+    assert_eq!(recovery.resume(MemoryTransport::pair().0, session.sid(), pid(&session), 0).await, Err(SessionError::NotRecoverable));
+}
+
+#[tokio::test]
+async fn resume_rejects_lost_offsets_unknown_sids_and_wrong_pids() {
+    let recovery = Recovery::new(RecoveryConfig::default().with_max_packets(1));
+    let (session, mut client) = open(&recovery, &SessionConfig::default()).await;
+    session.send(RawData::Text("one".into())).await.unwrap();
+    session.send(RawData::Text("two".into())).await.unwrap();
+    next_packet(&mut client).await;
+    next_packet(&mut client).await;
+
+    let result = recovery.resume(MemoryTransport::pair().0, session.sid(), pid(&session), 0).await;
+    assert_eq!(result, Err(SessionError::NotRecoverable));
+    let result = recovery.resume(MemoryTransport::pair().0, "unknown", pid(&session), 0).await;
+    assert_eq!(result, Err(SessionError::NotRecoverable));
+    let result = recovery.resume(MemoryTransport::pair().0, session.sid(), session.sid(), 2).await;
+    assert_eq!(result, Err(SessionError::NotRecoverable));
+
+    // The session is unaffected by a failed resume.
+    session.send(RawData::Text("three".into())).await.unwrap();
+    assert_eq!(next_packet(&mut client).await, text_message("three"));
+}
+
+#[tokio::test]
+async fn close_packet_is_not_recoverable() {
+    let recovery = Recovery::new(RecoveryConfig::default());
+    let (mut session, mut client) = open(&recovery, &SessionConfig::default()).await;
+
+    client.send(Packet::new(PacketType::Close).into()).await.unwrap();
+    assert_eq!(session.recv().await, Some(SessionEvent::Close(CloseReason::TransportClose)));
+    assert!(recovery.is_empty());
+}
+
+#[tokio::test]
+async fn client_acknowledges_offset_in_pongs() {
+    let recovery = Recovery::new(RecoveryConfig::default());
+    let config = SessionConfig::default()
+        .with_ping_interval(Duration::from_millis(30))
+        .with_ping_timeout(Duration::from_millis(200));
+    let (server, client) = MemoryTransport::pair();
+    let (server, client) = tokio::join!(recovery.accept(server, &config), Session::connect(client));
+    let (server, mut client) = (server.unwrap(), client.unwrap());
+
+    server.send(RawData::Text("one".into())).await.unwrap();
+    assert_eq!(client.recv().await, Some(SessionEvent::Message(RawData::Text("one".into()))));
+    assert_eq!(client.offset(), 1);
+
+    // After the next heartbeat the server has forgotten everything up to the client's offset.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let result = recovery.resume(MemoryTransport::pair().0, server.sid(), pid(&server), 0).await;
+    assert_eq!(result, Err(SessionError::NotRecoverable));
+}
@@ -0,0 +1,132 @@
+use futures::{SinkExt, StreamExt};
+
+use crate::protocol::{Packet, PacketType, RawData};
+use crate::session::state::Lifecycle;
+use crate::session::{
+    CloseReason, LifecycleEvent, Session, SessionConfig, SessionError, SessionState, TransitionReason,
+};
+use crate::transport::memory::MemoryTransport;
+
+#[test]
+fn legal_transitions() {
+    use SessionState::*;
+    assert!(Opening.can_transition(Open));
+    assert!(Open.can_transition(Upgrading));
+    assert!(Upgrading.can_transition(Open));
+    assert!(Upgrading.can_transition(Closing));
+    assert!(Open.can_transition(Closing));
+    assert!(Closing.can_transition(Closed));
+    assert!(Opening.can_transition(Closed));
+}
+
+#[test]
+fn illegal_transitions() {
+    use SessionState::*;
+    assert!(!Opening.can_transition(Upgrading));
+    assert!(!Opening.can_transition(Closing));
+    assert!(!Closing.can_transition(Open));
+    assert!(!Open.can_transition(Open));
+    for state in [Opening, Open, Upgrading, Closing, Closed] {
+        assert!(!Closed.can_transition(state));
+    }
+}
+
+#[test]
+fn messages_only_flow_while_open() {
+    assert!(!SessionState::Opening.can_send(&PacketType::Message));
+    assert!(SessionState::Opening.can_send(&PacketType::Open));
+    assert!(SessionState::Open.can_send(&PacketType::Message));
+    assert!(!SessionState::Open.can_send(&PacketType::Open));
+    assert!(SessionState::Upgrading.can_send(&PacketType::Message));
+    assert!(!SessionState::Closing.can_send(&PacketType::Message));
+    assert!(SessionState::Closing.can_send(&PacketType::Close));
+
+    assert!(!SessionState::Opening.can_receive(&PacketType::Message));
+    assert!(SessionState::Closing.can_receive(&PacketType::Message));
+    assert!(!SessionState::Closed.can_receive(&PacketType::Message));
+    assert!(!SessionState::Closed.can_receive(&PacketType::Close));
+}
+
+#[tokio::test]
+async fn lifecycle_raises_event_per_transition() {
+    let (lifecycle, mut transitions) = Lifecycle::channel();
+    assert_eq!(lifecycle.state(), SessionState::Opening);
+
+    lifecycle.transition(SessionState::Open, TransitionReason::Handshake).unwrap();
+    lifecycle.transition(SessionState::Upgrading, TransitionReason::UpgradeStarted).unwrap();
+    lifecycle.transition(SessionState::Open, TransitionReason::UpgradeAborted).unwrap();
+    lifecycle.transition(SessionState::Closed, TransitionReason::Closed(CloseReason::PingTimeout)).unwrap();
+
+    assert_eq!(transitions.recv().await, Some(LifecycleEvent::new(SessionState::Opening, SessionState::Open, TransitionReason::Handshake)));
+    assert_eq!(transitions.recv().await, Some(LifecycleEvent::new(SessionState::Open, SessionState::Upgrading, TransitionReason::UpgradeStarted)));
+    assert_eq!(transitions.recv().await, Some(LifecycleEvent::new(SessionState::Upgrading, SessionState::Open, TransitionReason::UpgradeAborted)));
+    assert_eq!(transitions.recv().await, Some(LifecycleEvent::new(SessionState::Open, SessionState::Closed, TransitionReason::Closed(CloseReason::PingTimeout))));
+    assert_eq!(transitions.recv().await, None);
+}
+
+#[test]
+fn lifecycle_rejects_illegal_transition() {
+    let (lifecycle, _transitions) = Lifecycle::channel();
+    assert_eq!(
+        lifecycle.transition(SessionState::Closing, TransitionReason::CloseRequested),
+        Err(SessionError::InvalidState(SessionState::Opening)),
+    );
+    assert_eq!(lifecycle.state(), SessionState::Opening);
+}
+
+#[tokio::test]
+async fn session_reports_close_sequence() {
+    let (server, client) = MemoryTransport::pair();
+    let config = SessionConfig::default();
+    let (server, client) = tokio::join!(
+        Session::accept(server, &config),
+        Session::connect(client),
+    );
+    let (mut server, mut client) = (server.unwrap(), client.unwrap());
+    assert_eq!(server.state(), SessionState::Open);
+
+    server.close();
+    assert!(matches!(server.state(), SessionState::Closing | SessionState::Closed));
+
+    let open = LifecycleEvent::new(SessionState::Opening, SessionState::Open, TransitionReason::Handshake);
+    assert_eq!(server.recv_lifecycle().await, Some(open));
+    assert_eq!(server.recv_lifecycle().await, Some(LifecycleEvent::new(SessionState::Open, SessionState::Closing, TransitionReason::CloseRequested)));
+    assert_eq!(server.recv_lifecycle().await, Some(LifecycleEvent::new(SessionState::Closing, SessionState::Closed, TransitionReason::Closed(CloseReason::ForcedClose))));
+    assert_eq!(server.recv_lifecycle().await, None);
+
+    assert_eq!(client.recv_lifecycle().await, Some(open));
+    assert_eq!(client.recv_lifecycle().await, Some(LifecycleEvent::new(SessionState::Open, SessionState::Closed, TransitionReason::Closed(CloseReason::TransportClose))));
+    assert_eq!(client.state(), SessionState::Closed);
+}
+
+#[tokio::test]
+async fn session_rejects_packets_illegal_for_state() {
+    let (server, client) = MemoryTransport::pair();
+    let config = SessionConfig::default();
+    let (server, client) = tokio::join!(
+        Session::accept(server, &config),
+        Session::connect(client),
+    );
+    let (server, _client) = (server.unwrap(), client.unwrap());
+
+    let open = config.handshake("sid".into()).to_packet();
+    assert_eq!(server.try_send_packet(open), Err(SessionError::InvalidState(SessionState::Open)));
+
+    server.close();
+    assert_eq!(server.try_send(RawData::Text("late".into())), Err(SessionError::Closed));
+}
+
+#[tokio::test]
+async fn session_ignores_second_open() {
+    let (server, mut client) = MemoryTransport::pair();
+    let mut server = Session::accept(server, &SessionConfig::default()).await.unwrap();
+    client.next().await.unwrap().unwrap();
+
+    client.send(SessionConfig::default().handshake("other".into()).to_packet().into()).await.unwrap();
+    let mut message = Packet::new(PacketType::Message);
+    message.with_data(RawData::Text("after".into())).unwrap();
+    client.send(message.into()).await.unwrap();
+
+    assert_eq!(server.recv().await, Some(crate::session::SessionEvent::Message(RawData::Text("after".into()))));
+    assert_eq!(server.state(), SessionState::Open);
+}
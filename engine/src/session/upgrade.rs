@@ -0,0 +1,60 @@
+//! Transport upgrades: moving an open session onto another transport listed in `Handshake.upgrades`.
+//!
+//! The client opens the new transport and probes it with a `Ping` carrying `probe`, which the server answers with a
+//! `Pong` carrying `probe`, as Engine.IO does for WebSocket. Packets keep flowing over the old transport meanwhile.
+//! The client then stops writing to the old transport and sends `Upgrade` on the new one. The server answers with a
+//! `Noop` on the old transport, so a waiting poll returns, and both sides close it once it is drained.
+
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use tokio::sync::oneshot;
+
+use crate::protocol::{Packet, PacketType, RawData};
+use crate::rt;
+use crate::session::{Role, SessionError};
+use crate::transport::Transport;
+
+/// Data carried by the probe `Ping` and `Pong`.
+const PROBE: &str = "probe";
+
+/// A probed transport for the driver to move the session onto.
+pub(crate) struct Upgrade {
+    pub(crate) transport: Box<dyn Transport>,
+    pub(crate) reply: oneshot::Sender<Result<(), SessionError>>,
+}
+
+/// Runs one side of the probe on the new transport, failing if the peer does not finish it within `timeout`.
+/// The client's closing `Upgrade` is left to the driver, which sends it once it has stopped writing to the old transport.
+pub(crate) async fn probe(transport: &mut Box<dyn Transport>, role: Role, timeout: Duration) -> Result<(), SessionError> {
+    let exchange = async {
+        match role {
+            Role::Client => {
+                transport.send(probe_packet(PacketType::Ping).into()).await?;
+                expect(transport, probe_packet(PacketType::Pong)).await
+            }
+            Role::Server => {
+                expect(transport, probe_packet(PacketType::Ping)).await?;
+                transport.send(probe_packet(PacketType::Pong).into()).await?;
+                expect(transport, Packet::new(PacketType::Upgrade)).await
+            }
+        }
+    };
+    rt::timeout(timeout, exchange).await
+        .unwrap_or(Err(SessionError::UpgradeFailed))
+}
+
+fn probe_packet(packet_type: PacketType) -> Packet {
+    let mut packet = Packet::new(packet_type);
+    packet.with_data(RawData::Text(PROBE.into())).ok();
+    packet
+}
+
+/// Reads the next packet, failing unless it is `expected`.
+async fn expect(transport: &mut Box<dyn Transport>, expected: Packet) -> Result<(), SessionError> {
+    match transport.next().await {
+        Some(Ok(packet)) if packet == expected => Ok(()),
+        Some(Err(e)) => Err(SessionError::Transport(e)),
+        _ => Err(SessionError::UpgradeFailed),
+    }
+}
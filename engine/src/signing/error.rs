@@ -0,0 +1,32 @@
+use std::fmt;
+
+/// Error type for signing and verifying packets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    /// Key material is not a valid Ed25519 key.
+    InvalidKey,
+    /// Packet data is already compressed or encrypted; packets are signed before either.
+    Encoded,
+    /// Message is unsigned but signatures are required.
+    Unsigned,
+    /// No public key is known for the signature's key id.
+    UnknownKey(u32),
+    /// Signature does not match the packet data.
+    Invalid,
+    /// Signature counter was already accepted for its key, or is too far behind the newest one to tell.
+    Replayed,
+}
+
+impl std::error::Error for SignatureError {}
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureError::InvalidKey => write!(f, "Signing key is invalid"),
+            SignatureError::Encoded => write!(f, "Packet data is already compressed or encrypted"),
+            SignatureError::Unsigned => write!(f, "Message is not signed"),
+            SignatureError::UnknownKey(key_id) => write!(f, "No public key for key id {key_id}"),
+            SignatureError::Invalid => write!(f, "Signature does not match the packet data"),
+            SignatureError::Replayed => write!(f, "Signature counter was already used"),
+        }
+    }
+}
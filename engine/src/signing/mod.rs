@@ -0,0 +1,252 @@
+//! Per-packet authenticity with Ed25519 signatures.
+//!
+//! A sender signs a `Message` packet's data with a `SigningKey` before sending it; the signature and the key's id
+//! travel in the packet options. Receivers with a `SignatureConfig` look the key id up through their `KeyResolver`
+//! and verify the signature before the data reaches the application, delivering it as [`SessionEvent::Signed`].
+//!
+//! Signatures cover the data as the sender wrote it, before compression and encryption, so they survive relays that
+//! decrypt, decompress and re-encode packets: an intermediary can forward a signed message but cannot alter or forge one.
+//! They also cover the packet type, the chunk fields and a counter the key raises with every message. A receiver
+//! accepts each counter of a key once, across every session sharing its `SignatureConfig`, so a captured message
+//! cannot be delivered twice. Counters may arrive out of order, as over several sessions or relay paths, as long as
+//! they are within `REPLAY_WINDOW` of the newest one accepted for the key.
+//!
+//! [`SessionEvent::Signed`]: crate::session::SessionEvent::Signed
+
+mod error;
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+
+use crate::protocol::{Packet, RawData, Signature};
+
+pub use error::SignatureError;
+
+/// Prefixed to every signed message; changing it makes old and new peers reject each other's signatures.
+const CONTEXT: &[u8] = b"green-engine signature v2";
+
+/// Marks whether signed data was text or binary, so one cannot be passed off as the other.
+const TEXT_DATA: u8 = 0;
+const BINARY_DATA: u8 = 1;
+
+/// Number of counters below a key's newest accepted one that may still arrive late.
+pub const REPLAY_WINDOW: u64 = 64;
+
+/// Length of an Ed25519 public key in bytes.
+pub const PUBLIC_KEY_LEN: usize = 32;
+
+/// An Ed25519 key pair that signs packets under a key id.
+pub struct SigningKey {
+    key_id: u32,
+    pair: Ed25519KeyPair,
+    /// Counter the next signature carries.
+    counter: AtomicU64,
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("key_id", &self.key_id)
+            .field("counter", &self.counter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SigningKey {
+    /// Generates a new key pair, returned as a PKCS#8 document for `from_pkcs8`.
+    pub fn generate_pkcs8() -> Result<Vec<u8>, SignatureError> {
+        Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map(|document| document.as_ref().to_vec())
+            .map_err(|_| SignatureError::InvalidKey)
+    }
+
+    /// Loads a key pair from a PKCS#8 document, signing under `key_id`.
+    pub fn from_pkcs8(key_id: u32, pkcs8: &[u8]) -> Result<Self, SignatureError> {
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8)
+            .map_err(|_| SignatureError::InvalidKey)?;
+        Ok(Self { key_id, pair, counter: AtomicU64::new(0) })
+    }
+
+    /// Returns the counter the next signature carries.
+    pub fn counter(&self) -> u64 {
+        self.counter.load(Ordering::Relaxed)
+    }
+
+    /// Sets the counter the next signature carries, so a key loaded again after a restart
+    /// continues past the messages receivers have already accepted.
+    pub fn with_counter(mut self, counter: u64) -> Self {
+        *self.counter.get_mut() = counter;
+        self
+    }
+
+    /// Returns the id receivers resolve this key's public key by.
+    pub fn key_id(&self) -> u32 {
+        self.key_id
+    }
+
+    /// Returns the public key, for the receivers' `KeyResolver`.
+    pub fn public_key(&self) -> [u8; PUBLIC_KEY_LEN] {
+        let mut public = [0; PUBLIC_KEY_LEN];
+        public.copy_from_slice(self.pair.public_key().as_ref());
+        public
+    }
+
+    /// Signs a packet under the next counter, replacing any earlier signature.
+    /// Packets must be signed before they are compressed or encrypted, and after they are split into chunks.
+    pub fn sign(&self, mut packet: Packet) -> Result<Packet, SignatureError> {
+        let options = packet.options().copied().unwrap_or_default();
+        if options.compress() || options.encrypt() {
+            return Err(SignatureError::Encoded);
+        }
+        let counter = self.counter.fetch_add(1, Ordering::Relaxed);
+        let signature = self.pair.sign(&signed_bytes(self.key_id, counter, &packet));
+        let mut bytes = [0; Signature::LEN];
+        bytes.copy_from_slice(signature.as_ref());
+        packet.with_options(options.with_signature(Signature::new(self.key_id, counter, bytes)));
+        Ok(packet)
+    }
+}
+
+/// Looks up the public key for a signature's key id.
+pub trait KeyResolver: Send + Sync + 'static {
+    /// Returns the Ed25519 public key for `key_id`, or `None` if the key is unknown or revoked.
+    fn resolve(&self, key_id: u32) -> Option<[u8; PUBLIC_KEY_LEN]>;
+}
+
+impl<F> KeyResolver for F
+where
+    F: Fn(u32) -> Option<[u8; PUBLIC_KEY_LEN]> + Send + Sync + 'static,
+{
+    fn resolve(&self, key_id: u32) -> Option<[u8; PUBLIC_KEY_LEN]> {
+        self(key_id)
+    }
+}
+
+/// Signature verification settings for a session.
+#[derive(Clone)]
+pub struct SignatureConfig {
+    /// Source of the public keys signatures are verified with.
+    resolver: Arc<dyn KeyResolver>,
+    /// Whether unsigned messages are rejected.
+    required: bool,
+    /// Counters accepted per key id, shared by every clone.
+    seen: Arc<Mutex<HashMap<u32, ReplayWindow>>>,
+}
+
+impl fmt::Debug for SignatureConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignatureConfig").field("required", &self.required).finish_non_exhaustive()
+    }
+}
+
+impl SignatureConfig {
+    /// Verifies signed messages with the public keys `resolver` returns.
+    pub fn new(resolver: impl KeyResolver) -> Self {
+        Self {
+            resolver: Arc::new(resolver),
+            required: false,
+            seen: Arc::default(),
+        }
+    }
+
+    /// Returns whether unsigned messages are rejected.
+    pub fn required(&self) -> bool {
+        self.required
+    }
+
+    /// Sets whether unsigned messages are rejected.
+    pub fn with_required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Verifies a decrypted, decompressed packet's signature, refusing any whose counter was already accepted for its key
+    /// or is `REPLAY_WINDOW` or more behind the newest one.
+    /// Returns the signature if it is valid, or `None` if the packet is unsigned and signatures are optional.
+    pub fn verify(&self, packet: &Packet) -> Result<Option<Signature>, SignatureError> {
+        let signature = match packet.options().and_then(|options| options.signature()) {
+            Some(signature) => *signature,
+            None if self.required => return Err(SignatureError::Unsigned),
+            None => return Ok(None),
+        };
+        let public = self.resolver.resolve(signature.key_id())
+            .ok_or(SignatureError::UnknownKey(signature.key_id()))?;
+        UnparsedPublicKey::new(&ED25519, public)
+            .verify(&signed_bytes(signature.key_id(), signature.counter(), packet), signature.bytes())
+            .map_err(|_| SignatureError::Invalid)?;
+
+        // Only an authenticated packet is recorded against its key.
+        let mut seen = self.seen.lock().unwrap();
+        match seen.entry(signature.key_id()).or_default().accept(signature.counter()) {
+            true => Ok(Some(signature)),
+            false => Err(SignatureError::Replayed),
+        }
+    }
+}
+
+/// Counters accepted for one key: the newest, and a bitmap of the `REPLAY_WINDOW` counters up to it.
+#[derive(Debug, Default)]
+struct ReplayWindow {
+    newest: Option<u64>,
+    /// Bit `n` is set once counter `newest - n` has been accepted.
+    bits: u64,
+}
+
+impl ReplayWindow {
+    /// Records `counter`, returning `false` if it was already accepted or has fallen out of the window.
+    fn accept(&mut self, counter: u64) -> bool {
+        let newest = match self.newest {
+            Some(newest) => newest,
+            None => {
+                self.newest = Some(counter);
+                self.bits = 1;
+                return true;
+            }
+        };
+        if counter > newest {
+            let shift = counter - newest;
+            self.bits = match shift < REPLAY_WINDOW {
+                true => self.bits << shift | 1,
+                false => 1,
+            };
+            self.newest = Some(counter);
+            return true;
+        }
+        let age = newest - counter;
+        if age >= REPLAY_WINDOW || self.bits & (1 << age) != 0 {
+            return false;
+        }
+        self.bits |= 1 << age;
+        true
+    }
+}
+
+/// Builds the bytes a signature covers: the context, the key id, the counter, the packet type, the chunk fields
+/// (zero when unset, as on the wire), the data kind and the data.
+/// Packets without data are signed as empty text, which is how they are delivered.
+fn signed_bytes(key_id: u32, counter: u64, packet: &Packet) -> Vec<u8> {
+    let (kind, data) = match packet.data() {
+        Some(RawData::Binary(binary)) => (BINARY_DATA, binary.as_slice()),
+        Some(RawData::Text(text)) => (TEXT_DATA, text.as_bytes()),
+        None => (TEXT_DATA, &[][..]),
+    };
+    let options = packet.options().copied().unwrap_or_default();
+    let mut bytes = Vec::with_capacity(CONTEXT.len() + 18 + data.len());
+    bytes.extend_from_slice(CONTEXT);
+    bytes.extend_from_slice(&key_id.to_be_bytes());
+    bytes.extend_from_slice(&counter.to_be_bytes());
+    bytes.push(u8::from(packet._type().clone()));
+    bytes.extend_from_slice(&options.sequence().unwrap_or(0).to_be_bytes());
+    bytes.extend_from_slice(&options.total_chunks().unwrap_or(0).to_be_bytes());
+    bytes.push(kind);
+    bytes.extend_from_slice(data);
+    bytes
+}
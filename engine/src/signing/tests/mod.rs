@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use crate::protocol::{Packet, PacketOptions, PacketType, RawData, Signature};
+use crate::protocol::tests::message;
+use crate::signing::{SignatureConfig, SignatureError, SigningKey, PUBLIC_KEY_LEN, REPLAY_WINDOW};
+
+fn key(key_id: u32) -> SigningKey {
+    SigningKey::from_pkcs8(key_id, &SigningKey::generate_pkcs8().unwrap()).unwrap()
+}
+
+/// Verifies with the public keys of `keys`.
+fn config(keys: &[&SigningKey]) -> SignatureConfig {
+    let keys: HashMap<u32, [u8; PUBLIC_KEY_LEN]> = keys.iter()
+        .map(|key| (key.key_id(), key.public_key()))
+        .collect();
+    SignatureConfig::new(move |key_id| keys.get(&key_id).copied())
+}
+
+/// Returns `packet` with its data replaced, keeping its options.
+fn with_data(packet: &Packet, data: RawData) -> Packet {
+    let mut forged = message(data);
+    forged.with_options(*packet.options().unwrap());
+    forged
+}
+
+#[test]
+fn signed_messages_verify_after_encoding() {
+    let key = key(7);
+    let config = config(&[&key]);
+    for data in [RawData::Text("hello".into()), RawData::Binary(vec![1, 2, 3]), RawData::Text(String::new())] {
+        let signed = key.sign(message(data.clone())).unwrap();
+        let signature = signed.options().and_then(PacketOptions::signature).copied();
+        assert_eq!(signature.map(|signature| signature.key_id()), Some(7));
+        assert_eq!(config.verify(&signed), Ok(signature));
+
+        for binary in [true, false] {
+            let signed = key.sign(message(data.clone())).unwrap();
+            let signature = signed.options().and_then(PacketOptions::signature).copied();
+            let decoded = Packet::decode(signed.encode(binary)).unwrap();
+            assert_eq!(config.verify(&decoded), Ok(signature));
+        }
+    }
+}
+
+#[test]
+fn altered_data_is_rejected() {
+    let key = key(1);
+    let config = config(&[&key]);
+    let signed = key.sign(message(RawData::Text("pay 10".into()))).unwrap();
+    for data in [RawData::Text("pay 99".into()), RawData::Binary(b"pay 10".to_vec()), RawData::Text(String::new())] {
+        assert_eq!(config.verify(&with_data(&signed, data)), Err(SignatureError::Invalid));
+    }
+}
+
+#[test]
+fn signatures_are_bound_to_their_key() {
+    let (first, second) = (key(1), key(2));
+    let both = config(&[&first, &second]);
+    let signed = first.sign(message(RawData::Text("hello".into()))).unwrap();
+    let signature = *signed.options().and_then(PacketOptions::signature).unwrap();
+
+    // Relabelling a valid signature with another known key id does not carry it over.
+    let mut relabelled = message(RawData::Text("hello".into()));
+    relabelled.with_options(PacketOptions::default().with_signature(Signature::new(2, signature.counter(), *signature.bytes())));
+    assert_eq!(both.verify(&relabelled), Err(SignatureError::Invalid));
+
+    let only_second = config(&[&second]);
+    assert_eq!(only_second.verify(&signed), Err(SignatureError::UnknownKey(1)));
+}
+
+#[test]
+fn signatures_cover_type_chunks_and_counter() {
+    let key = key(1);
+    let config = config(&[&key]);
+    let signed = key.sign(message(RawData::Text("hello".into()))).unwrap();
+    let signature = *signed.options().and_then(PacketOptions::signature).unwrap();
+
+    let mut retyped = Packet::new(PacketType::Ping);
+    retyped.with_options(*signed.options().unwrap());
+    retyped.with_data(RawData::Text("hello".into())).unwrap();
+    assert_eq!(config.verify(&retyped), Err(SignatureError::Invalid));
+
+    let mut chunked = signed.clone();
+    chunked.with_options(PacketOptions::new(None, false, Some(1), Some(2)).unwrap().with_signature(signature));
+    assert_eq!(config.verify(&chunked), Err(SignatureError::Invalid));
+
+    let mut recounted = signed.clone();
+    recounted.with_options(PacketOptions::default().with_signature(Signature::new(1, 5, *signature.bytes())));
+    assert_eq!(config.verify(&recounted), Err(SignatureError::Invalid));
+
+    // A rejected packet does not use up its counter.
+    assert!(config.verify(&signed).is_ok());
+}
+
+#[test]
+fn repeated_counters_are_rejected() {
+    let key = key(1);
+    let config = config(&[&key]);
+    let first = key.sign(message(RawData::Text("first".into()))).unwrap();
+    let second = key.sign(message(RawData::Text("second".into()))).unwrap();
+
+    assert!(config.verify(&second).is_ok());
+    assert_eq!(config.verify(&second), Err(SignatureError::Replayed));
+    assert!(config.verify(&first).is_ok());
+    assert_eq!(config.verify(&first), Err(SignatureError::Replayed));
+    // Clones share what was accepted, as sessions sharing a config do.
+    assert_eq!(config.clone().verify(&second), Err(SignatureError::Replayed));
+}
+
+#[test]
+fn late_counters_are_accepted_within_the_window() {
+    let key = key(1);
+    let config = config(&[&key]);
+    let signed: Vec<_> = (0..REPLAY_WINDOW + 2)
+        .map(|n| key.sign(message(RawData::Text(n.to_string()))).unwrap())
+        .collect();
+
+    assert!(config.verify(&signed[1]).is_ok());
+    assert!(config.verify(&signed[0]).is_ok());
+    assert_eq!(config.verify(&signed[0]), Err(SignatureError::Replayed));
+
+    // Once the newest counter is a full window ahead, older ones can no longer be told apart from replays.
+    assert!(config.verify(&signed[REPLAY_WINDOW as usize + 1]).is_ok());
+    assert_eq!(config.verify(&signed[1]), Err(SignatureError::Replayed));
+    assert!(config.verify(&signed[2]).is_ok());
+}
+
+#[test]
+fn reloaded_keys_continue_from_their_counter() {
+    let pkcs8 = SigningKey::generate_pkcs8().unwrap();
+    let key = SigningKey::from_pkcs8(2, &pkcs8).unwrap();
+    let config = config(&[&key]);
+    assert!(config.verify(&key.sign(message(RawData::Text("before".into()))).unwrap()).is_ok());
+    let restarted = SigningKey::from_pkcs8(2, &pkcs8).unwrap();
+    assert_eq!(config.verify(&restarted.sign(message(RawData::Text("again".into()))).unwrap()), Err(SignatureError::Replayed));
+    let restarted = restarted.with_counter(key.counter());
+    assert_eq!(restarted.counter(), 1);
+    assert!(config.verify(&restarted.sign(message(RawData::Text("after".into()))).unwrap()).is_ok());
+}
+
+#[test]
+fn unsigned_messages_pass_unless_required() {
+    let config = config(&[&key(1)]);
+    let unsigned = message(RawData::Text("hello".into()));
+    assert_eq!(config.verify(&unsigned), Ok(None));
+    assert!(!config.required());
+    assert_eq!(config.with_required(true).verify(&unsigned), Err(SignatureError::Unsigned));
+}
+
+#[test]
+fn encoded_packets_cannot_be_signed() {
+    let key = key(1);
+    let mut encrypted = message(RawData::Binary(vec![0; 32]));
+    encrypted.with_options(PacketOptions::default().with_encryption());
+    assert_eq!(key.sign(encrypted).err(), Some(SignatureError::Encoded));
+}
+
+#[test]
+fn invalid_key_material_is_rejected() {
+    assert_eq!(SigningKey::from_pkcs8(1, &[0; 48]).err(), Some(SignatureError::InvalidKey));
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn signatures_survive_compression() {
+    use crate::compression::CompressionConfig;
+    use crate::protocol::Compression;
+
+    let key = key(3);
+    let compression = CompressionConfig::default().with_threshold(0);
+    let signed = key.sign(message(RawData::Text("hello ".repeat(32)))).unwrap();
+    let compressed = compression.compress(Compression::Deflate, signed.clone());
+    assert!(compressed.options().is_some_and(PacketOptions::compress));
+
+    let decompressed = compression.decompress(compressed).unwrap();
+    assert_eq!(decompressed, signed);
+    assert!(config(&[&key]).verify(&decompressed).unwrap().is_some());
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn signatures_survive_encryption() {
+    use crate::encryption::{EncryptionConfig, KeyExchange};
+    use crate::session::Role;
+
+    let encryption = EncryptionConfig::default();
+    let (client, server) = (KeyExchange::new().unwrap(), KeyExchange::new().unwrap());
+    let (client_key, server_key) = (client.public_key(), server.public_key());
+    let mut client = client.agree(&server_key, Role::Client, &encryption).unwrap();
+    let mut server = server.agree(&client_key, Role::Server, &encryption).unwrap();
+
+    let key = key(4);
+    let signed = key.sign(message(RawData::Binary(vec![9; 16]))).unwrap();
+    let opened = server.open(client.seal(&signed).unwrap()).unwrap();
+    assert_eq!(opened, signed);
+    assert!(config(&[&key]).verify(&opened).unwrap().is_some());
+}
@@ -0,0 +1,41 @@
+use std::fmt;
+use std::io;
+
+use crate::protocol::DecodingError;
+
+/// Error type for transport reads and writes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransportError {
+    /// Underlying I/O failed.
+    Io(io::ErrorKind),
+    /// Inbound frame could not be decoded.
+    Decoding(DecodingError),
+    /// Inbound payload exceeds the maximum allowed size.
+    PayloadTooLarge,
+    /// Transport is already closed.
+    Closed,
+}
+
+impl std::error::Error for TransportError {}
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportError::Io(kind) => write!(f, "Transport I/O failed: {}", kind),
+            TransportError::Decoding(e) => write!(f, "Transport frame decoding failed: {}", e),
+            TransportError::PayloadTooLarge => write!(f, "Transport payload exceeds maximum allowed size"),
+            TransportError::Closed => write!(f, "Transport is closed"),
+        }
+    }
+}
+
+impl From<io::Error> for TransportError {
+    fn from(e: io::Error) -> Self {
+        TransportError::Io(e.kind())
+    }
+}
+
+impl From<DecodingError> for TransportError {
+    fn from(e: DecodingError) -> Self {
+        TransportError::Decoding(e)
+    }
+}
@@ -0,0 +1,139 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::{Sink, Stream, StreamExt};
+
+use crate::protocol::{BinaryType, EncodedPacket, Packet, PacketDecoderStream};
+use crate::transport::{Transport, TransportError};
+
+/// Size of each read from the underlying reader.
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+/// Buffered outbound bytes before `poll_ready` forces a flush.
+const WRITE_BUFFER_LIMIT: usize = 64 * 1024;
+
+/// Adapts a reader into a stream of byte chunks for `PacketDecoderStream`.
+/// I/O errors end the stream and are kept for the transport to report.
+#[derive(Debug)]
+struct ReadChunks<R> {
+    inner: R,
+    error: Option<io::ErrorKind>,
+}
+
+impl<R: AsyncRead + Unpin> Stream for ReadChunks<R> {
+    type Item = BinaryType;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut chunk = vec![0u8; READ_CHUNK_SIZE];
+        match ready!(Pin::new(&mut self.inner).poll_read(cx, &mut chunk)) {
+            Ok(0) => Poll::Ready(None),
+            Ok(len) => {
+                chunk.truncate(len);
+                Poll::Ready(Some(chunk))
+            }
+            Err(e) => {
+                self.error = Some(e.kind());
+                Poll::Ready(None)
+            }
+        }
+    }
+}
+
+/// Engine transport over any byte stream, using the length-prefixed stream framing.
+/// Takes `futures::io` halves; tokio streams can be adapted with `tokio_util::compat`.
+#[derive(Debug)]
+pub struct FramedTransport<R, W> {
+    reader: PacketDecoderStream<ReadChunks<R>>,
+    writer: W,
+    write_buffer: BinaryType,
+    name: &'static str,
+}
+
+impl<R, W> FramedTransport<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    /// Wraps the read and write halves of a connected byte stream.
+    pub fn from_parts(reader: R, writer: W, name: &'static str) -> Self {
+        Self {
+            reader: PacketDecoderStream::new(ReadChunks { inner: reader, error: None }),
+            writer,
+            write_buffer: Vec::new(),
+            name,
+        }
+    }
+}
+
+impl<R, W> Transport for FramedTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn supports_binary(&self) -> bool {
+        true
+    }
+
+    fn received_len(&self) -> Option<usize> {
+        Some(self.reader.frame_len())
+    }
+}
+
+impl<R, W> Stream for FramedTransport<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: Unpin,
+{
+    type Item = Result<Packet, TransportError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match ready!(self.reader.poll_next_unpin(cx)) {
+            Some(decoded) => Poll::Ready(Some(decoded.map_err(TransportError::from))),
+            None => Poll::Ready(
+                self.reader.get_mut().error.take().map(|kind| Err(TransportError::Io(kind)))
+            ),
+        }
+    }
+}
+
+impl<R, W> Sink<EncodedPacket> for FramedTransport<R, W>
+where
+    R: Unpin,
+    W: AsyncWrite + Unpin,
+{
+    type Error = TransportError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.write_buffer.len() >= WRITE_BUFFER_LIMIT {
+            return self.poll_flush(cx);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, packet: EncodedPacket) -> Result<(), Self::Error> {
+        packet.write_frame(&mut self.write_buffer);
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = &mut *self;
+        while !this.write_buffer.is_empty() {
+            let written = ready!(Pin::new(&mut this.writer).poll_write(cx, &this.write_buffer))?;
+            if written == 0 {
+                return Poll::Ready(Err(TransportError::Io(io::ErrorKind::WriteZero)));
+            }
+            this.write_buffer.drain(..written);
+        }
+        Poll::Ready(ready!(Pin::new(&mut this.writer).poll_flush(cx)).map_err(TransportError::from))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        Poll::Ready(ready!(Pin::new(&mut self.writer).poll_close(cx)).map_err(TransportError::from))
+    }
+}
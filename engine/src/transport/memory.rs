@@ -0,0 +1,92 @@
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures::channel::mpsc;
+use futures::{Sink, Stream, StreamExt};
+
+use crate::protocol::{EncodedPacket, Packet, RawData};
+use crate::transport::{Transport, TransportError};
+
+/// Default number of in-flight packets per direction.
+const DEFAULT_CAPACITY: usize = 16;
+
+/// In-process transport, one half of a connected pair.
+/// Packets are encoded on send and decoded on receive, so both halves exercise the real codec.
+#[derive(Debug)]
+pub struct MemoryTransport {
+    tx: mpsc::Sender<RawData>,
+    rx: mpsc::Receiver<RawData>,
+    supports_binary: bool,
+    /// Encoded size of the packet last received.
+    received_len: usize,
+}
+
+impl MemoryTransport {
+    /// Creates a connected pair with binary support and the default capacity.
+    pub fn pair() -> (Self, Self) {
+        Self::pair_with(DEFAULT_CAPACITY, true)
+    }
+
+    /// Creates a connected pair.
+    /// `capacity` bounds the packets buffered in each direction before `send` waits;
+    /// without `supports_binary` packets cross the pair in their text encoding.
+    pub fn pair_with(capacity: usize, supports_binary: bool) -> (Self, Self) {
+        let (a_tx, b_rx) = mpsc::channel(capacity);
+        let (b_tx, a_rx) = mpsc::channel(capacity);
+        (
+            Self { tx: a_tx, rx: a_rx, supports_binary, received_len: 0 },
+            Self { tx: b_tx, rx: b_rx, supports_binary, received_len: 0 },
+        )
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn supports_binary(&self) -> bool {
+        self.supports_binary
+    }
+
+    fn received_len(&self) -> Option<usize> {
+        Some(self.received_len)
+    }
+}
+
+impl Stream for MemoryTransport {
+    type Item = Result<Packet, TransportError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let encoded = ready!(self.rx.poll_next_unpin(cx));
+        if let Some(raw) = &encoded {
+            self.received_len = raw.len();
+        }
+        Poll::Ready(encoded.map(|raw| Packet::decode(raw).map_err(TransportError::from)))
+    }
+}
+
+impl Sink<EncodedPacket> for MemoryTransport {
+    type Error = TransportError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.tx.poll_ready(cx).map_err(|_| TransportError::Closed)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, packet: EncodedPacket) -> Result<(), Self::Error> {
+        let encoded = match self.supports_binary {
+            true => RawData::Binary(packet.binary().to_vec()),
+            false => RawData::Text(packet.text().to_owned()),
+        };
+        self.tx.start_send(encoded).map_err(|_| TransportError::Closed)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.tx.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
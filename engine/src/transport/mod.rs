@@ -0,0 +1,56 @@
+pub mod memory;
+pub mod polling;
+#[cfg(feature = "quic")]
+pub mod quic;
+#[cfg(unix)]
+pub mod unix;
+
+mod error;
+mod framed;
+
+#[cfg(test)]
+mod tests;
+
+use futures::{Sink, Stream};
+
+use crate::protocol::{EncodedPacket, Packet};
+
+pub use error::TransportError;
+pub use framed::FramedTransport;
+
+/// A bidirectional packet transport.
+/// Inbound packets are read from the `Stream` half, outbound packets are written to the `Sink` half
+/// already wrapped in an `EncodedPacket`, so a broadcast is encoded once for every session.
+pub trait Transport:
+    Stream<Item = Result<Packet, TransportError>>
+    + Sink<EncodedPacket, Error = TransportError>
+    + Send
+    + Unpin
+    + 'static
+{
+    /// Returns the transport name, as advertised in `Handshake.upgrades`.
+    fn name(&self) -> &'static str;
+
+    /// Returns whether the transport carries binary frames natively.
+    fn supports_binary(&self) -> bool;
+
+    /// Returns how many bytes the packet last read from the stream took up on the wire, framing included.
+    /// Transports that cannot tell return `None`.
+    fn received_len(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn supports_binary(&self) -> bool {
+        (**self).supports_binary()
+    }
+
+    fn received_len(&self) -> Option<usize> {
+        (**self).received_len()
+    }
+}
@@ -0,0 +1,269 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use futures::channel::mpsc::{self, TryRecvError};
+use futures::{Sink, Stream, StreamExt};
+use tokio::sync::Mutex;
+
+use crate::protocol::{DecodingError, EncodedPacket, Packet, PacketType, PayloadBuilder, RawData};
+use crate::rt;
+use crate::transport::{Transport, TransportError};
+
+/// Outbound packets the transport holds before the session's send buffer takes the backpressure.
+const OUTBOUND_CAPACITY: usize = 64;
+
+/// Long-polling settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollingConfig {
+    /// Maximum encoded size of a single response or request payload.
+    max_payload: usize,
+    /// Time to wait after the first queued packet so a burst can share one response.
+    batch_delay: Duration,
+    /// Whether payloads use the binary encoding.
+    supports_binary: bool,
+    /// Whether payloads use the Engine.IO v4 encoding instead of the engine's own; v4 payloads are always text.
+    v4: bool,
+}
+
+impl Default for PollingConfig {
+    fn default() -> Self {
+        Self {
+            max_payload: 1_000_000,
+            batch_delay: Duration::ZERO,
+            supports_binary: false,
+            v4: false,
+        }
+    }
+}
+
+impl PollingConfig {
+    /// Returns the maximum payload size in bytes.
+    pub fn max_payload(&self) -> usize {
+        self.max_payload
+    }
+
+    /// Sets the maximum payload size in bytes.
+    pub fn with_max_payload(mut self, max_payload: usize) -> Self {
+        self.max_payload = max_payload;
+        self
+    }
+
+    /// Returns the micro-batching delay.
+    pub fn batch_delay(&self) -> Duration {
+        self.batch_delay
+    }
+
+    /// Sets the micro-batching delay.
+    pub fn with_batch_delay(mut self, batch_delay: Duration) -> Self {
+        self.batch_delay = batch_delay;
+        self
+    }
+
+    /// Returns whether payloads use the binary encoding.
+    pub fn supports_binary(&self) -> bool {
+        self.supports_binary
+    }
+
+    /// Sets whether payloads use the binary encoding.
+    pub fn with_binary(mut self, supports_binary: bool) -> Self {
+        self.supports_binary = supports_binary;
+        self
+    }
+
+    /// Returns whether payloads use the Engine.IO v4 encoding.
+    pub fn v4(&self) -> bool {
+        self.v4
+    }
+
+    /// Sets whether payloads use the Engine.IO v4 encoding, which has no room for packet options.
+    pub fn with_v4(mut self, v4: bool) -> Self {
+        self.v4 = v4;
+        self
+    }
+}
+
+/// An inbound packet, or the error that ended the inbound side, with the bytes it took up in its POST body.
+type Inbound = (Result<Packet, TransportError>, usize);
+
+/// Session side of a long-polling transport.
+/// Outbound packets wait here until the HTTP layer collects them through the paired `PollingHandle`.
+#[derive(Debug)]
+pub struct PollingTransport {
+    outbound: mpsc::Sender<EncodedPacket>,
+    inbound: mpsc::UnboundedReceiver<Inbound>,
+    supports_binary: bool,
+    /// Size of the packet last received.
+    received_len: usize,
+}
+
+#[derive(Debug)]
+struct Outbound {
+    queue: mpsc::Receiver<EncodedPacket>,
+    /// A packet that did not fit in the previous response.
+    held: Option<EncodedPacket>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    config: PollingConfig,
+    outbound: Mutex<Outbound>,
+    inbound: mpsc::UnboundedSender<Inbound>,
+}
+
+/// HTTP side of a long-polling transport: answers GET and POST requests for one session.
+#[derive(Debug, Clone)]
+pub struct PollingHandle {
+    shared: Arc<Shared>,
+}
+
+impl PollingTransport {
+    /// Creates a transport and the handle the HTTP layer uses to drive it.
+    pub fn new(config: PollingConfig) -> (Self, PollingHandle) {
+        let (outbound_tx, outbound_rx) = mpsc::channel(OUTBOUND_CAPACITY);
+        let (inbound_tx, inbound_rx) = mpsc::unbounded();
+        let transport = Self {
+            outbound: outbound_tx,
+            inbound: inbound_rx,
+            supports_binary: config.supports_binary && !config.v4,
+            received_len: 0,
+        };
+        let handle = PollingHandle {
+            shared: Arc::new(Shared {
+                config,
+                outbound: Mutex::new(Outbound { queue: outbound_rx, held: None }),
+                inbound: inbound_tx,
+            }),
+        };
+        (transport, handle)
+    }
+}
+
+impl PollingHandle {
+    /// Returns the polling settings.
+    pub fn config(&self) -> &PollingConfig {
+        &self.shared.config
+    }
+
+    /// Answers a GET request: waits for outbound packets and coalesces them into one payload.
+    /// Packets beyond `max_payload` are kept for the next request; a `Noop` is only sent when nothing else is queued.
+    /// Fails once the session has ended; a request already waiting at that point gets a `Noop` instead.
+    pub async fn poll(&self) -> Result<RawData, TransportError> {
+        let config = self.shared.config;
+        let mut outbound = self.shared.outbound.lock().await;
+
+        if outbound.held.is_none() {
+            let first = match outbound.queue.try_recv() {
+                Ok(packet) => packet,
+                Err(TryRecvError::Closed) => return Err(TransportError::Closed),
+                // A request still waiting when the session ends is released with a `Noop`.
+                Err(TryRecvError::Empty) => outbound.queue.next().await
+                    .unwrap_or_else(|| Packet::new(PacketType::Noop).into()),
+            };
+            outbound.held = Some(first);
+        }
+        // The first packet stays in `held` through the delay, so a request dropped meanwhile leaves it for the next.
+        if !config.batch_delay.is_zero() {
+            rt::sleep(config.batch_delay).await;
+        }
+
+        let mut payload = match config.v4 {
+            true => PayloadBuilder::v4(config.max_payload),
+            false => PayloadBuilder::new(config.supports_binary, config.max_payload),
+        };
+        let mut noop = false;
+        let mut next = outbound.held.take();
+        while let Some(packet) = next.take().or_else(|| outbound.queue.try_recv().ok()) {
+            if packet._type() == &PacketType::Noop {
+                noop = true;
+                continue;
+            }
+            if let Err(packet) = payload.try_push_encoded(packet) {
+                outbound.held = Some(packet);
+                break;
+            }
+        }
+        if payload.is_empty() && noop {
+            payload.try_push(Packet::new(PacketType::Noop)).ok();
+        }
+        Ok(payload.finish())
+    }
+
+    /// Accepts a POST request body and hands its packets to the session.
+    pub fn post(&self, body: RawData) -> Result<(), TransportError> {
+        if body.len() > self.shared.config.max_payload {
+            return Err(TransportError::PayloadTooLarge);
+        }
+        let len = body.len();
+        let decoded = match (self.shared.config.v4, body) {
+            (true, RawData::Text(text)) => Packet::decode_payload_v4_sized(&text),
+            (true, RawData::Binary(_)) => Err(DecodingError::InvalidFormat),
+            (false, body) => Packet::decode_payload_sized(body),
+        };
+        let packets = match decoded {
+            Ok(packets) => packets,
+            Err(e) => {
+                let _ = self.shared.inbound.unbounded_send((Err(TransportError::Decoding(e.clone())), len));
+                return Err(TransportError::Decoding(e));
+            }
+        };
+        for (packet, len) in packets {
+            self.shared.inbound.unbounded_send((Ok(packet), len))
+                .map_err(|_| TransportError::Closed)?;
+        }
+        Ok(())
+    }
+
+    /// Ends the inbound side, as when the client goes away.
+    pub fn close(&self) {
+        self.shared.inbound.close_channel();
+    }
+}
+
+impl Transport for PollingTransport {
+    fn name(&self) -> &'static str {
+        "polling"
+    }
+
+    fn supports_binary(&self) -> bool {
+        self.supports_binary
+    }
+
+    fn received_len(&self) -> Option<usize> {
+        Some(self.received_len)
+    }
+}
+
+impl Stream for PollingTransport {
+    type Item = Result<Packet, TransportError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let inbound = ready!(self.inbound.poll_next_unpin(cx));
+        Poll::Ready(inbound.map(|(packet, len)| {
+            self.received_len = len;
+            packet
+        }))
+    }
+}
+
+impl Sink<EncodedPacket> for PollingTransport {
+    type Error = TransportError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.outbound.poll_ready(cx).map_err(|_| TransportError::Closed)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, packet: EncodedPacket) -> Result<(), Self::Error> {
+        self.outbound.start_send(packet).map_err(|_| TransportError::Closed)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.outbound.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
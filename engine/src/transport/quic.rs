@@ -0,0 +1,209 @@
+//! Experimental QUIC transport.
+//!
+//! Each session runs over one bidirectional QUIC stream with the same length-prefixed framing
+//! as the Unix transport, so a lost datagram only stalls the session it belongs to.
+//! Engine packets must arrive in order, so unreliable QUIC datagrams are not used.
+//!
+//! The client's first packet on a stream is an `Open`: without data it asks for a fresh session, and with
+//! `{"sid":".."}` it asks to upgrade that session, as a WebSocket connection does with its `sid` query.
+//! Servers advertise the transport by listing `"quic"` in `SessionConfig::with_upgrades`; a listener given the
+//! `EngineServer` with `QuicListener::with_server` moves its polling sessions over once the client has probed the stream.
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use quinn::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use quinn::rustls::RootCertStore;
+use quinn::{ClientConfig, Endpoint, RecvStream, SendStream, ServerConfig};
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::{Packet, PacketType, RawData};
+use crate::rt;
+use crate::server::EngineServer;
+use crate::session::{Session, SessionConfig, SessionError};
+use crate::transport::{FramedTransport, TransportError};
+
+/// Transport name, as advertised in `Handshake.upgrades`.
+pub const QUIC_TRANSPORT: &str = "quic";
+
+/// Engine transport over a bidirectional QUIC stream.
+pub type QuicTransport = FramedTransport<RecvStream, SendStream>;
+
+impl QuicTransport {
+    /// Wraps the halves of a bidirectional QUIC stream.
+    pub fn new(send: SendStream, recv: RecvStream) -> Self {
+        Self::from_parts(recv, send, QUIC_TRANSPORT)
+    }
+}
+
+/// Data of the `Open` packet that asks to upgrade an existing session.
+#[derive(Debug, Serialize, Deserialize)]
+struct UpgradeRequest {
+    sid: String,
+}
+
+/// Maps QUIC connection failures onto transport I/O errors.
+fn quic_error<E: Into<io::Error>>(e: E) -> SessionError {
+    SessionError::Transport(TransportError::from(e.into()))
+}
+
+/// Accepts engine sessions over QUIC.
+#[derive(Debug)]
+pub struct QuicListener {
+    endpoint: Endpoint,
+    config: SessionConfig,
+    /// Server whose sessions may upgrade to QUIC; upgrade requests are refused without one.
+    server: Option<Arc<EngineServer>>,
+}
+
+impl QuicListener {
+    /// Binds a listener with the given TLS server configuration.
+    pub fn bind(addr: SocketAddr, server_config: ServerConfig, config: SessionConfig) -> io::Result<Self> {
+        Ok(Self {
+            endpoint: Endpoint::server(server_config, addr)?,
+            config,
+            server: None,
+        })
+    }
+
+    /// Binds a listener with a freshly generated self-signed certificate for `localhost`.
+    /// Returns the certificate so local clients can trust it. Intended for testing only.
+    pub fn bind_self_signed(addr: SocketAddr, config: SessionConfig) -> io::Result<(Self, CertificateDer<'static>)> {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".into()])
+            .map_err(io::Error::other)?;
+        let cert = certified.cert.der().clone();
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.signing_key.serialize_der()));
+
+        let server_config = ServerConfig::with_single_cert(vec![cert.clone()], key)
+            .map_err(io::Error::other)?;
+        Ok((Self::bind(addr, server_config, config)?, cert))
+    }
+
+    /// Returns the local address the listener is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.endpoint.local_addr()
+    }
+
+    /// Returns the settings advertised to accepted sessions.
+    pub fn config(&self) -> &SessionConfig {
+        &self.config
+    }
+
+    /// Lets `server`'s sessions upgrade to QUIC through this listener.
+    pub fn with_server(mut self, server: Arc<EngineServer>) -> Self {
+        self.server = Some(server);
+        self
+    }
+
+    /// Waits for a connection asking for a fresh session, then completes the server side of the handshake.
+    /// Connections asking to upgrade a session are handed to the server in the background.
+    pub async fn accept(&self) -> Result<Session, SessionError> {
+        loop {
+            let incoming = self.endpoint.accept().await
+                .ok_or(SessionError::Closed)?;
+            let connection = incoming.await.map_err(quic_error)?;
+            let (send, recv) = connection.accept_bi().await.map_err(quic_error)?;
+            let mut transport = QuicTransport::new(send, recv);
+
+            let ping_timeout = self.config.ping_timeout();
+            let opening = match rt::timeout(ping_timeout, transport.next()).await {
+                Some(Some(Ok(packet))) if packet._type() == &PacketType::Open => packet,
+                _ => {
+                    debug!("dropped QUIC stream without an opening packet");
+                    continue;
+                }
+            };
+            let request = match opening.data() {
+                None => return Session::accept(transport, &self.config).await,
+                Some(RawData::Text(json)) => serde_json::from_str::<UpgradeRequest>(json).ok(),
+                Some(RawData::Binary(_)) => None,
+            };
+            match (request, &self.server) {
+                (Some(request), Some(server)) => {
+                    let server = server.clone();
+                    rt::spawn(async move {
+                        if server.upgrade(&request.sid, transport).await.is_err() {
+                            debug!("QUIC upgrade failed");
+                        }
+                    });
+                }
+                _ => {
+                    debug!("refused QUIC upgrade request");
+                }
+            }
+        }
+    }
+
+    /// Stops accepting connections and closes those still open.
+    pub fn close(&self) {
+        self.endpoint.close(0u32.into(), b"server closed");
+    }
+}
+
+/// Opens engine sessions to a QUIC listener.
+#[derive(Debug)]
+pub struct QuicConnector {
+    endpoint: Endpoint,
+    server_addr: SocketAddr,
+    server_name: String,
+}
+
+impl QuicConnector {
+    /// Creates a connector with the given TLS client configuration.
+    pub fn new(server_addr: SocketAddr, server_name: &str, client_config: ClientConfig) -> io::Result<Self> {
+        let bind_addr: SocketAddr = match server_addr {
+            SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+            SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+        };
+        let mut endpoint = Endpoint::client(bind_addr)?;
+        endpoint.set_default_client_config(client_config);
+        Ok(Self {
+            endpoint,
+            server_addr,
+            server_name: server_name.to_string(),
+        })
+    }
+
+    /// Creates a connector that trusts only the given certificate, e.g. one from `QuicListener::bind_self_signed`.
+    pub fn with_trusted_cert(server_addr: SocketAddr, server_name: &str, cert: CertificateDer<'static>) -> io::Result<Self> {
+        let mut roots = RootCertStore::empty();
+        roots.add(cert).map_err(io::Error::other)?;
+        let client_config = ClientConfig::with_root_certificates(Arc::new(roots))
+            .map_err(io::Error::other)?;
+        Self::new(server_addr, server_name, client_config)
+    }
+
+    /// Connects and completes the client side of the handshake.
+    pub async fn connect(&self) -> Result<Session, SessionError> {
+        Session::connect(self.open(Packet::new(PacketType::Open)).await?).await
+    }
+
+    /// Moves `session` onto a new QUIC stream. The server must list `"quic"` in its upgrades.
+    pub async fn upgrade(&self, session: &Session) -> Result<(), SessionError> {
+        session.upgrade(self.open_upgrade(session.sid()).await?).await
+    }
+
+    /// Opens a stream asking to upgrade the session `sid`, for a client that probes it itself.
+    pub async fn open_upgrade(&self, sid: &str) -> Result<QuicTransport, SessionError> {
+        let request = UpgradeRequest { sid: sid.to_string() };
+        let mut opening = Packet::new(PacketType::Open);
+        opening.with_data(RawData::Text(serde_json::to_string(&request).unwrap_or_default()))
+            .map_err(SessionError::Packet)?;
+        self.open(opening).await
+    }
+
+    /// Connects and opens a stream with `opening`.
+    /// QUIC streams are only announced to the peer once data is written, so the opening packet goes first.
+    async fn open(&self, opening: Packet) -> Result<QuicTransport, SessionError> {
+        let connecting = self.endpoint.connect(self.server_addr, &self.server_name)
+            .map_err(|e| quic_error(io::Error::other(e)))?;
+        let connection = connecting.await.map_err(quic_error)?;
+        let (send, recv) = connection.open_bi().await.map_err(quic_error)?;
+
+        let mut transport = QuicTransport::new(send, recv);
+        transport.send(opening.into()).await?;
+        Ok(transport)
+    }
+}
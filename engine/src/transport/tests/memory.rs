@@ -0,0 +1,67 @@
+use futures::{SinkExt, StreamExt};
+
+use crate::protocol::{Packet, PacketType, RawData};
+use crate::protocol::tests::message;
+use crate::session::{CloseReason, Session, SessionConfig, SessionEvent};
+use crate::transport::memory::MemoryTransport;
+use crate::transport::{Transport, TransportError};
+
+#[tokio::test]
+async fn pair_delivers_packets_both_ways() {
+    let (mut a, mut b) = MemoryTransport::pair();
+    a.send(message(RawData::Text("ping from a".into())).into()).await.unwrap();
+    b.send(message(RawData::Binary(vec![1, 2, 3])).into()).await.unwrap();
+
+    assert_eq!(b.next().await, Some(Ok(message(RawData::Text("ping from a".into())))));
+    assert_eq!(a.next().await, Some(Ok(message(RawData::Binary(vec![1, 2, 3])))));
+}
+
+#[tokio::test]
+async fn text_only_pair_round_trips_binary_data() {
+    let (mut a, mut b) = MemoryTransport::pair_with(4, false);
+    assert!(!a.supports_binary());
+    assert_eq!(a.name(), "memory");
+
+    let packet = message(RawData::Binary(vec![0xfb, 0xff, 0xbf, 0x00]));
+    a.send(packet.clone().into()).await.unwrap();
+    assert_eq!(b.next().await, Some(Ok(packet)));
+}
+
+#[tokio::test]
+async fn capacity_bounds_unread_packets() {
+    let (mut a, _b) = MemoryTransport::pair_with(1, true);
+    // futures mpsc reserves one extra slot per sender.
+    a.feed(Packet::new(PacketType::Noop).into()).await.unwrap();
+    a.feed(Packet::new(PacketType::Noop).into()).await.unwrap();
+
+    let third = tokio::time::timeout(
+        std::time::Duration::from_millis(50),
+        a.feed(Packet::new(PacketType::Noop).into()),
+    ).await;
+    assert!(third.is_err());
+}
+
+#[tokio::test]
+async fn dropping_one_half_ends_the_other() {
+    let (mut a, b) = MemoryTransport::pair();
+    drop(b);
+    assert_eq!(a.next().await, None);
+    assert_eq!(a.send(Packet::new(PacketType::Noop).into()).await, Err(TransportError::Closed));
+}
+
+#[tokio::test]
+async fn sessions_end_to_end() {
+    let (server, client) = MemoryTransport::pair_with(8, false);
+    let config = SessionConfig::default();
+    let (server, client) = tokio::join!(Session::accept(server, &config), Session::connect(client));
+    let (mut server, mut client) = (server.unwrap(), client.unwrap());
+
+    assert_eq!(server.sid(), client.sid());
+    assert_eq!(client.transport(), "memory");
+
+    client.send(RawData::Text("hello".into())).await.unwrap();
+    assert_eq!(server.recv().await, Some(SessionEvent::Message(RawData::Text("hello".into()))));
+
+    server.close();
+    assert_eq!(client.recv().await, Some(SessionEvent::Close(CloseReason::TransportClose)));
+}
@@ -0,0 +1,11 @@
+#[cfg(test)]
+mod memory;
+
+#[cfg(test)]
+mod polling;
+
+#[cfg(all(test, unix))]
+mod unix;
+
+#[cfg(all(test, feature = "quic"))]
+mod quic;
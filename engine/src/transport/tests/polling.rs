@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use futures::{FutureExt, SinkExt, StreamExt};
+
+use crate::protocol::{Handshake, Packet, PacketType, RawData};
+use crate::protocol::tests::text_message;
+use crate::session::{CloseReason, Session, SessionConfig, SessionEvent};
+use crate::transport::polling::{PollingConfig, PollingTransport};
+use crate::transport::TransportError;
+
+#[tokio::test]
+async fn queued_packets_share_one_response() {
+    let (mut transport, handle) = PollingTransport::new(PollingConfig::default());
+    for text in ["a", "b", "c"] {
+        transport.send(text_message(text).into()).await.unwrap();
+    }
+    let response = handle.poll().await.unwrap();
+    assert_eq!(response, Packet::encode_payload(vec![text_message("a"), text_message("b"), text_message("c")], false));
+}
+
+#[tokio::test]
+async fn v4_payloads_use_record_separators() {
+    let (mut transport, handle) = PollingTransport::new(PollingConfig::default().with_v4(true).with_binary(true));
+    transport.send(text_message("a").into()).await.unwrap();
+    let mut binary = Packet::new(PacketType::Message);
+    binary.with_data(RawData::Binary(vec![1, 2, 3, 4])).unwrap();
+    transport.send(binary.clone().into()).await.unwrap();
+    assert_eq!(handle.poll().await.unwrap(), RawData::Text("4a\x1ebAQIDBA==".into()));
+
+    handle.post(RawData::Text("4b\x1e3".into())).unwrap();
+    assert_eq!(transport.next().await.unwrap().unwrap(), text_message("b"));
+    assert_eq!(transport.next().await.unwrap().unwrap(), Packet::new(PacketType::Pong));
+    assert!(handle.post(RawData::Binary(vec![4])).is_err());
+}
+
+#[tokio::test]
+async fn responses_split_at_max_payload() {
+    // Each packet is 8 (prefix) + 5 (header) + 1 (data) bytes.
+    let config = PollingConfig::default().with_max_payload(30).with_binary(false);
+    let (mut transport, handle) = PollingTransport::new(config);
+    for text in ["a", "b", "c"] {
+        transport.send(text_message(text).into()).await.unwrap();
+    }
+    let first = Packet::decode_payload(handle.poll().await.unwrap()).unwrap();
+    let second = Packet::decode_payload(handle.poll().await.unwrap()).unwrap();
+    assert_eq!(first, vec![text_message("a"), text_message("b")]);
+    assert_eq!(second, vec![text_message("c")]);
+}
+
+#[tokio::test]
+async fn batch_delay_combines_burst() {
+    let config = PollingConfig::default().with_batch_delay(Duration::from_millis(50));
+    let (mut transport, handle) = PollingTransport::new(config);
+
+    let poll = tokio::spawn(async move { handle.poll().await });
+    transport.send(text_message("a").into()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    transport.send(text_message("b").into()).await.unwrap();
+
+    let response = Packet::decode_payload(poll.await.unwrap().unwrap()).unwrap();
+    assert_eq!(response, vec![text_message("a"), text_message("b")]);
+}
+
+#[tokio::test]
+async fn poll_dropped_during_batch_delay_keeps_packets() {
+    let config = PollingConfig::default().with_batch_delay(Duration::from_millis(50));
+    let (mut transport, handle) = PollingTransport::new(config);
+    transport.send(text_message("a").into()).await.unwrap();
+
+    let dropped = tokio::time::timeout(Duration::from_millis(10), handle.poll()).await;
+    assert!(dropped.is_err());
+    let response = Packet::decode_payload(handle.poll().await.unwrap()).unwrap();
+    assert_eq!(response, vec![text_message("a")]);
+}
+
+#[tokio::test]
+async fn noop_held_back_when_packets_queued() {
+    let (mut transport, handle) = PollingTransport::new(PollingConfig::default());
+    transport.send(Packet::new(PacketType::Noop).into()).await.unwrap();
+    transport.send(text_message("a").into()).await.unwrap();
+    transport.send(Packet::new(PacketType::Noop).into()).await.unwrap();
+
+    let response = Packet::decode_payload(handle.poll().await.unwrap()).unwrap();
+    assert_eq!(response, vec![text_message("a")]);
+}
+
+#[tokio::test]
+async fn lone_noop_is_sent() {
+    let config = PollingConfig::default().with_binary(true);
+    let (mut transport, handle) = PollingTransport::new(config);
+    transport.send(Packet::new(PacketType::Noop).into()).await.unwrap();
+    transport.send(Packet::new(PacketType::Noop).into()).await.unwrap();
+
+    let response = handle.poll().await.unwrap();
+    assert_eq!(response, Packet::encode_payload(vec![Packet::new(PacketType::Noop)], true));
+}
+
+#[tokio::test]
+async fn post_delivers_packets() {
+    let (mut transport, handle) = PollingTransport::new(PollingConfig::default());
+    handle.post(Packet::encode_payload(vec![text_message("a"), text_message("b")], false)).unwrap();
+    assert_eq!(transport.next().await, Some(Ok(text_message("a"))));
+    assert_eq!(transport.next().await, Some(Ok(text_message("b"))));
+
+    handle.close();
+    assert_eq!(transport.next().await, None);
+}
+
+#[tokio::test]
+async fn post_rejects_oversized_body() {
+    let config = PollingConfig::default().with_max_payload(10);
+    let (_transport, handle) = PollingTransport::new(config);
+    let body = Packet::encode_payload(vec![text_message("too long for the limit")], false);
+    assert_eq!(handle.post(body), Err(TransportError::PayloadTooLarge));
+}
+
+#[tokio::test]
+async fn poll_after_close_fails() {
+    let (mut transport, handle) = PollingTransport::new(PollingConfig::default());
+    transport.close().await.unwrap();
+    assert_eq!(handle.poll().await, Err(TransportError::Closed));
+}
+
+#[tokio::test]
+async fn pending_poll_gets_noop_on_close() {
+    let (mut transport, handle) = PollingTransport::new(PollingConfig::default());
+    let mut poll = Box::pin(handle.poll());
+    assert!((&mut poll).now_or_never().is_none());
+    transport.close().await.unwrap();
+
+    let response = poll.await.unwrap();
+    assert_eq!(response, Packet::encode_payload(vec![Packet::new(PacketType::Noop)], false));
+    assert_eq!(handle.poll().await, Err(TransportError::Closed));
+}
+
+#[tokio::test]
+async fn session_over_polling() {
+    let (transport, handle) = PollingTransport::new(PollingConfig::default());
+    let mut session = Session::accept(transport, &SessionConfig::default()).await.unwrap();
+
+    let open = Packet::decode_payload(handle.poll().await.unwrap()).unwrap();
+    let handshake = Handshake::from_packet(&open[0]).unwrap();
+    assert_eq!(handshake.sid(), session.sid());
+
+    handle.post(Packet::encode_payload(vec![text_message("hi")], false)).unwrap();
+    assert_eq!(session.recv().await, Some(SessionEvent::Message(RawData::Text("hi".into()))));
+
+    session.send(RawData::Text("one".into())).await.unwrap();
+    session.send(RawData::Text("two".into())).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let response = Packet::decode_payload(handle.poll().await.unwrap()).unwrap();
+    assert_eq!(response, vec![text_message("one"), text_message("two")]);
+}
+
+#[tokio::test]
+async fn malformed_post_closes_session() {
+    let (transport, handle) = PollingTransport::new(PollingConfig::default());
+    let mut session = Session::accept(transport, &SessionConfig::default()).await.unwrap();
+
+    assert!(handle.post(RawData::Text("garbage".into())).is_err());
+    assert_eq!(session.recv().await, Some(SessionEvent::Close(CloseReason::ParseError)));
+}
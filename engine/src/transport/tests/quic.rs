@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use http::Request;
+
+use crate::protocol::{Handshake, Packet, PacketType, RawData};
+use crate::server::EngineServer;
+use crate::session::{SessionConfig, SessionEvent, TransitionReason};
+use crate::transport::quic::{QuicConnector, QuicListener, QUIC_TRANSPORT};
+
+#[tokio::test]
+async fn self_signed_session_end_to_end() {
+    let config = SessionConfig::default().with_upgrades(vec![QUIC_TRANSPORT.into()]);
+    let (listener, cert) = QuicListener::bind_self_signed(([127, 0, 0, 1], 0).into(), config).unwrap();
+    let connector = QuicConnector::with_trusted_cert(listener.local_addr().unwrap(), "localhost", cert).unwrap();
+
+    let (server, client) = tokio::join!(listener.accept(), connector.connect());
+    let (mut server, mut client) = (server.unwrap(), client.unwrap());
+
+    assert_eq!(server.sid(), client.sid());
+    assert_eq!(client.transport(), QUIC_TRANSPORT);
+    assert_eq!(client.handshake().upgrades(), &[QUIC_TRANSPORT.to_string()]);
+
+    client.send(RawData::Binary(vec![4; 10_000])).await.unwrap();
+    assert_eq!(server.recv().await, Some(SessionEvent::Message(RawData::Binary(vec![4; 10_000]))));
+    server.send(RawData::Text("pong".into())).await.unwrap();
+    assert_eq!(client.recv().await, Some(SessionEvent::Message(RawData::Text("pong".into()))));
+}
+
+#[tokio::test]
+async fn untrusted_certificate_is_rejected() {
+    let (listener, _) = QuicListener::bind_self_signed(([127, 0, 0, 1], 0).into(), SessionConfig::default()).unwrap();
+    let (_, other_cert) = QuicListener::bind_self_signed(([127, 0, 0, 1], 0).into(), SessionConfig::default()).unwrap();
+    let connector = QuicConnector::with_trusted_cert(listener.local_addr().unwrap(), "localhost", other_cert).unwrap();
+
+    let accept = tokio::spawn(async move { listener.accept().await.map(|_| ()) });
+    assert!(connector.connect().await.is_err());
+    accept.abort();
+}
+
+#[tokio::test]
+async fn polling_session_upgrades_to_quic() {
+    let config = SessionConfig::default().with_upgrades(vec![QUIC_TRANSPORT.into()]);
+    let server = Arc::new(EngineServer::new(config.clone()));
+    let (listener, cert) = QuicListener::bind_self_signed(([127, 0, 0, 1], 0).into(), config).unwrap();
+    let listener = listener.with_server(server.clone());
+    let connector = QuicConnector::with_trusted_cert(listener.local_addr().unwrap(), "localhost", cert).unwrap();
+    let accept = tokio::spawn(async move { listener.accept().await.map(|_| ()) });
+
+    let request = Request::builder().uri("/engine.io/?EIO=4&transport=polling").body(Bytes::new()).unwrap();
+    let response = server.handle(request).await;
+    let packets = Packet::decode_payload_v4(std::str::from_utf8(response.body()).unwrap()).unwrap();
+    let handshake = Handshake::from_packet(&packets[0]).unwrap();
+    let mut session = server.accept().await.unwrap();
+
+    // Probe the stream the way a client does, then move over.
+    let probe = |packet_type| {
+        let mut packet = Packet::new(packet_type);
+        packet.with_data(RawData::Text("probe".into())).unwrap();
+        packet
+    };
+    let mut transport = connector.open_upgrade(handshake.sid()).await.unwrap();
+    transport.send(probe(PacketType::Ping).into()).await.unwrap();
+    assert_eq!(transport.next().await.unwrap().unwrap(), probe(PacketType::Pong));
+    transport.send(Packet::new(PacketType::Upgrade).into()).await.unwrap();
+    while let Some(event) = session.recv_lifecycle().await {
+        if event.reason() == TransitionReason::UpgradeCompleted {
+            break;
+        }
+    }
+
+    session.send(RawData::Text("over quic".into())).await.unwrap();
+    let received = transport.next().await.unwrap().unwrap();
+    assert_eq!(received.data(), Some(&RawData::Text("over quic".into())));
+    assert_eq!(session.transport(), QUIC_TRANSPORT);
+
+    let mut message = Packet::new(PacketType::Message);
+    message.with_data(RawData::Binary(vec![7; 64])).unwrap();
+    transport.send(message.into()).await.unwrap();
+    assert_eq!(session.recv().await, Some(SessionEvent::Message(RawData::Binary(vec![7; 64]))));
+    accept.abort();
+}
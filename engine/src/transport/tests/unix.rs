@@ -0,0 +1,101 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::protocol::RawData;
+use crate::session::{CloseReason, Session, SessionConfig, SessionEvent};
+use crate::transport::memory::MemoryTransport;
+use crate::transport::unix::{UnixConnector, UnixListener, UnixTransport};
+
+fn socket_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("green-engine-{}-{}.sock", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[tokio::test]
+async fn handshake_over_socket() {
+    let path = socket_path("handshake");
+    let config = SessionConfig::default()
+        .with_ping_interval(Duration::from_millis(1_000))
+        .with_max_payload(4096);
+    let listener = UnixListener::bind(&path, config).unwrap();
+
+    let connector = UnixConnector::new(&path);
+    let (server, client) = tokio::join!(listener.accept(), connector.connect());
+    let (server, client) = (server.unwrap(), client.unwrap());
+
+    assert_eq!(server.sid(), client.sid());
+    assert_eq!(client.handshake(), server.handshake());
+    assert_eq!(client.handshake().ping_interval(), 1_000);
+    assert_eq!(client.handshake().max_payload(), 4096);
+    assert_eq!(client.transport(), "unix");
+    std::fs::remove_file(&path).ok();
+}
+
+#[tokio::test]
+async fn messages_in_both_directions() {
+    let path = socket_path("messages");
+    let listener = UnixListener::bind(&path, SessionConfig::default()).unwrap();
+
+    let connector = UnixConnector::new(&path);
+    let (server, client) = tokio::join!(listener.accept(), connector.connect());
+    let (mut server, mut client) = (server.unwrap(), client.unwrap());
+
+    client.send(RawData::Text("hello".into())).await.unwrap();
+    client.send(RawData::Binary(vec![0; 100_000])).await.unwrap();
+    assert_eq!(server.recv().await, Some(SessionEvent::Message(RawData::Text("hello".into()))));
+    assert_eq!(server.recv().await, Some(SessionEvent::Message(RawData::Binary(vec![0; 100_000]))));
+
+    server.send(RawData::Binary(vec![1, 2, 3])).await.unwrap();
+    assert_eq!(client.recv().await, Some(SessionEvent::Message(RawData::Binary(vec![1, 2, 3]))));
+    std::fs::remove_file(&path).ok();
+}
+
+#[tokio::test]
+async fn dropped_client_closes_server_session() {
+    let path = socket_path("dropped");
+    let listener = UnixListener::bind(&path, SessionConfig::default()).unwrap();
+
+    let connector = UnixConnector::new(&path);
+    let (server, client) = tokio::join!(listener.accept(), connector.connect());
+    let mut server = server.unwrap();
+    drop(client);
+
+    assert_eq!(server.recv().await, Some(SessionEvent::Close(CloseReason::TransportClose)));
+    std::fs::remove_file(&path).ok();
+}
+
+#[tokio::test]
+async fn connect_without_listener_fails() {
+    let path = socket_path("missing");
+    assert!(UnixConnector::new(&path).connect().await.is_err());
+}
+
+#[tokio::test]
+async fn sessions_upgrade_onto_a_socket() {
+    let config = SessionConfig::default().with_upgrades(vec!["unix".into()]);
+    let (server, client) = MemoryTransport::pair();
+    let (server, client) = tokio::join!(Session::accept(server, &config), Session::connect(client));
+    let (mut server, mut client) = (server.unwrap(), client.unwrap());
+    server.send(RawData::Text("before".into())).await.unwrap();
+    client.send(RawData::Text("before".into())).await.unwrap();
+
+    #[cfg(feature = "tokio")]
+    let (server_stream, client_stream) = tokio::net::UnixStream::pair().unwrap();
+    #[cfg(not(feature = "tokio"))]
+    let (server_stream, client_stream) = smol::net::unix::UnixStream::pair().unwrap();
+    let (upgraded_server, upgraded_client) = tokio::join!(
+        server.upgrade(UnixTransport::new(server_stream)),
+        client.upgrade(UnixTransport::new(client_stream)),
+    );
+    upgraded_server.unwrap();
+    upgraded_client.unwrap();
+    assert_eq!((server.transport(), client.transport()), ("unix", "unix"));
+
+    server.send(RawData::Text("after".into())).await.unwrap();
+    client.send(RawData::Text("after".into())).await.unwrap();
+    for session in [&mut server, &mut client] {
+        assert_eq!(session.recv().await, Some(SessionEvent::Message(RawData::Text("before".into()))));
+        assert_eq!(session.recv().await, Some(SessionEvent::Message(RawData::Text("after".into()))));
+    }
+}
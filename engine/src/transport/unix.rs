@@ -0,0 +1,92 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[cfg(all(feature = "smol", not(feature = "tokio")))]
+use smol::net::unix::{UnixListener as Listener, UnixStream};
+#[cfg(feature = "tokio")]
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+#[cfg(feature = "tokio")]
+use tokio::net::{UnixListener as Listener, UnixStream};
+#[cfg(feature = "tokio")]
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+use crate::session::{Session, SessionConfig, SessionError};
+use crate::transport::{FramedTransport, TransportError};
+
+/// Engine transport over a Unix domain socket.
+#[cfg(feature = "tokio")]
+pub type UnixTransport = FramedTransport<Compat<OwnedReadHalf>, Compat<OwnedWriteHalf>>;
+/// Engine transport over a Unix domain socket.
+#[cfg(all(feature = "smol", not(feature = "tokio")))]
+pub type UnixTransport = FramedTransport<UnixStream, UnixStream>;
+
+impl UnixTransport {
+    /// Wraps a connected Unix stream from the default runtime.
+    pub fn new(stream: UnixStream) -> Self {
+        #[cfg(feature = "tokio")]
+        let (reader, writer) = {
+            let (reader, writer) = stream.into_split();
+            (reader.compat(), writer.compat_write())
+        };
+        #[cfg(all(feature = "smol", not(feature = "tokio")))]
+        let (reader, writer) = (stream.clone(), stream);
+        Self::from_parts(reader, writer, "unix")
+    }
+}
+
+/// Accepts engine sessions on a Unix domain socket.
+/// Access control is left to the socket file's permissions.
+#[derive(Debug)]
+pub struct UnixListener {
+    listener: Listener,
+    config: SessionConfig,
+}
+
+impl UnixListener {
+    /// Binds a listener to the given socket path.
+    pub fn bind<P: AsRef<Path>>(path: P, config: SessionConfig) -> io::Result<Self> {
+        Ok(Self {
+            listener: Listener::bind(path)?,
+            config,
+        })
+    }
+
+    /// Returns the settings advertised to accepted sessions.
+    pub fn config(&self) -> &SessionConfig {
+        &self.config
+    }
+
+    /// Waits for a connection and completes the server side of the handshake.
+    pub async fn accept(&self) -> Result<Session, SessionError> {
+        let (stream, _) = self.listener.accept().await
+            .map_err(TransportError::from)?;
+        Session::accept(UnixTransport::new(stream), &self.config).await
+    }
+}
+
+/// Opens engine sessions to a Unix domain socket listener.
+#[derive(Debug, Clone)]
+pub struct UnixConnector {
+    path: PathBuf,
+}
+
+impl UnixConnector {
+    /// Creates a connector for the given socket path.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the socket path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Connects and completes the client side of the handshake.
+    pub async fn connect(&self) -> Result<Session, SessionError> {
+        let stream = UnixStream::connect(&self.path).await
+            .map_err(TransportError::from)?;
+        Session::connect(UnixTransport::new(stream)).await
+    }
+}
@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use green_engine::protocol::{Packet, PacketType};
+use http::StatusCode;
+
+use crate::server::{polling, TestServer};
+
+#[tokio::test]
+async fn client_close_ends_the_pending_poll() {
+    let server = TestServer::start().await;
+    let sid = server.open().await;
+
+    let query = polling(&sid);
+    let (poll, close) = tokio::join!(server.get(&query), async {
+        // Let the poll reach the server first.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        server.send(&query, vec![Packet::new(PacketType::Close)]).await
+    });
+    assert_eq!(close.status, StatusCode::OK);
+    assert_eq!(poll.types(), [PacketType::Noop]);
+
+    let reply = server.get(&query).await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+    assert_eq!(reply.error_code(), 1);
+}
+
+#[tokio::test]
+async fn server_close_sends_a_close_packet() {
+    let server = TestServer::start().await;
+    let sid = server.open().await;
+    let session = server.engine().accept().await.unwrap();
+
+    session.close();
+    assert_eq!(server.get(&polling(&sid)).await.types(), [PacketType::Close]);
+
+    let reply = server.get(&polling(&sid)).await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+    assert_eq!(reply.error_code(), 1);
+}
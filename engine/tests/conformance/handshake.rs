@@ -0,0 +1,76 @@
+use green_engine::protocol::{Handshake, PacketType, RawData};
+use http::StatusCode;
+use serde_json::{json, Value};
+
+use crate::server::{polling, TestServer, MAX_PAYLOAD, PING_INTERVAL, PING_TIMEOUT};
+
+#[tokio::test]
+async fn opens_a_session() {
+    let server = TestServer::start().await;
+    let reply = server.get("EIO=4&transport=polling").await;
+    assert!(reply.content_type.starts_with("text/plain"));
+
+    let packets = reply.packets();
+    assert_eq!(packets.len(), 1);
+    assert_eq!(packets[0]._type(), &PacketType::Open);
+    let Some(RawData::Text(json)) = packets[0].data() else {
+        panic!("open packet without a JSON handshake");
+    };
+    let handshake: Value = serde_json::from_str(json).unwrap();
+    let mut fields: Vec<&str> = handshake.as_object().unwrap().keys().map(String::as_str).collect();
+    fields.sort_unstable();
+    assert_eq!(fields, ["maxPayload", "pingInterval", "pingTimeout", "sid", "upgrades"]);
+
+    let handshake = Handshake::from_packet(&packets[0]).unwrap();
+    assert!(!handshake.sid().is_empty());
+    assert!(handshake.upgrades().is_empty());
+    assert_eq!(handshake.ping_interval(), PING_INTERVAL);
+    assert_eq!(handshake.ping_timeout(), PING_TIMEOUT);
+    assert_eq!(handshake.max_payload(), MAX_PAYLOAD);
+}
+
+#[tokio::test]
+async fn issues_a_new_sid_per_session() {
+    let server = TestServer::start().await;
+    let first = server.open().await;
+    let second = server.open().await;
+    assert_ne!(first, second);
+}
+
+#[tokio::test]
+async fn rejects_an_invalid_eio() {
+    let server = TestServer::start().await;
+    for query in ["transport=polling", "EIO=abc&transport=polling", "EIO=3&transport=polling"] {
+        let reply = server.get(query).await;
+        assert_eq!(reply.status, StatusCode::BAD_REQUEST, "{}", query);
+        assert_eq!(reply.error_code(), 5, "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn rejects_an_invalid_transport() {
+    let server = TestServer::start().await;
+    for query in ["EIO=4", "EIO=4&transport=abc"] {
+        let reply = server.get(query).await;
+        assert_eq!(reply.status, StatusCode::BAD_REQUEST, "{}", query);
+        assert_eq!(reply.error_code(), 0, "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn rejects_a_handshake_post() {
+    let server = TestServer::start().await;
+    let reply = server.post("EIO=4&transport=polling", "").await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+    assert_eq!(reply.error_code(), 2);
+}
+
+#[tokio::test]
+async fn rejects_an_unknown_sid() {
+    let server = TestServer::start().await;
+    let reply = server.get(&polling("abc")).await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+    assert!(reply.content_type.starts_with("application/json"));
+    let body: Value = serde_json::from_str(&reply.body).unwrap();
+    assert_eq!(body, json!({ "code": 1, "message": "Session ID unknown" }));
+}
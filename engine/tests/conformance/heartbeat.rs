@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use green_engine::protocol::{Packet, PacketType};
+use http::StatusCode;
+
+use crate::server::{polling, TestServer, PING_INTERVAL, PING_TIMEOUT};
+
+#[tokio::test]
+async fn exchanges_pings_and_pongs() {
+    let server = TestServer::start().await;
+    let sid = server.open().await;
+
+    for _ in 0..3 {
+        assert_eq!(server.get(&polling(&sid)).await.types(), [PacketType::Ping]);
+        let reply = server.send(&polling(&sid), vec![Packet::new(PacketType::Pong)]).await;
+        assert_eq!(reply.status, StatusCode::OK);
+        assert_eq!(reply.body, "ok");
+    }
+}
+
+#[tokio::test]
+async fn waits_a_ping_interval_before_pinging() {
+    let server = TestServer::start().await;
+    let sid = server.open().await;
+
+    let started = tokio::time::Instant::now();
+    assert_eq!(server.get(&polling(&sid)).await.types(), [PacketType::Ping]);
+    assert!(started.elapsed() >= Duration::from_millis(PING_INTERVAL - 50));
+}
+
+#[tokio::test]
+async fn closes_the_session_on_ping_timeout() {
+    let server = TestServer::start().await;
+    let sid = server.open().await;
+
+    assert_eq!(server.get(&polling(&sid)).await.types(), [PacketType::Ping]);
+    tokio::time::sleep(Duration::from_millis(PING_TIMEOUT + 100)).await;
+
+    let reply = server.get(&polling(&sid)).await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+    assert_eq!(reply.error_code(), 1);
+}
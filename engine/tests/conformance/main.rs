@@ -0,0 +1,16 @@
+//! Engine.IO protocol conformance, modeled on the cases of the engine.io-protocol test suite.
+//! Every case talks to an `EngineServer` served over loopback HTTP, in the Engine.IO v4 polling encoding:
+//! `<type><data>` packets, base64 binary behind a `b`, joined by `\x1e`. The byte-exact cases live in `wire`.
+//!
+//! Only the polling transport is covered. The engine has no WebSocket transport, so WebSocket framing is untested;
+//! `websocket` only checks that such requests are refused.
+#![cfg(feature = "tokio")]
+
+mod close;
+mod handshake;
+mod heartbeat;
+mod messages;
+mod server;
+mod upgrade;
+mod websocket;
+mod wire;
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use green_engine::protocol::{Packet, RawData};
+use http::StatusCode;
+
+use crate::server::{message, polling, TestServer};
+
+/// Polls until `count` packets have arrived and returns them in order.
+async fn collect(server: &TestServer, sid: &str, count: usize) -> Vec<Packet> {
+    let mut packets = Vec::new();
+    while packets.len() < count {
+        packets.extend(server.get(&polling(sid)).await.packets());
+    }
+    packets
+}
+
+#[tokio::test]
+async fn echoes_a_text_message() {
+    let server = TestServer::echo().await;
+    let sid = server.open().await;
+
+    let reply = server.send(&polling(&sid), vec![message(RawData::Text("hello".into()))]).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(reply.body, "ok");
+    assert_eq!(collect(&server, &sid, 1).await, [message(RawData::Text("hello".into()))]);
+}
+
+#[tokio::test]
+async fn echoes_a_binary_message() {
+    let server = TestServer::echo().await;
+    let sid = server.open().await;
+
+    server.send(&polling(&sid), vec![message(RawData::Binary(vec![1, 2, 3, 4]))]).await;
+    assert_eq!(collect(&server, &sid, 1).await, [message(RawData::Binary(vec![1, 2, 3, 4]))]);
+}
+
+#[tokio::test]
+async fn echoes_a_payload_of_several_messages() {
+    let server = TestServer::echo().await;
+    let sid = server.open().await;
+    let packets = vec![
+        message(RawData::Text("€".into())),
+        message(RawData::Binary(vec![1, 2, 3, 4])),
+        message(RawData::Text("é".into())),
+    ];
+
+    let reply = server.send(&polling(&sid), packets.clone()).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(collect(&server, &sid, 3).await, packets);
+}
+
+#[tokio::test]
+async fn batches_queued_packets_into_one_payload() {
+    let server = TestServer::start().await;
+    let sid = server.open().await;
+    let session = server.engine().accept().await.unwrap();
+
+    session.send(RawData::Text("one".into())).await.unwrap();
+    session.send(RawData::Text("two".into())).await.unwrap();
+    session.send(RawData::Binary(vec![1, 2, 3, 4])).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let reply = server.get(&polling(&sid)).await;
+    assert!(reply.content_type.starts_with("text/plain"));
+    assert_eq!(reply.packets(), [
+        message(RawData::Text("one".into())),
+        message(RawData::Text("two".into())),
+        message(RawData::Binary(vec![1, 2, 3, 4])),
+    ]);
+}
+
+#[tokio::test]
+async fn closes_the_session_on_an_invalid_payload() {
+    let server = TestServer::start().await;
+    let sid = server.open().await;
+
+    let reply = server.post(&polling(&sid), "abc").await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+    let reply = server.get(&polling(&sid)).await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+    assert_eq!(reply.error_code(), 1);
+}
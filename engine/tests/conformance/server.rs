@@ -0,0 +1,184 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use green_engine::protocol::{Handshake, Packet, PacketType, RawData};
+use green_engine::server::EngineServer;
+use green_engine::session::{SessionConfig, SessionEvent};
+use http::{header, Method, Request, StatusCode};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use tokio::net::{TcpListener, TcpStream};
+
+/// Ping interval advertised by the test server, as in the reference suite.
+pub const PING_INTERVAL: u64 = 300;
+/// Ping timeout advertised by the test server, as in the reference suite.
+pub const PING_TIMEOUT: u64 = 200;
+/// Maximum payload advertised by the test server, as in the reference suite.
+pub const MAX_PAYLOAD: u64 = 1_000_000;
+
+/// An `EngineServer` listening on a loopback port.
+pub struct TestServer {
+    addr: SocketAddr,
+    engine: Arc<EngineServer>,
+}
+
+/// An HTTP response, with the parts the protocol pins down.
+#[derive(Debug)]
+pub struct Reply {
+    pub status: StatusCode,
+    pub content_type: String,
+    pub body: String,
+}
+
+impl Reply {
+    /// Returns the engine error code of a rejection.
+    pub fn error_code(&self) -> u64 {
+        let body: serde_json::Value = serde_json::from_str(&self.body).unwrap();
+        body["code"].as_u64().unwrap()
+    }
+
+    /// Decodes the body as an Engine.IO v4 payload.
+    pub fn packets(&self) -> Vec<Packet> {
+        assert_eq!(self.status, StatusCode::OK, "{}", self.body);
+        Packet::decode_payload_v4(&self.body).unwrap()
+    }
+
+    /// Decodes the body as a payload and returns its packet types.
+    pub fn types(&self) -> Vec<PacketType> {
+        self.packets().iter().map(|packet| packet._type().clone()).collect()
+    }
+}
+
+impl TestServer {
+    /// Returns the session settings of the reference suite's server.
+    pub fn config() -> SessionConfig {
+        SessionConfig::default()
+            .with_ping_interval(Duration::from_millis(PING_INTERVAL))
+            .with_ping_timeout(Duration::from_millis(PING_TIMEOUT))
+            .with_max_payload(MAX_PAYLOAD as usize)
+    }
+
+    /// Starts a server whose sessions are left to the test through `engine().accept()`.
+    pub async fn start() -> Self {
+        Self::start_with(Self::config()).await
+    }
+
+    /// Starts a server with `config` whose sessions are left to the test.
+    pub async fn start_with(config: SessionConfig) -> Self {
+        let engine = Arc::new(EngineServer::new(config));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = engine.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let server = server.clone();
+                let service = service_fn(move |request: Request<Incoming>| {
+                    let server = server.clone();
+                    async move {
+                        let (parts, body) = request.into_parts();
+                        let body = body.collect().await?.to_bytes();
+                        let response = server.handle(Request::from_parts(parts, body)).await;
+                        Ok::<_, hyper::Error>(response.map(Full::new))
+                    }
+                });
+                tokio::spawn(hyper::server::conn::http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service));
+            }
+        });
+        Self { addr, engine }
+    }
+
+    /// Starts a server that sends every message it receives back to the client.
+    pub async fn echo() -> Self {
+        Self::echo_with(Self::config()).await
+    }
+
+    /// Starts an echo server with `config`.
+    pub async fn echo_with(config: SessionConfig) -> Self {
+        let server = Self::start_with(config).await;
+        let engine = server.engine.clone();
+        tokio::spawn(async move {
+            while let Some(mut session) = engine.accept().await {
+                tokio::spawn(async move {
+                    while let Some(event) = session.recv().await {
+                        if let SessionEvent::Message(data) = event {
+                            let _ = session.send(data).await;
+                        }
+                    }
+                });
+            }
+        });
+        server
+    }
+
+    /// Returns the engine behind the listener.
+    pub fn engine(&self) -> &EngineServer {
+        &self.engine
+    }
+
+    /// Sends a GET request to `/engine.io/?<query>`.
+    pub async fn get(&self, query: &str) -> Reply {
+        self.request(Method::GET, query, "").await
+    }
+
+    /// Sends a POST request with a text body to `/engine.io/?<query>`.
+    pub async fn post(&self, query: &str, body: &str) -> Reply {
+        self.request(Method::POST, query, body).await
+    }
+
+    /// Sends a POST request carrying `packets` as an Engine.IO v4 payload.
+    pub async fn send(&self, query: &str, packets: Vec<Packet>) -> Reply {
+        self.post(query, &Packet::encode_payload_v4(packets)).await
+    }
+
+    /// Opens a polling session and returns its id.
+    pub async fn open(&self) -> String {
+        let reply = self.get("EIO=4&transport=polling").await;
+        let handshake = Handshake::from_packet(&reply.packets()[0]).unwrap();
+        handshake.sid().to_string()
+    }
+
+    /// Sends one request on its own connection, as a browser's concurrent polls would.
+    async fn request(&self, method: Method, query: &str, body: &str) -> Reply {
+        let stream = TcpStream::connect(self.addr).await.unwrap();
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream)).await.unwrap();
+        tokio::spawn(connection);
+
+        let request = Request::builder()
+            .method(method)
+            .uri(format!("/engine.io/?{}", query))
+            .header(header::HOST, self.addr.to_string())
+            .header(header::CONTENT_TYPE, "text/plain;charset=UTF-8")
+            .body(Full::new(Bytes::from(body.to_string())))
+            .unwrap();
+        let response = sender.send_request(request).await.unwrap();
+        let status = response.status();
+        let content_type = response.headers()
+            .get(header::CONTENT_TYPE)
+            .map(|value| value.to_str().unwrap().to_string())
+            .unwrap_or_default();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        Reply {
+            status,
+            content_type,
+            body: String::from_utf8(body.to_vec()).unwrap(),
+        }
+    }
+}
+
+/// Returns the polling query for an open session.
+pub fn polling(sid: &str) -> String {
+    format!("EIO=4&transport=polling&sid={}", sid)
+}
+
+/// Builds a `Message` packet.
+pub fn message(data: RawData) -> Packet {
+    let mut packet = Packet::new(PacketType::Message);
+    packet.with_data(data).unwrap();
+    packet
+}
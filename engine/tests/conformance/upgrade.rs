@@ -0,0 +1,67 @@
+//! The upgrade sequence. The engine has no WebSocket transport, so the session moves onto an in-process transport,
+//! driven through the same probe a WebSocket client sends: `2probe`, `3probe`, then `5`.
+
+use futures::{SinkExt, StreamExt};
+use green_engine::protocol::{Packet, PacketType, RawData};
+use green_engine::transport::memory::MemoryTransport;
+use http::StatusCode;
+
+use crate::server::{message, polling, TestServer};
+
+fn probe(packet_type: PacketType) -> Packet {
+    let mut packet = Packet::new(packet_type);
+    packet.with_data(RawData::Text("probe".into())).unwrap();
+    packet
+}
+
+#[tokio::test]
+async fn upgrades_from_polling() {
+    let server = TestServer::echo_with(TestServer::config().with_upgrades(vec!["memory".into()])).await;
+    let reply = server.get("EIO=4&transport=polling").await;
+    let open: serde_json::Value = match reply.packets()[0].data() {
+        Some(RawData::Text(json)) => serde_json::from_str(json).unwrap(),
+        other => panic!("Expected a JSON handshake, got {:?}", other),
+    };
+    assert_eq!(open["upgrades"], serde_json::json!(["memory"]));
+    let sid = open["sid"].as_str().unwrap().to_string();
+
+    let (transport, mut client) = MemoryTransport::pair();
+    let upgrade = async {
+        client.send(probe(PacketType::Ping).into()).await.unwrap();
+        assert_eq!(client.next().await.unwrap().unwrap(), probe(PacketType::Pong));
+        client.send(Packet::new(PacketType::Upgrade).into()).await.unwrap();
+    };
+    // The poll waiting while the client upgrades is released with a noop.
+    let query = polling(&sid);
+    let (pending, upgraded, ()) = tokio::join!(
+        server.get(&query),
+        server.engine().upgrade(&sid, transport),
+        upgrade,
+    );
+    upgraded.unwrap();
+    assert_eq!(pending.types(), vec![PacketType::Noop]);
+
+    client.send(message(RawData::Text("upgraded".into())).into()).await.unwrap();
+    assert_eq!(client.next().await.unwrap().unwrap(), message(RawData::Text("upgraded".into())));
+}
+
+#[tokio::test]
+async fn ignores_polling_after_an_upgrade() {
+    let server = TestServer::start_with(TestServer::config().with_upgrades(vec!["memory".into()])).await;
+    let sid = server.open().await;
+
+    let (transport, mut client) = MemoryTransport::pair();
+    let upgrade = async {
+        client.send(probe(PacketType::Ping).into()).await.unwrap();
+        client.next().await.unwrap().unwrap();
+        client.send(Packet::new(PacketType::Upgrade).into()).await.unwrap();
+    };
+    let (upgraded, ()) = tokio::join!(server.engine().upgrade(&sid, transport), upgrade);
+    upgraded.unwrap();
+
+    // Drain the noop left for a poll that never came.
+    server.get(&polling(&sid)).await;
+    let reply = server.get(&polling(&sid)).await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+    assert_eq!(reply.error_code(), 1);
+}
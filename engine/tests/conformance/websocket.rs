@@ -0,0 +1,28 @@
+//! The engine has no WebSocket transport yet, so WebSocket framing is not covered here.
+//! Handshakes advertise no upgrades, and WebSocket requests are refused the way the reference server refuses
+//! unknown transports.
+
+use green_engine::protocol::{Packet, PacketType};
+use http::StatusCode;
+
+use crate::server::{polling, TestServer};
+
+#[tokio::test]
+async fn refuses_a_websocket_handshake() {
+    let server = TestServer::start().await;
+    let reply = server.get("EIO=4&transport=websocket").await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+    assert_eq!(reply.error_code(), 0);
+}
+
+#[tokio::test]
+async fn refuses_an_upgrade_probe() {
+    let server = TestServer::start().await;
+    let sid = server.open().await;
+
+    let reply = server.get(&format!("EIO=4&transport=websocket&sid={}", sid)).await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+    assert_eq!(reply.error_code(), 0);
+    // The polling session is unaffected.
+    assert_eq!(server.send(&polling(&sid), vec![Packet::new(PacketType::Pong)]).await.body, "ok");
+}
@@ -0,0 +1,63 @@
+//! Byte-exact cases from the reference suite, written as a v4 client sends and reads them.
+
+use std::time::Duration;
+
+use http::StatusCode;
+
+use crate::server::{polling, TestServer};
+
+#[tokio::test]
+async fn handshake_is_an_open_packet_with_json() {
+    let server = TestServer::start().await;
+    let reply = server.get("EIO=4&transport=polling").await;
+    assert_eq!(reply.status, StatusCode::OK);
+    let open = reply.body.strip_prefix('0').expect("an open packet");
+    assert!(serde_json::from_str::<serde_json::Value>(open).is_ok());
+}
+
+#[tokio::test]
+async fn ping_and_pong_are_single_characters() {
+    let server = TestServer::start().await;
+    let sid = server.open().await;
+    assert_eq!(server.get(&polling(&sid)).await.body, "2");
+    assert_eq!(server.post(&polling(&sid), "3").await.body, "ok");
+}
+
+#[tokio::test]
+async fn messages_are_prefixed_with_their_type() {
+    let server = TestServer::echo().await;
+    let sid = server.open().await;
+    assert_eq!(server.post(&polling(&sid), "4hello").await.body, "ok");
+    assert_eq!(server.get(&polling(&sid)).await.body, "4hello");
+}
+
+#[tokio::test]
+async fn binary_messages_are_base64_with_a_b_prefix() {
+    let server = TestServer::echo().await;
+    let sid = server.open().await;
+    assert_eq!(server.post(&polling(&sid), "bAQIDBA==").await.body, "ok");
+    assert_eq!(server.get(&polling(&sid)).await.body, "bAQIDBA==");
+}
+
+#[tokio::test]
+async fn payload_packets_are_separated_by_record_separators() {
+    let server = TestServer::echo().await;
+    let sid = server.open().await;
+    assert_eq!(server.post(&polling(&sid), "4€\x1ebAQIDBA==\x1e4é").await.body, "ok");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(server.get(&polling(&sid)).await.body, "4€\x1ebAQIDBA==\x1e4é");
+}
+
+#[tokio::test]
+async fn client_close_is_a_single_character() {
+    let server = TestServer::start().await;
+    let sid = server.open().await;
+
+    let query = polling(&sid);
+    let (poll, close) = tokio::join!(server.get(&query), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        server.post(&query, "1").await
+    });
+    assert_eq!(close.body, "ok");
+    assert_eq!(poll.body, "6");
+}